@@ -12,6 +12,8 @@ use gba2k::{
   },
 };
 
+gba2k::gba_header!(title = "BASIC PROG", code = "ABPE");
+
 pub static THE_COLOR: GbaCell<Color> = GbaCell::new(Color::WHITE);
 
 #[no_mangle]
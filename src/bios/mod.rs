@@ -32,6 +32,9 @@
 mod x02;
 pub use x02::*;
 
+mod x03;
+pub use x03::*;
+
 mod x04;
 pub use x04::*;
 
@@ -41,4 +44,7 @@ pub use x05::*;
 mod x10;
 pub use x10::*;
 
+mod x25;
+pub use x25::*;
+
 // Note(Lokathor): 0x2A is the highest SWI on the GBA.
@@ -0,0 +1,36 @@
+/// `swi #0x03`: Stops the CPU, and most of the rest of the GBA as well.
+///
+/// This is the lowest power state the GBA has. The CPU, video, sound, timers,
+/// and serial port all stop running. Only the following interrupts can wake
+/// the GBA back up:
+/// * Keypad (see [`KEYCNT`](crate::keys::KEYCNT))
+/// * Game Pak (the cartridge being removed)
+/// * Serial (only when using an external clock)
+///
+/// The interrupt that you want to wake the GBA must be set in
+/// [`IE`](crate::interrupts::IE), but [`IME`](crate::interrupts::IME) does not
+/// need to be enabled.
+///
+/// Before you use this function you should turn off the display with
+/// [forced blank](crate::video::DisplayControl::with_forced_blank) and either
+/// turn off the sound with [`SOUNDCNT_X`](crate::sound::SOUNDCNT_X) or take
+/// every channel off of the speakers with
+/// [`SOUNDCNT_L`](crate::sound::SOUNDCNT_L) and
+/// [`SOUNDCNT_H`](crate::sound::SOUNDCNT_H), otherwise the screen and speaker
+/// will be left in a junk state while the GBA is stopped.
+/// The [`sleep_until_keys`](crate::keys::sleep_until_keys) function handles
+/// all of this for you.
+#[inline]
+#[instruction_set(arm::t32)]
+pub fn Stop() {
+  unsafe {
+    core::arch::asm! {
+      "swi #0x03",
+      out("r0") _,
+      out("r1") _,
+      out("r2") _,
+      out("r3") _,
+      options(preserves_flags)
+    }
+  }
+}
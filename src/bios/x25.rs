@@ -0,0 +1,118 @@
+/// The parameters that the [MultiBoot] BIOS function uses.
+///
+/// The BIOS only reads a few of these fields, the rest are working space.
+/// Usually you'd let [`send_multiboot`](crate::serial::send_multiboot) fill
+/// this in for you.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+#[allow(missing_docs)]
+pub struct MultiBootParam {
+  pub reserved1: [u32; 5],
+  /// The final handshake value that was sent to the clients.
+  pub handshake_data: u8,
+  pub padding: u8,
+  pub handshake_timeout: u16,
+  pub probe_count: u8,
+  /// The random byte from each client, or `0xFF` for no client.
+  pub client_data: [u8; 3],
+  /// The palette value that was sent to the clients.
+  pub palette_data: u8,
+  pub response_bit: u8,
+  /// Which clients (bits 1 to 3) are being sent to.
+  pub client_bit: u8,
+  pub reserved2: u8,
+  /// The start of the data to send, just after the header.
+  pub boot_srcp: *const u8,
+  /// The end of the data to send.
+  pub boot_endp: *const u8,
+  pub masterp: *const u8,
+  pub reserved3: [*const u8; 3],
+  pub system_work2: [u32; 4],
+  pub sendflag: u8,
+  pub probe_target_bit: u8,
+  pub check_wait: u8,
+  pub server_type: u8,
+}
+
+impl Default for MultiBootParam {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MultiBootParam {
+  /// Makes a value with all fields zeroed.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      reserved1: [0; 5],
+      handshake_data: 0,
+      padding: 0,
+      handshake_timeout: 0,
+      probe_count: 0,
+      client_data: [0; 3],
+      palette_data: 0,
+      response_bit: 0,
+      client_bit: 0,
+      reserved2: 0,
+      boot_srcp: core::ptr::null(),
+      boot_endp: core::ptr::null(),
+      masterp: core::ptr::null(),
+      reserved3: [core::ptr::null(); 3],
+      system_work2: [0; 4],
+      sendflag: 0,
+      probe_target_bit: 0,
+      check_wait: 0,
+      server_type: 0,
+    }
+  }
+}
+
+/// The serial transfer mode that [MultiBoot] uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum MultiBootMode {
+  /// Normal mode, 32-bit transfers at 256KHz. Only one client.
+  Normal256K = 0,
+  /// Multiplayer mode, with up to three clients.
+  Multiplayer = 1,
+  /// Normal mode, 32-bit transfers at 2MHz. Only one client.
+  Normal2M = 2,
+}
+
+/// `swi #0x25`: Sends a multiboot program to other GBAs over the link cable.
+///
+/// This is only the final part of a multiboot transfer. The header must have
+/// already been sent, and the handshake done, with the results put into
+/// `param`. The [`send_multiboot`](crate::serial::send_multiboot) function
+/// does all of the steps for you.
+///
+/// The BIOS uses the rest of `param` as working space, so it's written to.
+///
+/// The BIOS doesn't return until the transfer is done, which can take a few
+/// seconds.
+///
+/// **Returns:** If the transfer succeeded.
+///
+/// ## Safety
+/// * `param.boot_srcp` to `param.boot_endp` must be readable memory.
+/// * `param.boot_srcp` must be aligned to 4.
+#[inline]
+#[instruction_set(arm::t32)]
+pub unsafe fn MultiBoot(
+  param: &mut MultiBootParam, mode: MultiBootMode,
+) -> bool {
+  let failed: u32;
+  core::arch::asm! {
+    "swi #0x25",
+    inout("r0") param as *mut MultiBootParam => failed,
+    inout("r1") mode as u32 => _,
+    out("r2") _,
+    out("r3") _,
+    options(preserves_flags)
+  }
+  failed == 0
+}
@@ -0,0 +1,264 @@
+#![warn(missing_docs)]
+
+//! Cooperative coroutines, each with their own stack.
+//!
+//! Some code, such as cutscenes and scripted events, is most easily written as
+//! straight-line code that pauses once per frame. A [Coroutine] runs a function
+//! on its own stack, and that function can call [yield_now] at any point to
+//! pause and return control to whoever resumed it. The next time that the
+//! coroutine is resumed it picks up right where it left off.
+//!
+//! ```no_run
+//! # use gba2k::coroutine::*;
+//! static mut CUTSCENE_STACK: [u32; 256] = [0; 256];
+//!
+//! extern "C" fn cutscene(_arg: usize) {
+//!   for _frame in 0..60 {
+//!     // move an actor a little
+//!     yield_now();
+//!   }
+//!   // show some text, and so on
+//! }
+//!
+//! let stack = unsafe { &mut *core::ptr::addr_of_mut!(CUTSCENE_STACK) };
+//! let mut scheduler = Scheduler::<4>::new();
+//! scheduler.spawn(Coroutine::new(stack, cutscene, 0)).unwrap();
+//! scheduler.run_each_vblank();
+//! ```
+//!
+//! ## Stacks
+//!
+//! Each coroutine's stack is a `&'static mut [u32]`, usually a `static mut`
+//! array. A `static mut` array of zeroes goes in IWRAM by default, which is
+//! the fastest memory. Coroutines that don't need the speed can have their
+//! stack placed elsewhere with `#[link_section]`.
+//!
+//! When a coroutine is made, its whole stack is filled with the
+//! [STACK_CANARY] value. After each time the coroutine is resumed, the
+//! lowest word of the stack is checked, and if it's been overwritten then the
+//! stack has overflowed and the program panics. You can also check how much of
+//! the stack has ever been used with
+//! [`stack_high_water_mark`](Coroutine::stack_high_water_mark), which is handy
+//! for picking a stack size.
+//!
+//! Interrupts that happen while a coroutine runs are handled on that
+//! coroutine's stack: the rt0's interrupt handler pushes 32 bytes, and then
+//! the Rust interrupt handler's own stack frame goes below that. If you allow
+//! nested interrupts, each level of nesting adds that much again. Include
+//! this in every coroutine's stack size.
+//!
+//! Stack overflow can only be detected *after* it has happened, so whatever
+//! memory is just below the stack may have been damaged by then. Leaving
+//! plenty of extra room in each stack is strongly advised.
+
+use crate::{
+  bios::VBlankIntrWait,
+  interrupts::GbaCell,
+  rt0::{rt0_context_switch, rt0_coroutine_start},
+};
+
+pub use crate::rt0::STACK_CANARY;
+
+/// The stack pointer of the code that resumed the current coroutine.
+static RESUMER_SP: GbaCell<u32> = GbaCell::new(0);
+
+/// The stack pointer of the coroutine that most recently paused.
+static COROUTINE_SP: GbaCell<u32> = GbaCell::new(0);
+
+/// If a coroutine is currently running.
+static IN_COROUTINE: GbaCell<bool> = GbaCell::new(false);
+
+/// If the coroutine that most recently paused has finished.
+static COROUTINE_FINISHED: GbaCell<bool> = GbaCell::new(false);
+
+/// The number of words that a coroutine's context switch saves on its stack.
+const SAVED_WORDS: usize = 10;
+
+/// A function running on its own stack, which can pause itself.
+#[derive(Debug)]
+pub struct Coroutine {
+  /// The lowest address of the stack. Once the coroutine is made, the stack is
+  /// only accessed through this pointer (and by the coroutine itself).
+  stack: *mut u32,
+  stack_words: usize,
+  sp: u32,
+  finished: bool,
+}
+
+impl Coroutine {
+  /// Makes a new coroutine, which will call `entry(arg)` when first resumed.
+  ///
+  /// The coroutine hasn't started running yet when this returns. If `entry`
+  /// returns then the coroutine is finished.
+  ///
+  /// The stack also has to have room for the interrupt handler (see the
+  /// [module docs](self#stacks)).
+  ///
+  /// ## Panics
+  /// * The stack must be at least 32 words.
+  #[must_use]
+  pub fn new(
+    stack: &'static mut [u32], entry: extern "C" fn(usize), arg: usize,
+  ) -> Self {
+    assert!(stack.len() >= 32);
+    stack.fill(STACK_CANARY);
+    // The stack pointer must stay 8-byte aligned at all public interfaces.
+    let stack_base = stack.as_mut_ptr() as usize;
+    let top_word = ((stack_base + stack.len() * 4) & !7) - stack_base;
+    let frame = &mut stack[top_word / 4 - SAVED_WORDS..top_word / 4];
+    // This is the order that `rt0_context_switch` pops: r3 to r11, then lr.
+    frame.fill(0);
+    frame[1] = entry as usize as u32;
+    frame[2] = arg as u32;
+    frame[SAVED_WORDS - 1] =
+      rt0_coroutine_start as unsafe extern "C" fn() as usize as u32;
+    let sp = frame.as_ptr() as u32;
+    let stack_words = stack.len();
+    Self { stack: stack.as_mut_ptr(), stack_words, sp, finished: false }
+  }
+
+  /// If the coroutine's function has returned.
+  #[inline]
+  #[must_use]
+  pub const fn is_finished(&self) -> bool {
+    self.finished
+  }
+
+  /// Runs the coroutine until it calls [yield_now] or finishes.
+  ///
+  /// If the coroutine is already finished this does nothing.
+  ///
+  /// **Returns:** If the coroutine is finished.
+  ///
+  /// ## Panics
+  /// * Coroutines can't resume other coroutines, so this can't be called from
+  ///   within a coroutine.
+  /// * If the stack canary was overwritten, meaning that the coroutine
+  ///   overflowed its stack.
+  pub fn resume(&mut self) -> bool {
+    if self.finished {
+      return true;
+    }
+    assert!(!IN_COROUTINE.read(), "coroutines can't resume other coroutines");
+    IN_COROUTINE.write(true);
+    COROUTINE_FINISHED.write(false);
+    unsafe { rt0_context_switch(RESUMER_SP.get(), self.sp) };
+    IN_COROUTINE.write(false);
+    self.sp = COROUTINE_SP.read();
+    self.finished = COROUTINE_FINISHED.read();
+    let canary = unsafe { self.stack.read_volatile() };
+    assert_eq!(canary, STACK_CANARY, "coroutine stack overflow");
+    self.finished
+  }
+
+  /// The size of the coroutine's stack, in bytes.
+  #[inline]
+  #[must_use]
+  pub fn stack_size(&self) -> usize {
+    self.stack_words * 4
+  }
+
+  /// The most stack space the coroutine has ever used, in bytes.
+  ///
+  /// This counts the stack words that don't hold the [STACK_CANARY] value
+  /// anymore, starting from the bottom of the stack. It's possible (but
+  /// unlikely) for a coroutine to write the canary value itself, which would
+  /// make this a slight undercount.
+  #[must_use]
+  pub fn stack_high_water_mark(&self) -> usize {
+    let untouched = (0..self.stack_words)
+      .take_while(
+        |&i| unsafe { self.stack.add(i).read_volatile() } == STACK_CANARY,
+      )
+      .count();
+    (self.stack_words - untouched) * 4
+  }
+}
+
+/// Pauses the current coroutine, returning control to whoever resumed it.
+///
+/// ## Panics
+/// * This must be called from within a coroutine.
+pub fn yield_now() {
+  assert!(IN_COROUTINE.read(), "yield_now called outside of a coroutine");
+  unsafe { rt0_context_switch(COROUTINE_SP.get(), RESUMER_SP.read()) };
+}
+
+/// When a coroutine's entry fn returns, the rt0 code calls this.
+#[no_mangle]
+extern "C" fn rt0_coroutine_finished() -> ! {
+  COROUTINE_FINISHED.write(true);
+  unsafe { rt0_context_switch(COROUTINE_SP.get(), RESUMER_SP.read()) };
+  unreachable!("a finished coroutine was resumed")
+}
+
+/// Holds up to `N` coroutines, and resumes them in turn.
+#[derive(Debug)]
+pub struct Scheduler<const N: usize> {
+  coroutines: [Option<Coroutine>; N],
+}
+
+impl<const N: usize> Default for Scheduler<N> {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<const N: usize> Scheduler<N> {
+  /// Makes a new scheduler with no coroutines.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { coroutines: [const { None }; N] }
+  }
+
+  /// Adds a coroutine to the first open spot in the scheduler.
+  ///
+  /// **Returns:** The index of the coroutine within the scheduler.
+  ///
+  /// ## Failure
+  /// * If the scheduler is full you get the coroutine back as an error.
+  pub fn spawn(&mut self, coroutine: Coroutine) -> Result<usize, Coroutine> {
+    match self.coroutines.iter().position(Option::is_none) {
+      Some(i) => {
+        self.coroutines[i] = Some(coroutine);
+        Ok(i)
+      }
+      None => Err(coroutine),
+    }
+  }
+
+  /// Gets the coroutine at an index, if there is one.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, i: usize) -> Option<&Coroutine> {
+    self.coroutines.get(i)?.as_ref()
+  }
+
+  /// Removes the coroutine at an index, if there is one.
+  ///
+  /// This frees up the spot for another coroutine to be spawned.
+  #[inline]
+  pub fn remove(&mut self, i: usize) -> Option<Coroutine> {
+    self.coroutines.get_mut(i)?.take()
+  }
+
+  /// Resumes each coroutine that isn't finished, once each, in index order.
+  pub fn resume_all(&mut self) {
+    for coroutine in self.coroutines.iter_mut().flatten() {
+      coroutine.resume();
+    }
+  }
+
+  /// Forever: waits for v-blank, then resumes all coroutines.
+  ///
+  /// This uses [VBlankIntrWait], so the v-blank interrupt must be enabled.
+  pub fn run_each_vblank(&mut self) -> ! {
+    loop {
+      VBlankIntrWait();
+      self.resume_all();
+    }
+  }
+}
@@ -0,0 +1,152 @@
+use core::{
+  future::Future,
+  pin::Pin,
+  task::{Context, Poll},
+};
+
+use crate::{
+  interrupts::IrqSource,
+  keys::{KeyMask, KEYINPUT},
+};
+
+use super::{irq_count, wait_on_irq};
+
+/// A future that waits for an interrupt.
+///
+/// The future is ready once the executor has seen the interrupt source occur
+/// at least once since the future was first polled. These futures only work
+/// within the executor's [run](super::run) function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IrqFuture {
+  source: IrqSource,
+  start: Option<u16>,
+}
+
+impl IrqFuture {
+  /// Makes a future that waits for the interrupt source given.
+  #[inline]
+  #[must_use]
+  pub const fn new(source: IrqSource) -> Self {
+    Self { source, start: None }
+  }
+}
+
+impl Future for IrqFuture {
+  type Output = ();
+  #[inline]
+  fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+    let count = irq_count(self.source);
+    match self.start {
+      Some(start) if start != count => Poll::Ready(()),
+      _ => {
+        self.start.get_or_insert(count);
+        wait_on_irq(self.source);
+        Poll::Pending
+      }
+    }
+  }
+}
+
+/// Waits for the next v-blank interrupt.
+#[inline]
+#[must_use]
+pub const fn vblank() -> IrqFuture {
+  IrqFuture::new(IrqSource::VBlank)
+}
+
+/// Waits for the next interrupt from timer `n`.
+///
+/// ## Panics
+/// * `n` must be less than 4.
+#[inline]
+#[must_use]
+pub const fn timer(n: usize) -> IrqFuture {
+  IrqFuture::new(match n {
+    0 => IrqSource::Timer0,
+    1 => IrqSource::Timer1,
+    2 => IrqSource::Timer2,
+    3 => IrqSource::Timer3,
+    _ => panic!("timer index must be less than 4"),
+  })
+}
+
+/// Waits for DMA channel `ch` to finish a transfer.
+///
+/// ## Panics
+/// * `ch` must be less than 4.
+#[inline]
+#[must_use]
+pub const fn dma_done(ch: usize) -> IrqFuture {
+  IrqFuture::new(match ch {
+    0 => IrqSource::Dma0,
+    1 => IrqSource::Dma1,
+    2 => IrqSource::Dma2,
+    3 => IrqSource::Dma3,
+    _ => panic!("DMA channel must be less than 4"),
+  })
+}
+
+/// A future that waits for any of a set of keys to be pressed.
+///
+/// The keys are checked when the future is first polled, and then once per
+/// v-blank after that, so this needs the v-blank interrupt to be enabled.
+///
+/// **Output:** The keys of the set that were pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPressed {
+  keys: KeyMask,
+  vblank: IrqFuture,
+}
+
+impl Future for KeyPressed {
+  type Output = KeyMask;
+  #[inline]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<KeyMask> {
+    loop {
+      let pressed = KEYINPUT.read().pressed().intersection(self.keys);
+      if !pressed.is_empty() {
+        return Poll::Ready(pressed);
+      }
+      match Pin::new(&mut self.vblank).poll(cx) {
+        // A new frame started, so we start waiting on the *next* frame and
+        // check the keys again.
+        Poll::Ready(()) => self.vblank = vblank(),
+        Poll::Pending => return Poll::Pending,
+      }
+    }
+  }
+}
+
+/// Waits until any key of `keys` is pressed.
+///
+/// See [KeyPressed].
+#[inline]
+#[must_use]
+pub const fn key_pressed(keys: KeyMask) -> KeyPressed {
+  KeyPressed { keys, vblank: vblank() }
+}
+
+/// A future that lets other tasks run before this task continues.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+  type Output = ();
+  #[inline]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    if self.0 {
+      Poll::Ready(())
+    } else {
+      self.0 = true;
+      cx.waker().wake_by_ref();
+      Poll::Pending
+    }
+  }
+}
+
+/// Lets all other ready tasks run before this task continues.
+#[inline]
+#[must_use]
+pub const fn yield_now() -> YieldNow {
+  YieldNow(false)
+}
@@ -0,0 +1,166 @@
+#![warn(missing_docs)]
+
+//! A tiny async executor that's driven by hardware interrupts.
+//!
+//! Game logic that waits on things (the next frame, a timer, a key press) is
+//! often easier to write as `async` code than as a hand-written state machine.
+//! This module lets you do that without any allocation.
+//!
+//! * Each task is a future that you create and pin yourself, usually right in
+//!   `main` (which never returns, so the tasks live for the whole program).
+//! * You pass all the tasks to [run], which polls them forever.
+//! * Futures such as [vblank] and [timer] wait on an interrupt. When that
+//!   interrupt occurs, only the tasks waiting on that [IrqSource] are woken.
+//! * When no task is ready to run, the executor calls [`Halt`] so that the CPU
+//!   sleeps until the next interrupt.
+//!
+//! ```no_run
+//! # use gba2k::executor::*;
+//! # use core::pin::pin;
+//! async fn game_loop() {
+//!   loop {
+//!     vblank().await;
+//!     // update the game
+//!   }
+//! }
+//! async fn music() {
+//!   loop {
+//!     timer(0).await;
+//!     // update the music
+//!   }
+//! }
+//! let mut a = pin!(game_loop());
+//! let mut b = pin!(music());
+//! run(&mut [a.as_mut(), b.as_mut()]);
+//! ```
+//!
+//! The rt0 interrupt handler records every interrupt that occurs, and the
+//! executor reads those records, so this works with whatever interrupt
+//! handler(s) you've set (including none at all). However, you still need to
+//! set up the hardware to send the interrupts you wait on, including setting
+//! [`IE`](crate::interrupts::IE) and [`IME`](crate::interrupts::IME).
+
+use core::{
+  future::Future,
+  pin::Pin,
+  task::{Context, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{
+  bios::Halt,
+  interrupts::{free, GbaCell, IrqBits, IrqSource},
+  rt0::RUST_IRQ_WAKE_FLAGS,
+};
+
+mod futures;
+pub use futures::*;
+
+/// The most tasks that [run] can handle at once.
+pub const MAX_TASKS: usize = 32;
+
+/// Bit set of tasks that should be polled.
+static READY: GbaCell<u32> = GbaCell::new(0);
+
+/// The index of the task being polled right now.
+static CURRENT_TASK: GbaCell<u8> = GbaCell::new(0);
+
+/// For each interrupt source, the bit set of tasks waiting on it.
+static IRQ_WAITERS: [GbaCell<u32>; 14] = [const { GbaCell::new(0) }; 14];
+
+/// For each interrupt source, how many times the executor has seen it occur.
+static IRQ_COUNTS: [GbaCell<u16>; 14] = [const { GbaCell::new(0) }; 14];
+
+/// Runs the tasks given, forever.
+///
+/// Tasks are polled in order, and each task is only polled again once it's
+/// been woken. Any task that completes is never polled again. If every task
+/// completes, the CPU just halts forever.
+///
+/// ## Panics
+/// * There can't be more than [MAX_TASKS] tasks.
+pub fn run(tasks: &mut [Pin<&mut dyn Future<Output = ()>>]) -> ! {
+  assert!(tasks.len() <= MAX_TASKS);
+  let all_tasks =
+    u32::MAX.checked_shr((MAX_TASKS - tasks.len()) as u32).unwrap_or(0);
+  let mut done = 0_u32;
+  READY.write(all_tasks);
+  loop {
+    wake_irq_waiters();
+    let mut ready = READY.replace(0) & !done;
+    while ready != 0 {
+      let i = ready.trailing_zeros() as usize;
+      ready &= !(1 << i);
+      CURRENT_TASK.write(i as u8);
+      let waker = task_waker(i);
+      let mut cx = Context::from_waker(&waker);
+      if tasks[i].as_mut().poll(&mut cx).is_ready() {
+        done |= 1 << i;
+      }
+    }
+    if done == all_tasks {
+      loop {
+        Halt();
+      }
+    }
+    // With `IME` off, an interrupt can't slip in between the check and the
+    // `Halt`, but `Halt` will still wake up when the interrupt occurs.
+    free(|_| {
+      let irq_pending = unsafe { RUST_IRQ_WAKE_FLAGS.read() } != IrqBits::new();
+      if READY.read() & !done == 0 && !irq_pending {
+        Halt();
+      }
+    });
+  }
+}
+
+/// Marks all tasks waiting on any interrupt that's occurred as ready.
+fn wake_irq_waiters() {
+  let fired = u16::from(unsafe { RUST_IRQ_WAKE_FLAGS.replace(IrqBits::new()) });
+  if fired == 0 {
+    return;
+  }
+  for source in IrqSource::ALL {
+    let i = source as usize;
+    if fired & (1 << i) != 0 {
+      IRQ_COUNTS[i].fetch_add(1);
+      READY.fetch_or(IRQ_WAITERS[i].replace(0));
+    }
+  }
+}
+
+/// Makes the task currently being polled wait on an interrupt source.
+#[inline]
+fn wait_on_irq(source: IrqSource) {
+  IRQ_WAITERS[source as usize].fetch_or(1 << CURRENT_TASK.read());
+}
+
+/// The number of times the executor has seen an interrupt source occur.
+#[inline]
+#[must_use]
+fn irq_count(source: IrqSource) -> u16 {
+  IRQ_COUNTS[source as usize].read()
+}
+
+static TASK_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+  task_waker_clone,
+  task_waker_wake,
+  task_waker_wake,
+  task_waker_drop,
+);
+
+/// Each waker is just a task index stored in the data pointer.
+#[inline]
+#[must_use]
+fn task_waker(i: usize) -> Waker {
+  unsafe { Waker::from_raw(RawWaker::new(i as *const (), &TASK_WAKER_VTABLE)) }
+}
+
+unsafe fn task_waker_clone(data: *const ()) -> RawWaker {
+  RawWaker::new(data, &TASK_WAKER_VTABLE)
+}
+
+unsafe fn task_waker_wake(data: *const ()) {
+  READY.fetch_or(1 << (data as usize));
+}
+
+unsafe fn task_waker_drop(_: *const ()) {}
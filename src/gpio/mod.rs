@@ -0,0 +1,104 @@
+#![warn(missing_docs)]
+
+//! Module for the cartridge's general purpose I/O port.
+//!
+//! Some cartridges have extra hardware wired to four pins in the ROM address
+//! space, such as a real-time clock. Each pin can be an input or an output.
+//!
+//! The drivers in this module use the pins through the [GpioPins] trait.
+//! [Gpio] is the real port, and a simulated device can implement the trait to
+//! test a driver on the host. `RtcChipSim` (with the `sim` cargo feature) is
+//! such a simulation, for [Rtc].
+//!
+//! Only cartridges that have the extra hardware have the port. Otherwise, the
+//! registers are just part of the ROM.
+
+use voladdress::*;
+
+mod rtc;
+pub use rtc::*;
+
+#[cfg(any(test, feature = "sim"))]
+mod rtc_sim;
+#[cfg(any(test, feature = "sim"))]
+pub use rtc_sim::*;
+
+/// "GPIO Data"
+///
+/// The low 4 bits are the pins. Only pins set as outputs are written, and the
+/// pins can only be read while [GpioControl::readable] is set.
+pub const GPIO_DATA: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0800_00C4) };
+
+/// "GPIO Direction"
+///
+/// The low 4 bits are the pins: 1 for an output, 0 for an input.
+pub const GPIO_DIRECTION: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0800_00C6) };
+
+/// "GPIO Control"
+pub const GPIO_CONTROL: VolAddress<GpioControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0800_00C8) };
+
+/// [GPIO_CONTROL] settings.
+///
+/// * `readable`: The GPIO registers can be read. Otherwise, reading them gives
+///   the ROM data at those addresses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct GpioControl(u16);
+
+impl GpioControl {
+  pub_const_fn_new!();
+  u16_bool_field!(0, readable, with_readable);
+}
+
+/// Access to four GPIO pins.
+///
+/// Pin `i` is bit `i` of each value.
+pub trait GpioPins {
+  /// Sets which pins are outputs (1) and which are inputs (0).
+  fn set_direction(&mut self, outputs: u16);
+  /// Sets the output pins.
+  fn write(&mut self, pins: u16);
+  /// Reads all of the pins.
+  fn read(&mut self) -> u16;
+}
+
+/// The cartridge's GPIO port.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct Gpio {
+  _private: (),
+}
+
+impl Gpio {
+  /// Makes the port readable, so that inputs can be read.
+  #[inline]
+  pub fn new() -> Self {
+    GPIO_CONTROL.write(GpioControl::new().with_readable(true));
+    Self { _private: () }
+  }
+}
+
+impl Default for Gpio {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GpioPins for Gpio {
+  #[inline]
+  fn set_direction(&mut self, outputs: u16) {
+    GPIO_DIRECTION.write(outputs & 0b1111);
+  }
+  #[inline]
+  fn write(&mut self, pins: u16) {
+    GPIO_DATA.write(pins & 0b1111);
+  }
+  #[inline]
+  fn read(&mut self) -> u16 {
+    GPIO_DATA.read() & 0b1111
+  }
+}
@@ -0,0 +1,399 @@
+use super::GpioPins;
+
+/// The serial clock pin.
+const SCK: u16 = 0b001;
+/// The serial data pin.
+const SIO: u16 = 0b010;
+/// The chip select pin.
+const CS: u16 = 0b100;
+
+/// The RTC's commands, which go in bits 1-3 of the command byte.
+const CMD_RESET: u8 = 0x60;
+const CMD_STATUS: u8 = 0x62;
+const CMD_DATE_TIME: u8 = 0x64;
+/// The command byte bit for reading instead of writing.
+const CMD_READ: u8 = 0x01;
+
+/// The AM/PM flag of the RTC's hour byte.
+const HOUR_PM: u8 = 0x80;
+
+/// An error from an [Rtc] operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RtcError {
+  /// The RTC sent back a value that isn't valid BCD, or isn't a valid date
+  /// and time. Usually this means that the cartridge doesn't have an RTC.
+  BadData,
+  /// The date and time given is out of range.
+  InvalidDateTime,
+}
+
+/// The RTC's status register.
+///
+/// * `irq_*`: Settings for the RTC's interrupt output. A GBA doesn't use these.
+/// * `hour_24`: Hours go from 0 to 23, instead of 0 to 11 with a PM flag.
+/// * `power_lost`: (read-only) The RTC lost power, so the date and time are
+///   wrong. This is cleared by [Rtc::reset].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct RtcStatus(u8);
+
+impl RtcStatus {
+  pub_const_fn_new!();
+  u8_bool_field!(1, irq_frequency, with_irq_frequency);
+  u8_bool_field!(3, irq_minute, with_irq_minute);
+  u8_bool_field!(5, irq_alarm, with_irq_alarm);
+  u8_bool_field!(6, hour_24, with_hour_24);
+  u8_bool_field!(7, power_lost, with_power_lost);
+
+  /// The status value as a byte.
+  #[inline]
+  #[must_use]
+  pub const fn to_u8(self) -> u8 {
+    self.0
+  }
+
+  /// Makes a status value from a byte.
+  #[inline]
+  #[must_use]
+  pub const fn from_u8(u: u8) -> Self {
+    Self(u)
+  }
+}
+
+/// A date and time.
+///
+/// The RTC can only hold the years 2000 to 2099, and doesn't check that the
+/// day of the week matches the date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+  /// The year, 2000 to 2099.
+  pub year: u16,
+  /// The month, 1 to 12.
+  pub month: u8,
+  /// The day of the month, 1 to 31.
+  pub day: u8,
+  /// The day of the week, 0 to 6. Which day is 0 is up to the program.
+  pub weekday: u8,
+  /// The hour, 0 to 23.
+  pub hour: u8,
+  /// The minute, 0 to 59.
+  pub minute: u8,
+  /// The second, 0 to 59.
+  pub second: u8,
+}
+
+impl DateTime {
+  /// If every field is in range.
+  #[must_use]
+  pub const fn is_valid(&self) -> bool {
+    self.year >= 2000
+      && self.year <= 2099
+      && self.month >= 1
+      && self.month <= 12
+      && self.day >= 1
+      && self.day <= 31
+      && self.weekday <= 6
+      && self.hour <= 23
+      && self.minute <= 59
+      && self.second <= 59
+  }
+
+  /// Converts from the RTC's 7 BCD bytes.
+  pub(crate) fn from_rtc(bytes: [u8; 7], hour_24: bool) -> Option<Self> {
+    let hour = from_bcd(bytes[4] & 0x3F)?;
+    let hour = if hour_24 {
+      hour
+    } else if hour < 12 {
+      hour + if bytes[4] & HOUR_PM != 0 { 12 } else { 0 }
+    } else {
+      return None;
+    };
+    let date_time = Self {
+      year: 2000 + u16::from(from_bcd(bytes[0])?),
+      month: from_bcd(bytes[1])?,
+      day: from_bcd(bytes[2])?,
+      weekday: from_bcd(bytes[3])?,
+      hour,
+      minute: from_bcd(bytes[5])?,
+      // The top bit is the chip's test flag.
+      second: from_bcd(bytes[6] & 0x7F)?,
+    };
+    if date_time.is_valid() {
+      Some(date_time)
+    } else {
+      None
+    }
+  }
+
+  /// Converts to the RTC's 7 BCD bytes.
+  pub(crate) fn to_rtc(self, hour_24: bool) -> [u8; 7] {
+    let pm = if self.hour >= 12 { HOUR_PM } else { 0 };
+    let hour = if hour_24 { self.hour } else { self.hour % 12 };
+    [
+      to_bcd((self.year - 2000) as u8),
+      to_bcd(self.month),
+      to_bcd(self.day),
+      to_bcd(self.weekday),
+      to_bcd(hour) | pm,
+      to_bcd(self.minute),
+      to_bcd(self.second),
+    ]
+  }
+}
+
+/// Converts a number from 0 to 99 into BCD.
+#[inline]
+#[must_use]
+pub const fn to_bcd(n: u8) -> u8 {
+  ((n / 10) << 4) | (n % 10)
+}
+
+/// Converts a BCD byte into a number.
+///
+/// **Returns:** `None` if either digit is more than 9.
+#[inline]
+#[must_use]
+pub const fn from_bcd(bcd: u8) -> Option<u8> {
+  let (tens, ones) = (bcd >> 4, bcd & 0xF);
+  if tens > 9 || ones > 9 {
+    None
+  } else {
+    Some(tens * 10 + ones)
+  }
+}
+
+/// A driver for the Seiko S-3511 real-time clock.
+///
+/// This is the RTC in cartridges such as Pokémon Ruby, Sapphire, and Emerald.
+/// It's connected to the GPIO pins: pin 0 is the clock, pin 1 is data, and pin
+/// 2 is chip select. The RTC keeps running from the cartridge battery while
+/// the GBA is off.
+///
+/// Check [power_lost](Rtc::power_lost) when the game starts. If the RTC lost
+/// power (or it's brand new), [reset](Rtc::reset) it and ask the player to set
+/// the date and time.
+///
+/// The driver can use any [GpioPins], so `RtcChipSim` (with the `sim` cargo
+/// feature) can stand in for the real chip on the host.
+#[derive(Debug)]
+pub struct Rtc<P: GpioPins> {
+  pins: P,
+  hour_24: bool,
+}
+
+impl<P: GpioPins> Rtc<P> {
+  /// Makes a driver using the pins given, and reads the RTC's hour mode.
+  pub fn new(pins: P) -> Self {
+    let mut rtc = Self { pins, hour_24: false };
+    rtc.hour_24 = rtc.status().hour_24();
+    rtc
+  }
+
+  /// Gives back the pins.
+  #[inline]
+  pub fn into_pins(self) -> P {
+    self.pins
+  }
+
+  /// Reads the status register.
+  pub fn status(&mut self) -> RtcStatus {
+    let mut byte = [0];
+    self.read_command(CMD_STATUS, &mut byte);
+    RtcStatus(byte[0])
+  }
+
+  /// Writes the status register.
+  ///
+  /// The `power_lost` bit is read-only.
+  pub fn set_status(&mut self, status: RtcStatus) {
+    self.write_command(CMD_STATUS, &[status.0]);
+    self.hour_24 = status.hour_24();
+  }
+
+  /// If the RTC lost power since it was last reset.
+  #[inline]
+  pub fn power_lost(&mut self) -> bool {
+    self.status().power_lost()
+  }
+
+  /// Resets the RTC to midnight on 2000-01-01, clears the power lost flag,
+  /// and puts it in 24-hour mode.
+  pub fn reset(&mut self) {
+    self.write_command(CMD_RESET, &[]);
+    self.set_status(RtcStatus::new().with_hour_24(true));
+  }
+
+  /// Reads the date and time.
+  ///
+  /// ## Failure
+  /// * [RtcError::BadData] if the RTC's reply isn't a date and time.
+  pub fn read(&mut self) -> Result<DateTime, RtcError> {
+    let mut bytes = [0; 7];
+    self.read_command(CMD_DATE_TIME, &mut bytes);
+    DateTime::from_rtc(bytes, self.hour_24).ok_or(RtcError::BadData)
+  }
+
+  /// Sets the date and time.
+  ///
+  /// ## Failure
+  /// * [RtcError::InvalidDateTime] if a field is out of range.
+  pub fn write(&mut self, date_time: &DateTime) -> Result<(), RtcError> {
+    if !date_time.is_valid() {
+      return Err(RtcError::InvalidDateTime);
+    }
+    self.write_command(CMD_DATE_TIME, &date_time.to_rtc(self.hour_24));
+    Ok(())
+  }
+
+  /// Selects the chip and sends a command byte.
+  fn begin(&mut self, command: u8) {
+    self.pins.write(SCK);
+    self.pins.write(SCK | CS);
+    self.pins.set_direction(SCK | SIO | CS);
+    // Unlike the data bytes, the command byte is sent high bit first.
+    for i in (0..8).rev() {
+      let bit = u16::from((command >> i) & 1) << 1;
+      self.pins.write(bit | CS);
+      self.pins.write(bit | CS | SCK);
+    }
+  }
+
+  /// Deselects the chip.
+  fn end(&mut self) {
+    self.pins.write(SCK);
+    self.pins.set_direction(SCK | SIO | CS);
+  }
+
+  fn write_command(&mut self, command: u8, bytes: &[u8]) {
+    self.begin(command);
+    for &byte in bytes {
+      for i in 0..8 {
+        let bit = u16::from((byte >> i) & 1) << 1;
+        self.pins.write(bit | CS);
+        self.pins.write(bit | CS | SCK);
+      }
+    }
+    self.end();
+  }
+
+  fn read_command(&mut self, command: u8, bytes: &mut [u8]) {
+    self.begin(command | CMD_READ);
+    self.pins.set_direction(SCK | CS);
+    for byte in bytes.iter_mut() {
+      *byte = 0;
+      for i in 0..8 {
+        self.pins.write(CS);
+        self.pins.write(CS | SCK);
+        if self.pins.read() & SIO != 0 {
+          *byte |= 1 << i;
+        }
+      }
+    }
+    self.end();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gpio::RtcChipSim;
+
+  const DATE_TIME: DateTime = DateTime {
+    year: 2004,
+    month: 11,
+    day: 21,
+    weekday: 0,
+    hour: 19,
+    minute: 30,
+    second: 45,
+  };
+
+  /// Pins with nothing connected, which read as all 1 bits.
+  struct NoChip;
+
+  impl GpioPins for NoChip {
+    fn set_direction(&mut self, _outputs: u16) {}
+    fn write(&mut self, _pins: u16) {}
+    fn read(&mut self) -> u16 {
+      0b1111
+    }
+  }
+
+  #[test]
+  fn bcd_round_trip() {
+    for n in 0..100 {
+      assert_eq!(from_bcd(to_bcd(n)), Some(n));
+    }
+    assert_eq!(to_bcd(59), 0x59);
+    assert_eq!(from_bcd(0x1A), None);
+    assert_eq!(from_bcd(0xA1), None);
+  }
+
+  #[test]
+  fn hours_in_12_hour_mode() {
+    let at = |hour| DateTime { hour, ..DATE_TIME };
+    for (hour, byte) in [(0, 0x00), (11, 0x11), (12, 0x80), (23, 0x91)] {
+      let bytes = at(hour).to_rtc(false);
+      assert_eq!(bytes[4], byte, "hour {hour}");
+      assert_eq!(DateTime::from_rtc(bytes, false), Some(at(hour)));
+    }
+    assert_eq!(at(23).to_rtc(true)[4] & 0x3F, 0x23);
+    assert_eq!(DateTime::from_rtc(at(23).to_rtc(true), true), Some(at(23)));
+    // 12 and up isn't an hour in 12-hour mode, with or without PM.
+    let mut bytes = at(0).to_rtc(false);
+    bytes[4] = 0x12;
+    assert_eq!(DateTime::from_rtc(bytes, false), None);
+    bytes[4] = 0x92;
+    assert_eq!(DateTime::from_rtc(bytes, false), None);
+  }
+
+  #[test]
+  fn reset_clears_power_lost() {
+    let mut rtc = Rtc::new(RtcChipSim::new());
+    assert!(rtc.power_lost());
+    assert!(!rtc.status().hour_24());
+    rtc.reset();
+    assert!(!rtc.power_lost());
+    assert!(rtc.status().hour_24());
+    let chip = rtc.into_pins();
+    assert_eq!(chip.date_time().year, 2000);
+    assert_eq!(chip.date_time().hour, 0);
+  }
+
+  #[test]
+  fn date_time_round_trip() {
+    // A new chip is in 12-hour mode, and reset puts it in 24-hour mode.
+    let mut rtc = Rtc::new(RtcChipSim::new());
+    rtc.write(&DATE_TIME).unwrap();
+    assert_eq!(rtc.read(), Ok(DATE_TIME));
+    rtc.reset();
+    rtc.write(&DATE_TIME).unwrap();
+    assert_eq!(rtc.read(), Ok(DATE_TIME));
+    let mut chip = rtc.into_pins();
+    assert_eq!(chip.date_time(), DATE_TIME);
+    chip.advance(15);
+    let mut rtc = Rtc::new(chip);
+    assert_eq!(
+      rtc.read(),
+      Ok(DateTime { hour: 19, minute: 31, second: 0, ..DATE_TIME })
+    );
+    assert_eq!(
+      rtc.write(&DateTime { hour: 24, ..DATE_TIME }),
+      Err(RtcError::InvalidDateTime)
+    );
+  }
+
+  #[test]
+  fn status_writes_keep_power_lost() {
+    let mut rtc = Rtc::new(RtcChipSim::new());
+    rtc.set_status(RtcStatus::new().with_hour_24(true).with_irq_minute(true));
+    let status = rtc.status();
+    assert!(status.hour_24() && status.irq_minute() && status.power_lost());
+  }
+
+  #[test]
+  fn garbage_reply_is_bad_data() {
+    let mut rtc = Rtc::new(NoChip);
+    assert_eq!(rtc.read(), Err(RtcError::BadData));
+  }
+}
@@ -0,0 +1,278 @@
+//! A host side simulation of the S-3511 RTC.
+//!
+//! Nothing in this file touches the hardware, so it can be used to test an
+//! [Rtc](super::Rtc) (or another driver for the chip) on the host.
+
+use super::{DateTime, GpioPins, RtcStatus};
+
+const SCK: u16 = 0b001;
+const SIO: u16 = 0b010;
+const CS: u16 = 0b100;
+
+/// The status bits that can be written.
+const STATUS_WRITABLE: u8 = 0b0110_1010;
+
+/// What the simulated chip is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Phase {
+  /// Not selected, or done with the current command.
+  Idle,
+  /// Shifting in the command byte.
+  Command,
+  /// Shifting in data bytes for the command given.
+  Write(u8),
+  /// Shifting out data bytes.
+  Read,
+}
+
+/// A simulated S-3511 RTC, which is used through its [GpioPins] impl.
+///
+/// The simulated clock doesn't tick by itself, but
+/// [advance](RtcChipSim::advance) moves it forward.
+///
+/// This is only available with the `sim` cargo feature (or in the crate's own
+/// tests).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RtcChipSim {
+  status: RtcStatus,
+  date_time: DateTime,
+  direction: u16,
+  data: u16,
+  /// The pins as the chip last saw them.
+  seen: u16,
+  sio_out: bool,
+  phase: Phase,
+  shift: u8,
+  bits: u8,
+  bytes: [u8; 7],
+  index: usize,
+  len: usize,
+}
+
+impl RtcChipSim {
+  /// Makes a chip that has just had a battery put in: the power lost flag is
+  /// set, and it's in 12-hour mode.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      status: RtcStatus::new().with_power_lost(true),
+      date_time: DateTime {
+        year: 2000,
+        month: 1,
+        day: 1,
+        weekday: 0,
+        hour: 0,
+        minute: 0,
+        second: 0,
+      },
+      direction: 0,
+      data: 0,
+      seen: 0,
+      sio_out: false,
+      phase: Phase::Idle,
+      shift: 0,
+      bits: 0,
+      bytes: [0; 7],
+      index: 0,
+      len: 0,
+    }
+  }
+
+  /// The chip's status register.
+  #[inline]
+  #[must_use]
+  pub const fn status(&self) -> RtcStatus {
+    self.status
+  }
+
+  /// The chip's date and time.
+  #[inline]
+  #[must_use]
+  pub const fn date_time(&self) -> DateTime {
+    self.date_time
+  }
+
+  /// Sets the chip's date and time directly.
+  #[inline]
+  pub fn set_date_time(&mut self, date_time: DateTime) {
+    self.date_time = date_time;
+  }
+
+  /// Sets the power lost flag, as if the battery ran out.
+  #[inline]
+  pub fn lose_power(&mut self) {
+    self.status = self.status.with_power_lost(true);
+  }
+
+  /// Moves the clock forward.
+  ///
+  /// Months are treated as having 31 days.
+  pub fn advance(&mut self, seconds: u32) {
+    let t = &mut self.date_time;
+    let mut carry = seconds;
+    let s = u32::from(t.second) + carry;
+    t.second = (s % 60) as u8;
+    carry = s / 60;
+    let m = u32::from(t.minute) + carry;
+    t.minute = (m % 60) as u8;
+    carry = m / 60;
+    let h = u32::from(t.hour) + carry;
+    t.hour = (h % 24) as u8;
+    carry = h / 24;
+    t.weekday = ((u32::from(t.weekday) + carry) % 7) as u8;
+    let d = u32::from(t.day) - 1 + carry;
+    t.day = (d % 31) as u8 + 1;
+    carry = d / 31;
+    let mo = u32::from(t.month) - 1 + carry;
+    t.month = (mo % 12) as u8 + 1;
+    carry = mo / 12;
+    t.year = (2000 + (u32::from(t.year) - 2000 + carry) % 100) as u16;
+  }
+
+  /// Reacts to the pins that the GBA is driving.
+  fn update(&mut self) {
+    let pins = self.data & self.direction;
+    let (was, now) = (self.seen, pins);
+    self.seen = pins;
+    if now & CS == 0 {
+      self.phase = Phase::Idle;
+      return;
+    }
+    if was & CS == 0 {
+      self.phase = Phase::Command;
+      self.shift = 0;
+      self.bits = 0;
+      return;
+    }
+    if was & SCK != 0 || now & SCK == 0 {
+      return;
+    }
+    // A rising clock edge while selected.
+    let bit = u8::from(now & SIO != 0);
+    match self.phase {
+      Phase::Idle => (),
+      Phase::Command => {
+        self.shift = (self.shift << 1) | bit;
+        self.bits += 1;
+        if self.bits == 8 {
+          self.command(self.shift);
+        }
+      }
+      Phase::Write(command) => {
+        self.shift |= bit << self.bits;
+        self.bits += 1;
+        if self.bits == 8 {
+          self.bytes[self.index] = self.shift;
+          self.index += 1;
+          self.shift = 0;
+          self.bits = 0;
+          if self.index == self.len {
+            self.finish_write(command);
+            self.phase = Phase::Idle;
+          }
+        }
+      }
+      Phase::Read => {
+        self.sio_out = (self.bytes[self.index] >> self.bits) & 1 != 0;
+        self.bits += 1;
+        if self.bits == 8 {
+          self.bits = 0;
+          self.index += 1;
+          if self.index == self.len {
+            self.phase = Phase::Idle;
+          }
+        }
+      }
+    }
+  }
+
+  /// Starts a command, once its byte has been shifted in.
+  fn command(&mut self, byte: u8) {
+    self.shift = 0;
+    self.bits = 0;
+    self.index = 0;
+    self.phase = Phase::Idle;
+    if byte & 0xF0 != 0x60 {
+      return;
+    }
+    let hour_24 = self.status.hour_24();
+    let command = byte & 0x0E;
+    let (len, bytes) = match command {
+      0x0 => {
+        *self = Self {
+          status: RtcStatus::new(),
+          direction: self.direction,
+          data: self.data,
+          seen: self.seen,
+          ..Self::new()
+        };
+        return;
+      }
+      0x2 => (1, [self.status.to_u8(), 0, 0, 0, 0, 0, 0]),
+      0x4 => (7, self.date_time.to_rtc(hour_24)),
+      0x6 => {
+        let [_, _, _, _, h, m, s] = self.date_time.to_rtc(hour_24);
+        (3, [h, m, s, 0, 0, 0, 0])
+      }
+      _ => return,
+    };
+    self.len = len;
+    if byte & 1 != 0 {
+      self.bytes = bytes;
+      self.phase = Phase::Read;
+    } else {
+      self.phase = Phase::Write(command);
+    }
+  }
+
+  fn finish_write(&mut self, command: u8) {
+    let hour_24 = self.status.hour_24();
+    match command {
+      0x2 => {
+        let old = self.status.to_u8();
+        let new = self.bytes[0];
+        self.status = RtcStatus::from_u8(
+          (old & !STATUS_WRITABLE) | (new & STATUS_WRITABLE),
+        );
+      }
+      0x4 => {
+        if let Some(date_time) = DateTime::from_rtc(self.bytes, hour_24) {
+          self.date_time = date_time;
+        }
+      }
+      0x6 => {
+        let mut bytes = self.date_time.to_rtc(hour_24);
+        bytes[4..].copy_from_slice(&self.bytes[..3]);
+        if let Some(date_time) = DateTime::from_rtc(bytes, hour_24) {
+          self.date_time = date_time;
+        }
+      }
+      _ => (),
+    }
+  }
+}
+
+impl Default for RtcChipSim {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GpioPins for RtcChipSim {
+  #[inline]
+  fn set_direction(&mut self, outputs: u16) {
+    self.direction = outputs & 0b1111;
+    self.update();
+  }
+  #[inline]
+  fn write(&mut self, pins: u16) {
+    self.data = pins & 0b1111;
+    self.update();
+  }
+  #[inline]
+  fn read(&mut self) -> u16 {
+    let sio = if self.direction & SIO == 0 && self.sio_out { SIO } else { 0 };
+    (self.data & self.direction) | sio
+  }
+}
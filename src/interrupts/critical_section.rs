@@ -0,0 +1,87 @@
+use core::marker::PhantomData;
+
+#[cfg(target_arch = "arm")]
+use super::IME;
+
+/// A token showing that interrupts are currently disabled.
+///
+/// You get one of these inside of a [free] call. It can't be created any other
+/// way, and it's not allowed to escape from the closure.
+#[derive(Debug, Clone, Copy)]
+pub struct CriticalSection<'cs>(PhantomData<&'cs ()>);
+
+/// Runs the closure with interrupts disabled.
+///
+/// This turns `IME` off, runs the closure, and then puts `IME` back to how it
+/// was before. Since the old value is restored rather than always turning `IME`
+/// on at the end, it's fine to call `free` inside of another `free` call, or
+/// inside of the interrupt handler.
+///
+/// As noted on [IME], an interrupt that was already on its way when `IME` is
+/// turned off can still happen during the next 2 cycles. To account for this,
+/// `free` waits 2 cycles after turning `IME` off before running the closure,
+/// so any such interrupt is fully handled before the closure starts.
+///
+/// When the crate is built for the host (for tests) there are no interrupts,
+/// and the closure just runs.
+#[inline]
+pub fn free<F, R>(f: F) -> R
+where
+  F: FnOnce(CriticalSection<'_>) -> R,
+{
+  #[cfg(target_arch = "arm")]
+  let ime_previous = disable_ime();
+  let r = f(CriticalSection(PhantomData));
+  #[cfg(target_arch = "arm")]
+  enable_ime(ime_previous);
+  r
+}
+
+/// Turns `IME` off, and returns the old setting.
+///
+/// The interrupt handler always puts `IME` back how it found it, so reading
+/// and then writing `IME` as two steps is fine even if an interrupt happens in
+/// between them.
+#[cfg(target_arch = "arm")]
+#[inline]
+fn disable_ime() -> bool {
+  let ime_previous = IME.read();
+  IME.write(false);
+  // Note: This is *not* `nomem`, so that it also acts as a compiler barrier
+  // and the closure's memory accesses can't be moved above it.
+  unsafe {
+    core::arch::asm! {
+      "nop",
+      "nop",
+      options(nostack, preserves_flags)
+    }
+  }
+  ime_previous
+}
+
+/// Puts `IME` back to a setting given by [disable_ime].
+#[cfg(target_arch = "arm")]
+#[inline]
+fn enable_ime(ime_previous: bool) {
+  core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+  IME.write(ime_previous);
+}
+
+// The host has no interrupts to turn off, so a host build leaves this out.
+#[cfg(all(feature = "critical-section", target_arch = "arm"))]
+struct GbaCriticalSection;
+
+#[cfg(all(feature = "critical-section", target_arch = "arm"))]
+critical_section::set_impl!(GbaCriticalSection);
+
+#[cfg(all(feature = "critical-section", target_arch = "arm"))]
+unsafe impl critical_section::Impl for GbaCriticalSection {
+  #[inline]
+  unsafe fn acquire() -> critical_section::RawRestoreState {
+    disable_ime()
+  }
+  #[inline]
+  unsafe fn release(ime_previous: critical_section::RawRestoreState) {
+    enable_ime(ime_previous)
+  }
+}
@@ -1,6 +1,20 @@
-use core::{cell::UnsafeCell, fmt::Debug};
+use core::{
+  cell::UnsafeCell,
+  fmt::Debug,
+  mem::size_of,
+  ops::{BitAnd, BitOr},
+};
 
-use crate::{keys::KeyInput, video::Color};
+#[cfg(target_arch = "arm")]
+use core::mem::transmute_copy;
+
+#[cfg(target_arch = "arm")]
+use crate::{a32_swp_r0_r0_r1, a32_swpb_r0_r0_r1};
+use crate::{
+  interrupts::{free, IrqBits},
+  keys::KeyInput,
+  video::Color,
+};
 
 /// A GbaCell holds a value that's accessed in a single machine instruction.
 ///
@@ -51,7 +65,136 @@ impl<T: GbaCellSafe> GbaCell<T> {
   pub fn get(&self) -> *mut T {
     self.0.get()
   }
+
+  /// Writes a new value, returning the old value.
+  ///
+  /// For 1 and 4 byte types this is a single `swpb` or `swp` instruction, so
+  /// it can't be interrupted part way. For 2 byte types there's no swap
+  /// instruction, so interrupts are briefly disabled instead.
+  #[inline]
+  pub fn replace(&self, t: T) -> T {
+    match size_of::<T>() {
+      #[cfg(target_arch = "arm")]
+      4 => unsafe {
+        let old = a32_swp_r0_r0_r1(transmute_copy(&t), self.get().cast());
+        transmute_copy(&old)
+      },
+      #[cfg(target_arch = "arm")]
+      1 => unsafe {
+        let old = a32_swpb_r0_r0_r1(transmute_copy(&t), self.get().cast());
+        transmute_copy(&old)
+      },
+      _ => free(|_| {
+        let old = self.read();
+        self.write(t);
+        old
+      }),
+    }
+  }
+
+  /// Swaps the values of two cells.
+  ///
+  /// Interrupts are disabled while this happens.
+  #[inline]
+  pub fn swap(&self, other: &Self) {
+    if core::ptr::eq(self, other) {
+      return;
+    }
+    free(|_| {
+      let t = self.read();
+      self.write(other.read());
+      other.write(t);
+    })
+  }
+
+  /// Updates the value using a function, returning the old value.
+  ///
+  /// Interrupts are disabled while the function runs, so the function should
+  /// be kept short.
+  #[inline]
+  pub fn update<F: FnOnce(T) -> T>(&self, f: F) -> T {
+    free(|_| {
+      let old = self.read();
+      self.write(f(old));
+      old
+    })
+  }
+
+  /// Writes `new` only if the current value is `current`.
+  ///
+  /// The return value is a result indicating whether the new value was
+  /// written, and containing the previous value. On success this value is
+  /// guaranteed to be equal to `current`.
+  ///
+  /// Interrupts are disabled while this happens.
+  #[inline]
+  pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T>
+  where
+    T: PartialEq,
+  {
+    free(|_| {
+      let old = self.read();
+      if old == current {
+        self.write(new);
+        Ok(old)
+      } else {
+        Err(old)
+      }
+    })
+  }
+}
+
+impl<T> GbaCell<T>
+where
+  T: GbaCellSafe + BitOr<Output = T>,
+{
+  /// Does a bitwise "or" with the current value, returning the old value.
+  ///
+  /// Interrupts are disabled while this happens.
+  #[inline]
+  pub fn fetch_or(&self, t: T) -> T {
+    self.update(|old| old | t)
+  }
+}
+
+impl<T> GbaCell<T>
+where
+  T: GbaCellSafe + BitAnd<Output = T>,
+{
+  /// Does a bitwise "and" with the current value, returning the old value.
+  ///
+  /// Interrupts are disabled while this happens.
+  #[inline]
+  pub fn fetch_and(&self, t: T) -> T {
+    self.update(|old| old & t)
+  }
+}
+
+macro_rules! impl_fetch_add_sub {
+  ($($t:ty),*) => {
+    $(
+      impl GbaCell<$t> {
+        /// Adds to the current value (wrapping), returning the old value.
+        ///
+        /// Interrupts are disabled while this happens.
+        #[inline]
+        pub fn fetch_add(&self, val: $t) -> $t {
+          self.update(|old| old.wrapping_add(val))
+        }
+
+        /// Subtracts from the current value (wrapping), returning the old
+        /// value.
+        ///
+        /// Interrupts are disabled while this happens.
+        #[inline]
+        pub fn fetch_sub(&self, val: $t) -> $t {
+          self.update(|old| old.wrapping_sub(val))
+        }
+      }
+    )*
+  };
 }
+impl_fetch_add_sub!(u8, i8, u16, i16, u32, i32);
 
 /// Marker trait for all types that will safely work with a [GbaCell].
 ///
@@ -61,6 +204,7 @@ impl<T: GbaCellSafe> GbaCell<T> {
 /// structs over an integer.
 pub unsafe trait GbaCellSafe: Copy {}
 
+unsafe impl GbaCellSafe for bool {}
 unsafe impl GbaCellSafe for u8 {}
 unsafe impl GbaCellSafe for i8 {}
 
@@ -68,6 +212,7 @@ unsafe impl GbaCellSafe for u16 {}
 unsafe impl GbaCellSafe for i16 {}
 unsafe impl GbaCellSafe for Color {}
 unsafe impl GbaCellSafe for KeyInput {}
+unsafe impl GbaCellSafe for IrqBits {}
 
 unsafe impl GbaCellSafe for u32 {}
 unsafe impl GbaCellSafe for i32 {}
@@ -19,12 +19,21 @@ pub const IF: VolAddress<IrqBits, Safe, Safe> =
 /// and the CPU actually switching over and running the handler code, so it's
 /// *possible* for `IME` to be written to false during this 2 cycle gap and you
 /// end up having an interrupt request happen while `IME` is off.
+///
+/// If you want to turn off interrupts for a short time, the [free] function
+/// handles this for you.
 pub const IME: VolAddress<bool, Safe, Safe> =
   unsafe { VolAddress::new(0x0400_0208) };
 
 mod gba_cell;
 pub use gba_cell::*;
 
+mod critical_section;
+pub use self::critical_section::*;
+
+mod queue;
+pub use queue::*;
+
 /// A bit set where each bit is a particular interrupt source.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -81,3 +90,62 @@ impl IrqBits {
   pub const KEYPAD: Self = Self::new().with_keypad(true);
   pub const GAMEPAK: Self = Self::new().with_gamepak(true);
 }
+
+/// A single interrupt source.
+///
+/// The value of each variant is the index of that source's bit within
+/// [IrqBits]. Lower indexes are higher priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum IrqSource {
+  VBlank = 0,
+  HBlank = 1,
+  VCounter = 2,
+  Timer0 = 3,
+  Timer1 = 4,
+  Timer2 = 5,
+  Timer3 = 6,
+  Serial = 7,
+  Dma0 = 8,
+  Dma1 = 9,
+  Dma2 = 10,
+  Dma3 = 11,
+  Keypad = 12,
+  GamePak = 13,
+}
+
+impl IrqSource {
+  /// All of the interrupt sources, in priority order.
+  pub const ALL: [Self; 14] = [
+    Self::VBlank,
+    Self::HBlank,
+    Self::VCounter,
+    Self::Timer0,
+    Self::Timer1,
+    Self::Timer2,
+    Self::Timer3,
+    Self::Serial,
+    Self::Dma0,
+    Self::Dma1,
+    Self::Dma2,
+    Self::Dma3,
+    Self::Keypad,
+    Self::GamePak,
+  ];
+
+  /// The [IrqBits] value with only this source's bit set.
+  #[inline]
+  #[must_use]
+  pub const fn to_bits(self) -> IrqBits {
+    IrqBits(1 << (self as u16))
+  }
+}
+
+impl From<IrqSource> for IrqBits {
+  #[inline]
+  #[must_use]
+  fn from(source: IrqSource) -> Self {
+    source.to_bits()
+  }
+}
@@ -0,0 +1,355 @@
+use core::{
+  cell::UnsafeCell,
+  fmt::Debug,
+  mem::MaybeUninit,
+  sync::atomic::{compiler_fence, Ordering},
+};
+
+use super::{free, GbaCell};
+
+/// A fixed capacity queue with one producer and one consumer.
+///
+/// This lets the interrupt handler pass values to the main program (or the
+/// other way around) without disabling interrupts. Examples include serial
+/// bytes that were received, requests for more audio data, or key events.
+///
+/// The queue is only correct when there's a *single* producer (the code that
+/// calls [`push`](SpscQueue::push)) and a *single* consumer (the code that
+/// calls [`pop`](SpscQueue::pop)). Usually one of them is the main program and
+/// the other is a particular interrupt handler. If more than one interrupt
+/// source needs to push to the same queue, use an [MpscQueue] instead.
+///
+/// Because elements can't be dropped or moved out from a shared queue, the
+/// element type must be `Copy`. Breaking the single producer or single
+/// consumer rule can lose or repeat elements, but can't cause UB.
+///
+/// ## Index Arithmetic
+/// The `head` and `tail` indexes each count from 0 up to `2 * N` and then wrap
+/// back to 0. Counting past `N` is what allows telling a full queue apart from
+/// an empty queue, so that all `N` slots can be used. All of the index math is
+/// comparisons, adds, and subtracts, because the GBA has no division
+/// instruction.
+pub struct SpscQueue<T: Copy, const N: usize> {
+  /// The index of the next element to pop.
+  head: GbaCell<u16>,
+  /// The index of the next element to push.
+  tail: GbaCell<u16>,
+  slots: UnsafeCell<[MaybeUninit<T>; N]>,
+}
+
+unsafe impl<T: Copy, const N: usize> Send for SpscQueue<T, N> {}
+unsafe impl<T: Copy, const N: usize> Sync for SpscQueue<T, N> {}
+
+impl<T: Copy, const N: usize> Debug for SpscQueue<T, N> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("SpscQueue")
+      .field("len", &self.len())
+      .field("capacity", &N)
+      .finish()
+  }
+}
+
+impl<T: Copy, const N: usize> Default for SpscQueue<T, N> {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Copy, const N: usize> SpscQueue<T, N> {
+  /// Makes a new, empty queue.
+  ///
+  /// ## Panics
+  /// * The capacity `N` must be at least 1, and no more than `0x7FFF`.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    assert!(N > 0 && N <= (u16::MAX / 2) as usize);
+    Self {
+      head: GbaCell::new(0),
+      tail: GbaCell::new(0),
+      slots: UnsafeCell::new(unsafe {
+        MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init()
+      }),
+    }
+  }
+
+  /// The most elements that the queue can hold.
+  #[inline]
+  #[must_use]
+  pub const fn capacity(&self) -> usize {
+    N
+  }
+
+  /// The number of elements in the queue.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    usize::from(queue_len(self.head.read(), self.tail.read(), N as u16))
+  }
+
+  /// If the queue has no elements.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.head.read() == self.tail.read()
+  }
+
+  /// If the queue can't hold any more elements.
+  #[inline]
+  #[must_use]
+  pub fn is_full(&self) -> bool {
+    self.len() == N
+  }
+
+  /// Adds an element to the back of the queue.
+  ///
+  /// This should only be called by the producer.
+  ///
+  /// ## Failure
+  /// * If the queue is full you get the element back as an error.
+  #[inline]
+  pub fn push(&self, t: T) -> Result<(), T> {
+    let head = self.head.read();
+    let tail = self.tail.read();
+    if queue_len(head, tail, N as u16) == N as u16 {
+      return Err(t);
+    }
+    let slot = queue_slot(tail, N as u16);
+    unsafe { self.slots.get().cast::<T>().add(slot).write_volatile(t) };
+    // The element must be fully written before the consumer can see the new
+    // tail value.
+    compiler_fence(Ordering::Release);
+    self.tail.write(queue_next(tail, N as u16));
+    Ok(())
+  }
+
+  /// Removes the element at the front of the queue.
+  ///
+  /// This should only be called by the consumer.
+  #[inline]
+  pub fn pop(&self) -> Option<T> {
+    let head = self.head.read();
+    let tail = self.tail.read();
+    if head == tail {
+      return None;
+    }
+    compiler_fence(Ordering::Acquire);
+    let slot = queue_slot(head, N as u16);
+    let t = unsafe { self.slots.get().cast::<T>().add(slot).read_volatile() };
+    // The element must be fully read before the producer can see the new
+    // head value and reuse the slot.
+    compiler_fence(Ordering::Release);
+    self.head.write(queue_next(head, N as u16));
+    Some(t)
+  }
+}
+
+/// A fixed capacity queue with many producers and one consumer.
+///
+/// This works like an [SpscQueue], except that interrupts are disabled for the
+/// brief moment that an element is being pushed. That way any number of
+/// interrupt handlers (and the main program) can all push to the same queue.
+/// There must still be only a single consumer.
+#[derive(Debug)]
+pub struct MpscQueue<T: Copy, const N: usize>(SpscQueue<T, N>);
+
+impl<T: Copy, const N: usize> Default for MpscQueue<T, N> {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Copy, const N: usize> MpscQueue<T, N> {
+  /// Makes a new, empty queue.
+  ///
+  /// ## Panics
+  /// * The capacity `N` must be at least 1, and no more than `0x7FFF`.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self(SpscQueue::new())
+  }
+
+  /// The most elements that the queue can hold.
+  #[inline]
+  #[must_use]
+  pub const fn capacity(&self) -> usize {
+    N
+  }
+
+  /// The number of elements in the queue.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  /// If the queue has no elements.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// If the queue can't hold any more elements.
+  #[inline]
+  #[must_use]
+  pub fn is_full(&self) -> bool {
+    self.0.is_full()
+  }
+
+  /// Adds an element to the back of the queue.
+  ///
+  /// Interrupts are disabled while this happens.
+  ///
+  /// ## Failure
+  /// * If the queue is full you get the element back as an error.
+  #[inline]
+  pub fn push(&self, t: T) -> Result<(), T> {
+    free(|_| self.0.push(t))
+  }
+
+  /// Removes the element at the front of the queue.
+  ///
+  /// This should only be called by the consumer.
+  #[inline]
+  pub fn pop(&self) -> Option<T> {
+    self.0.pop()
+  }
+}
+
+/// The number of elements between `head` and `tail`, in a queue of capacity
+/// `n`.
+#[inline]
+#[must_use]
+const fn queue_len(head: u16, tail: u16, n: u16) -> u16 {
+  if tail >= head {
+    tail - head
+  } else {
+    // `tail + 2 * n` could overflow when `n` is big, so subtract first.
+    (2 * n - head) + tail
+  }
+}
+
+/// The index after `i`, in a queue of capacity `n`.
+#[inline]
+#[must_use]
+const fn queue_next(i: u16, n: u16) -> u16 {
+  if i + 1 == 2 * n {
+    0
+  } else {
+    i + 1
+  }
+}
+
+/// The slot that index `i` refers to, in a queue of capacity `n`.
+#[inline]
+#[must_use]
+const fn queue_slot(i: u16, n: u16) -> usize {
+  (if i >= n { i - n } else { i }) as usize
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MAX: u16 = 0x7FFF;
+
+  #[test]
+  fn index_math_wraps() {
+    assert_eq!(queue_next(0, 3), 1);
+    assert_eq!(queue_next(5, 3), 0);
+    assert_eq!(queue_slot(2, 3), 2);
+    assert_eq!(queue_slot(3, 3), 0);
+    assert_eq!(queue_slot(5, 3), 2);
+    assert_eq!(queue_len(4, 4, 3), 0);
+    assert_eq!(queue_len(1, 4, 3), 3);
+    assert_eq!(queue_len(4, 1, 3), 3);
+    assert_eq!(queue_len(5, 0, 3), 1);
+  }
+
+  #[test]
+  fn index_math_near_the_biggest_capacity() {
+    let last = 2 * MAX - 1;
+    assert_eq!(queue_next(last, MAX), 0);
+    assert_eq!(queue_next(last - 1, MAX), last);
+    assert_eq!(queue_slot(last, MAX), usize::from(MAX - 1));
+    assert_eq!(queue_slot(MAX, MAX), 0);
+    assert_eq!(queue_len(last, 0, MAX), 1);
+    assert_eq!(queue_len(last, MAX - 1, MAX), MAX);
+    assert_eq!(queue_len(MAX, 0, MAX), MAX);
+    assert_eq!(queue_len(0, MAX, MAX), MAX);
+    assert_eq!(queue_len(1, 0, MAX), last);
+  }
+
+  #[test]
+  fn push_and_pop_wrap_around() {
+    let q = SpscQueue::<u32, 3>::new();
+    let mut next_in = 0;
+    let mut next_out = 0;
+    // Enough rounds for the indexes to wrap many times, at every fill level.
+    for round in 0..50 {
+      let fill = round % 4;
+      while q.len() < fill {
+        q.push(next_in).unwrap();
+        next_in += 1;
+      }
+      assert_eq!(q.len(), fill);
+      assert_eq!(q.is_empty(), fill == 0);
+      assert_eq!(q.is_full(), fill == 3);
+      while let Some(t) = q.pop() {
+        assert_eq!(t, next_out);
+        next_out += 1;
+      }
+    }
+    assert_eq!(next_in, next_out);
+  }
+
+  #[test]
+  fn full_queue_gives_the_element_back() {
+    let q = SpscQueue::<u8, 2>::new();
+    assert_eq!(q.push(1), Ok(()));
+    assert_eq!(q.push(2), Ok(()));
+    assert_eq!(q.push(3), Err(3));
+    assert_eq!(q.pop(), Some(1));
+    assert_eq!(q.push(3), Ok(()));
+    assert_eq!(q.pop(), Some(2));
+    assert_eq!(q.pop(), Some(3));
+    assert_eq!(q.pop(), None);
+  }
+
+  #[test]
+  fn biggest_capacity_queue_wraps() {
+    let q = SpscQueue::<u8, { MAX as usize }>::new();
+    for i in 0..MAX {
+      q.push(i as u8).unwrap();
+    }
+    assert!(q.is_full());
+    assert_eq!(q.push(0), Err(0));
+    // Go around the whole index range, keeping the queue nearly full so that
+    // `tail` is often behind `head`.
+    for i in 0..2 * u32::from(MAX) + 10 {
+      assert_eq!(q.pop(), Some(i as u8));
+      assert_eq!(q.len(), usize::from(MAX - 1));
+      q.push((i + u32::from(MAX)) as u8).unwrap();
+      assert!(q.is_full());
+    }
+  }
+
+  #[test]
+  fn mpsc_queue_works_like_spsc() {
+    let q = MpscQueue::<u16, 4>::new();
+    for i in 0..10 {
+      q.push(i).unwrap();
+      q.push(i + 100).unwrap();
+      assert_eq!(q.len(), 2);
+      assert_eq!(q.pop(), Some(i));
+      assert_eq!(q.pop(), Some(i + 100));
+      assert!(q.is_empty());
+    }
+  }
+}
@@ -0,0 +1,341 @@
+use super::{KeyInput, KeyMask};
+
+/// One step of a [Combo].
+///
+/// A step is performed on a given frame when:
+/// * All of the step's keys are held, and
+/// * At least one of the step's keys was newly pressed that frame, and
+/// * If the step has any d-pad directions, the d-pad directions being held are
+///   *exactly* the step's directions. This is what keeps "down" and
+///   "down-forward" from counting as the same step.
+///
+/// Needing a new press means that just holding a key down won't perform the
+/// same step over and over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComboStep {
+  /// The keys of this step.
+  pub keys: KeyMask,
+  /// The most frames allowed after the previous step for this step to count.
+  ///
+  /// This is ignored for the first step of a combo.
+  pub window: u16,
+}
+
+impl ComboStep {
+  /// Makes a new step.
+  #[inline]
+  #[must_use]
+  pub const fn new(keys: KeyMask, window: u16) -> Self {
+    Self { keys, window }
+  }
+
+  #[inline]
+  #[must_use]
+  const fn is_performed(self, held: KeyMask, new_presses: KeyMask) -> bool {
+    let dpad = self.keys.intersection(KeyMask::DPAD);
+    held.contains(self.keys)
+      && !new_presses.intersection(self.keys).is_empty()
+      && (dpad.is_empty() || same_keys(held.intersection(KeyMask::DPAD), dpad))
+  }
+}
+
+#[inline]
+#[must_use]
+const fn same_keys(a: KeyMask, b: KeyMask) -> bool {
+  a.contains(b) && b.contains(a)
+}
+
+/// A sequence of [ComboStep] values that a [ComboMatcher] looks for.
+///
+/// A combo with only a single step is a "chord": all the keys of the step
+/// pressed together. Something like a soft-reset chord would be written as
+/// ```no_run
+/// # use gba2k::keys::*;
+/// const SOFT_RESET: Combo<'static> = Combo::new(&[ComboStep::new(
+///   KeyMask::A.union(KeyMask::B).union(KeyMask::SELECT).union(KeyMask::START),
+///   0,
+/// )]);
+/// ```
+///
+/// A motion input is written as several steps, each with a frame window:
+/// ```no_run
+/// # use gba2k::keys::*;
+/// const FIREBALL: Combo<'static> = Combo::new(&[
+///   ComboStep::new(KeyMask::DOWN, 0),
+///   ComboStep::new(KeyMask::DOWN.union(KeyMask::RIGHT), 8),
+///   ComboStep::new(KeyMask::RIGHT.union(KeyMask::A), 8),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Combo<'a> {
+  steps: &'a [ComboStep],
+}
+
+impl<'a> Combo<'a> {
+  /// Makes a new combo.
+  ///
+  /// ## Panics
+  /// * There must be at least 1 step, and no more than 255 steps.
+  #[inline]
+  #[must_use]
+  pub const fn new(steps: &'a [ComboStep]) -> Self {
+    assert!(!steps.is_empty());
+    assert!(steps.len() <= u8::MAX as usize);
+    Self { steps }
+  }
+
+  /// The steps of this combo.
+  #[inline]
+  #[must_use]
+  pub const fn steps(self) -> &'a [ComboStep] {
+    self.steps
+  }
+}
+
+/// How far along a combo is within a [ComboMatcher].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct ComboProgress {
+  /// The index of the next step to perform.
+  next_step: u8,
+  /// Frames since the most recent step was performed.
+  frames: u16,
+}
+
+/// Watches the per-frame key state for any of several combos.
+///
+/// The combos are given in priority order, highest priority first. Call
+/// [`update`](ComboMatcher::update) once per frame with that frame's
+/// [KeyInput]. If one or more combos are completed on the same frame then the
+/// one with the highest priority is reported.
+///
+/// Each step must be performed within its window after the step before it.
+/// Pressing a key that isn't part of the next step breaks the combo, unless
+/// that press performs the combo's first step, which starts it over.
+///
+/// This is all plain logic with no hardware access, so you can drive a matcher
+/// with any sequence of `KeyInput` values you like.
+#[derive(Debug, Clone)]
+pub struct ComboMatcher<'a, const N: usize> {
+  combos: [Combo<'a>; N],
+  progress: [ComboProgress; N],
+  previous: KeyMask,
+}
+
+impl<'a, const N: usize> ComboMatcher<'a, N> {
+  /// Makes a new matcher for the combos given, in priority order.
+  #[inline]
+  #[must_use]
+  pub const fn new(combos: [Combo<'a>; N]) -> Self {
+    Self {
+      combos,
+      progress: [ComboProgress { next_step: 0, frames: 0 }; N],
+      previous: KeyMask::new(),
+    }
+  }
+
+  /// Gets the combos of this matcher.
+  #[inline]
+  #[must_use]
+  pub const fn combos(&self) -> &[Combo<'a>; N] {
+    &self.combos
+  }
+
+  /// Advances the matcher by one frame.
+  ///
+  /// **Returns:** the index of the highest priority combo completed this
+  /// frame, if any.
+  #[inline]
+  pub fn update(&mut self, keys: KeyInput) -> Option<usize> {
+    self.update_pressed(keys.pressed())
+  }
+
+  /// Advances the matcher by one frame, using the set of pressed keys.
+  ///
+  /// This lets you adjust the input before matching, such as with
+  /// [KeyMask::flipped_horizontal] when a character is facing left.
+  ///
+  /// **Returns:** the index of the highest priority combo completed this
+  /// frame, if any.
+  pub fn update_pressed(&mut self, held: KeyMask) -> Option<usize> {
+    let new_presses = held & !self.previous;
+    self.previous = held;
+    let mut completed = None;
+    for (i, (combo, progress)) in
+      self.combos.iter().zip(self.progress.iter_mut()).enumerate()
+    {
+      let steps = combo.steps;
+      progress.frames = progress.frames.saturating_add(1);
+      if progress.next_step > 0
+        && progress.frames > steps[usize::from(progress.next_step)].window
+      {
+        progress.next_step = 0;
+      }
+      let next = steps[usize::from(progress.next_step)];
+      if next.is_performed(held, new_presses) {
+        progress.next_step += 1;
+        progress.frames = 0;
+      } else if progress.next_step > 0 {
+        if steps[0].is_performed(held, new_presses) {
+          // This frame starts the combo over.
+          progress.next_step = 1;
+          progress.frames = 0;
+        } else if !next.keys.contains(new_presses) {
+          // A key that isn't part of the next step breaks the combo.
+          progress.next_step = 0;
+        }
+      }
+      if usize::from(progress.next_step) == steps.len() {
+        progress.next_step = 0;
+        completed = completed.or(Some(i));
+      }
+    }
+    completed
+  }
+
+  /// Clears all partial progress, as well as the previous frame's keys.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.progress = [ComboProgress::default(); N];
+    self.previous = KeyMask::new();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FIREBALL: Combo<'static> = Combo::new(&[
+    ComboStep::new(KeyMask::DOWN, 0),
+    ComboStep::new(KeyMask::DOWN.union(KeyMask::RIGHT), 8),
+    ComboStep::new(KeyMask::RIGHT.union(KeyMask::A), 8),
+  ]);
+
+  const SOFT_RESET: Combo<'static> = Combo::new(&[ComboStep::new(
+    KeyMask::L.union(KeyMask::R).union(KeyMask::SELECT).union(KeyMask::START),
+    0,
+  )]);
+
+  const JAB: Combo<'static> = Combo::new(&[ComboStep::new(RIGHT_A, 0)]);
+
+  const DOWN_RIGHT: KeyMask = KeyMask::DOWN.union(KeyMask::RIGHT);
+  const RIGHT_A: KeyMask = KeyMask::RIGHT.union(KeyMask::A);
+
+  /// The hardware bits for a set of held keys.
+  fn key_input(held: KeyMask) -> KeyInput {
+    KeyInput::from(!u16::from(held) & u16::from(KeyMask::ALL))
+  }
+
+  /// Runs the frames given, and gives the result of each frame.
+  fn run<const N: usize>(
+    matcher: &mut ComboMatcher<'_, N>, frames: &[KeyMask],
+  ) -> Vec<Option<usize>> {
+    frames.iter().map(|&held| matcher.update(key_input(held))).collect()
+  }
+
+  #[test]
+  fn key_input_is_low_active() {
+    let held = KeyMask::A.union(KeyMask::L);
+    assert_eq!(key_input(held).pressed(), held);
+    assert!(key_input(held).a() && !key_input(held).b());
+  }
+
+  #[test]
+  fn motion_in_order_completes() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let frames = [KeyMask::DOWN, DOWN_RIGHT, RIGHT_A];
+    assert_eq!(run(&mut matcher, &frames), [None, None, Some(0)]);
+    // The combo doesn't complete again while the keys are held.
+    assert_eq!(run(&mut matcher, &[RIGHT_A, RIGHT_A]), [None, None]);
+  }
+
+  #[test]
+  fn motion_out_of_order_does_not_complete() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let frames = [DOWN_RIGHT, KeyMask::DOWN, RIGHT_A];
+    assert_eq!(run(&mut matcher, &frames), [None; 3]);
+    let frames = [KeyMask::DOWN, RIGHT_A, DOWN_RIGHT];
+    assert_eq!(run(&mut matcher, &frames), [None; 3]);
+  }
+
+  #[test]
+  fn dpad_must_match_exactly() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    // Down-left isn't down.
+    let frames = [KeyMask::DOWN.union(KeyMask::LEFT), DOWN_RIGHT, RIGHT_A];
+    assert_eq!(run(&mut matcher, &frames), [None; 3]);
+  }
+
+  #[test]
+  fn steps_must_be_within_the_window() {
+    let mut frames = vec![KeyMask::DOWN];
+    frames.extend([KeyMask::DOWN; 7]);
+    frames.push(DOWN_RIGHT);
+    frames.extend([DOWN_RIGHT; 7]);
+    frames.push(RIGHT_A);
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let results = run(&mut matcher, &frames);
+    assert_eq!(results.last(), Some(&Some(0)));
+
+    // One more frame before the last step is too late.
+    frames.insert(frames.len() - 1, DOWN_RIGHT);
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    assert!(run(&mut matcher, &frames).iter().all(Option::is_none));
+  }
+
+  #[test]
+  fn wrong_key_resets() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let frames = [KeyMask::DOWN, KeyMask::DOWN.union(KeyMask::B), DOWN_RIGHT];
+    assert_eq!(run(&mut matcher, &frames), [None; 3]);
+    assert_eq!(run(&mut matcher, &[RIGHT_A]), [None]);
+
+    // A wrong key that performs the first step starts the combo over.
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let frames = [KeyMask::DOWN, KeyMask::UP, KeyMask::DOWN, DOWN_RIGHT];
+    assert_eq!(run(&mut matcher, &frames), [None; 4]);
+    assert_eq!(run(&mut matcher, &[RIGHT_A]), [Some(0)]);
+  }
+
+  #[test]
+  fn chord_completes_when_the_last_key_is_pressed() {
+    let mut matcher = ComboMatcher::new([SOFT_RESET]);
+    let l_r = KeyMask::L.union(KeyMask::R);
+    let l_r_select = l_r.union(KeyMask::SELECT);
+    let all = l_r_select.union(KeyMask::START);
+    let results = run(&mut matcher, &[KeyMask::L, l_r, l_r_select, all, all]);
+    assert_eq!(results, [None, None, None, Some(0), None]);
+  }
+
+  #[test]
+  fn highest_priority_combo_is_reported() {
+    let frames = [KeyMask::DOWN, DOWN_RIGHT, RIGHT_A];
+    let mut matcher = ComboMatcher::new([FIREBALL, JAB]);
+    assert_eq!(run(&mut matcher, &frames), [None, None, Some(0)]);
+    let mut matcher = ComboMatcher::new([JAB, FIREBALL]);
+    assert_eq!(run(&mut matcher, &frames), [None, None, Some(0)]);
+    assert_eq!(matcher.combos()[0], JAB);
+  }
+
+  #[test]
+  fn flipped_input_matches_facing_left() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    let frames = [
+      KeyMask::DOWN,
+      KeyMask::DOWN.union(KeyMask::LEFT),
+      KeyMask::LEFT.union(KeyMask::A),
+    ];
+    let results: Vec<_> = frames
+      .iter()
+      .map(|held| matcher.update_pressed(held.flipped_horizontal()))
+      .collect();
+    assert_eq!(results, [None, None, Some(0)]);
+  }
+
+  #[test]
+  fn reset_clears_progress() {
+    let mut matcher = ComboMatcher::new([FIREBALL]);
+    run(&mut matcher, &[KeyMask::DOWN, DOWN_RIGHT]);
+    matcher.reset();
+    assert_eq!(run(&mut matcher, &[RIGHT_A]), [None]);
+  }
+}
@@ -1,10 +1,12 @@
 use voladdress::*;
 
+use super::KeyMask;
+
 /// Key interrupt control.
 ///
 /// See [KeyControl].
 pub const KEYCNT: VolAddress<KeyControl, Safe, Safe> =
-  unsafe { VolAddress::new(0x0400_0000) };
+  unsafe { VolAddress::new(0x0400_0132) };
 
 /// Configures when the keypad should send an interrupt.
 ///
@@ -16,8 +18,9 @@ pub const KEYCNT: VolAddress<KeyControl, Safe, Safe> =
 /// trigger the interrupt.
 ///
 /// They key interrupt is advised only for breaking out of the low-power
-/// [`Halt`](crate::bios::Halt) state. You should not use it as a way to read
-/// regular user input.
+/// [`Halt`](crate::bios::Halt) and [`Stop`](crate::bios::Stop) states. You
+/// should not use it as a way to read regular user input. See
+/// [sleep_until_keys](crate::keys::sleep_until_keys) for a full sleep mode.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 pub struct KeyControl(u16);
@@ -36,4 +39,34 @@ impl KeyControl {
   u16_bool_field!(9, l, with_l);
   u16_bool_field!(14, irq_enable, with_irq_enable);
   u16_bool_field!(15, all_required, with_all_required);
+
+  /// The keys selected by this key control, as a [KeyMask].
+  #[inline]
+  #[must_use]
+  pub const fn keys(self) -> KeyMask {
+    KeyMask(self.0 & KeyMask::ALL.0)
+  }
+
+  /// Replaces the keys selected, leaving the other settings unchanged.
+  #[inline]
+  #[must_use]
+  pub const fn with_keys(self, keys: KeyMask) -> Self {
+    Self((self.0 & !KeyMask::ALL.0) | (keys.0 & KeyMask::ALL.0))
+  }
+}
+
+impl From<KeyControl> for u16 {
+  #[inline]
+  #[must_use]
+  fn from(c: KeyControl) -> Self {
+    c.0
+  }
+}
+
+impl From<u16> for KeyControl {
+  #[inline]
+  #[must_use]
+  fn from(u: u16) -> Self {
+    Self(u)
+  }
 }
@@ -33,6 +33,13 @@ impl KeyInput {
   pub const fn changes_since(self, previous: KeyInput) -> KeyChanges {
     KeyChanges(self.0 ^ previous.0)
   }
+
+  /// The set of keys that are currently pressed.
+  #[inline]
+  #[must_use]
+  pub const fn pressed(self) -> KeyMask {
+    KeyMask(!self.0 & KeyMask::ALL.0)
+  }
 }
 
 impl From<KeyInput> for u16 {
@@ -78,3 +85,105 @@ impl KeyChanges {
   u16_bool_field!(8, r, with_r);
   u16_bool_field!(9, l, with_l);
 }
+
+/// A set of keys.
+///
+/// Unlike [KeyInput], each bit of a key mask is 1 when the key is part of the
+/// set and 0 when it's not, so the default value is the empty set. You can get
+/// the set of currently pressed keys with [KeyInput::pressed].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct KeyMask(pub(super) u16);
+
+impl_bitops_for!(KeyMask);
+
+#[allow(missing_docs)]
+impl KeyMask {
+  pub_const_fn_new!();
+  u16_bool_field!(0, a, with_a);
+  u16_bool_field!(1, b, with_b);
+  u16_bool_field!(2, select, with_select);
+  u16_bool_field!(3, start, with_start);
+  u16_bool_field!(4, right, with_right);
+  u16_bool_field!(5, left, with_left);
+  u16_bool_field!(6, up, with_up);
+  u16_bool_field!(7, down, with_down);
+  u16_bool_field!(8, r, with_r);
+  u16_bool_field!(9, l, with_l);
+
+  pub const A: Self = Self::new().with_a(true);
+  pub const B: Self = Self::new().with_b(true);
+  pub const SELECT: Self = Self::new().with_select(true);
+  pub const START: Self = Self::new().with_start(true);
+  pub const RIGHT: Self = Self::new().with_right(true);
+  pub const LEFT: Self = Self::new().with_left(true);
+  pub const UP: Self = Self::new().with_up(true);
+  pub const DOWN: Self = Self::new().with_down(true);
+  pub const R: Self = Self::new().with_r(true);
+  pub const L: Self = Self::new().with_l(true);
+
+  /// All four directions of the d-pad.
+  pub const DPAD: Self =
+    Self::RIGHT.union(Self::LEFT).union(Self::UP).union(Self::DOWN);
+
+  /// All ten keys of the GBA.
+  pub const ALL: Self = Self(0b11_1111_1111);
+
+  /// The set of keys in either `self` or `other`.
+  ///
+  /// This is the same as `|`, but usable in a `const` context.
+  #[inline]
+  #[must_use]
+  pub const fn union(self, other: Self) -> Self {
+    Self(self.0 | other.0)
+  }
+
+  /// The set of keys in both `self` and `other`.
+  ///
+  /// This is the same as `&`, but usable in a `const` context.
+  #[inline]
+  #[must_use]
+  pub const fn intersection(self, other: Self) -> Self {
+    Self(self.0 & other.0)
+  }
+
+  /// If every key of `other` is also in `self`.
+  #[inline]
+  #[must_use]
+  pub const fn contains(self, other: Self) -> bool {
+    (self.0 & other.0) == other.0
+  }
+
+  /// If no keys are in the set.
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+
+  /// Swaps left and right.
+  ///
+  /// This lets you write "forward" and "back" inputs once, for a character
+  /// facing right, and then flip the input when the character faces left.
+  #[inline]
+  #[must_use]
+  pub const fn flipped_horizontal(self) -> Self {
+    self.with_left(self.right()).with_right(self.left())
+  }
+}
+
+impl From<KeyMask> for u16 {
+  #[inline]
+  #[must_use]
+  fn from(mask: KeyMask) -> Self {
+    mask.0
+  }
+}
+
+impl From<u16> for KeyMask {
+  #[inline]
+  #[must_use]
+  fn from(u: u16) -> Self {
+    Self(u)
+  }
+}
@@ -15,9 +15,20 @@
 //! the user can get inconsistent behavior when an early part of the frame's
 //! computation thinks a button is pressed while later on in the same frame it's
 //! released.
+//!
+//! For inputs that span several frames, such as motion inputs or button
+//! chords, see [ComboMatcher].
 
 mod key_input;
 pub use key_input::*;
 
 mod key_control;
 pub use key_control::*;
+
+mod combo;
+pub use combo::*;
+
+#[cfg(target_arch = "arm")]
+mod sleep;
+#[cfg(target_arch = "arm")]
+pub use sleep::*;
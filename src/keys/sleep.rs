@@ -0,0 +1,95 @@
+use crate::{
+  bios::Stop,
+  interrupts::{IrqBits, IE, IF, IME},
+  sound::{PsgMix, SOUNDCNT_H, SOUNDCNT_L},
+  video::{DISPCNT, VCOUNT},
+};
+
+use super::{KeyControl, KeyMask, KEYCNT, KEYINPUT};
+
+/// Puts the GBA to sleep until the given keys are pressed.
+///
+/// This is the "sleep mode" that many games offer, letting the player set down
+/// the GBA for a while without losing their progress. While asleep the GBA
+/// uses the [`Stop`] state, so the CPU, video, sound, and timers are all
+/// stopped. The GBA only wakes when the key interrupt configured by `wake`
+/// occurs. The `irq_enable` bit of `wake` is set for you.
+///
+/// The steps taken are:
+/// 1. Wait until none of the wake keys are held. Often the same key combo is
+///    used to enter and exit sleep, and the key interrupt would otherwise fire
+///    right away.
+/// 2. Save the current [`DISPCNT`], [`SOUNDCNT_L`], [`SOUNDCNT_H`], [`KEYCNT`],
+///    [`IE`], and [`IME`] values.
+/// 3. Turn off the display with forced blank, take every sound channel off of
+///    both speakers, set `KEYCNT` to `wake`, set `IE` to only the keypad
+///    interrupt, and set `IME` off.
+/// 4. Call [`Stop`].
+/// 5. Acknowledge the keypad interrupt, then restore all the saved values.
+/// 6. Wait until none of the wake keys are held again, so that the game doesn't
+///    see the wake keys as new input (and possibly go right back to sleep).
+///
+/// Because `IME` is off while the GBA is asleep, the interrupt handler does
+/// *not* run for the wake-up key interrupt.
+///
+/// The sound is silenced rather than turned off with `SOUNDCNT_X`, because
+/// turning it off would reset all of the other sound registers. So any sound
+/// that was playing picks up again where it stopped.
+///
+/// ## Panics
+/// * `wake` must select at least one key.
+pub fn sleep_until_keys(wake: KeyControl) {
+  let wake_keys = wake.keys();
+  assert!(!wake_keys.is_empty());
+
+  wait_for_release(wake_keys);
+
+  let prev_ime = IME.read();
+  IME.write(false);
+  let prev_ie = IE.read();
+  let prev_keycnt = KEYCNT.read();
+  let prev_dispcnt = DISPCNT.read();
+  let prev_psg_mix = SOUNDCNT_L.read();
+  let prev_sound_mix = SOUNDCNT_H.read();
+
+  DISPCNT.write(prev_dispcnt.with_forced_blank(true));
+  SOUNDCNT_L.write(PsgMix::new());
+  SOUNDCNT_H.write(
+    prev_sound_mix
+      .with_dma_a_right(false)
+      .with_dma_a_left(false)
+      .with_dma_b_right(false)
+      .with_dma_b_left(false),
+  );
+  KEYCNT.write(wake.with_irq_enable(true));
+  IE.write(IrqBits::KEYPAD);
+
+  Stop();
+
+  IF.write(IrqBits::KEYPAD);
+  KEYCNT.write(prev_keycnt);
+  IE.write(prev_ie);
+  SOUNDCNT_H.write(prev_sound_mix);
+  SOUNDCNT_L.write(prev_psg_mix);
+  DISPCNT.write(prev_dispcnt);
+  IME.write(prev_ime);
+
+  wait_for_release(wake_keys);
+}
+
+/// Waits until none of the keys given are held.
+///
+/// To get past any key bounce, the keys must stay released for a full frame.
+fn wait_for_release(keys: KeyMask) {
+  loop {
+    while !KEYINPUT.read().pressed().intersection(keys).is_empty() {}
+    // The scanline counter keeps going even during forced blank, so we can
+    // use it to wait out one frame.
+    let start = VCOUNT.read();
+    while VCOUNT.read() == start {}
+    while VCOUNT.read() != start {}
+    if KEYINPUT.read().pressed().intersection(keys).is_empty() {
+      return;
+    }
+  }
+}
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 //#![warn(missing_docs)]
 #![warn(missing_copy_implementations)]
 #![warn(missing_debug_implementations)]
@@ -12,24 +12,64 @@
 //!
 //! * All of the crate's safety calculations assume that you're using our
 //!   provided linker script, and using the `thumbv4t-none-eabi` target.
-//! * On any other target you *can* still render the crate's docs, but you
-//!   generally won't be able to compile the crate. Even if you can, all MMIO
-//!   and inline assembly would be incorrect to run.
+//! * On any other target you *can* still render the crate's docs and compile
+//!   the crate, but the modules that use ARM assembly (`bios`, `coroutine`,
+//!   `executor`, `rt0`, and the drivers built on them) are left out. This is so
+//!   that the hardware independent parts (protocols, save formats, and so on)
+//!   can be tested on the host with `cargo test`. All MMIO would still be
+//!   incorrect to use there.
+//!
+//! ## Memory Sections
+//!
+//! By default, code and read-only data stay in ROM, and mutable statics go in
+//! IWRAM. You can use `#[link_section]` to place an item somewhere else. The
+//! linker script understands the following section names (as well as any name
+//! that starts with one of these followed by a `.`):
+//!
+//! * `.iwram`: Code or data in IWRAM. It's copied from ROM during startup.
+//!   IWRAM is the fastest memory, but there's only 32K of it.
+//! * `.ewram`: Data in EWRAM. It's copied from ROM during startup.
+//! * `.ewram_text`: Code in EWRAM. It's copied from ROM during startup. EWRAM
+//!   code runs a little faster than ROM code, but much slower than IWRAM code.
+//! * `.ewram_bss`: Zeroed data in EWRAM. This is zeroed during startup, and the
+//!   initial value in your program is ignored, so only use this for statics
+//!   that start as all zeroes.
+//!
+//! ```no_run
+//! #[link_section = ".ewram_bss"]
+//! static mut BIG_BUFFER: [u32; 8192] = [0; 8192];
+//! ```
 
 #[macro_use]
+#[cfg_attr(not(target_arch = "arm"), allow(unused_macros))]
 mod macros;
 
+#[cfg(target_arch = "arm")]
 pub mod bios;
+#[cfg(target_arch = "arm")]
+pub mod coroutine;
+#[cfg(target_arch = "arm")]
+pub mod executor;
+pub mod gpio;
 pub mod interrupts;
 pub mod keys;
+pub mod log;
+pub mod peripherals;
+#[cfg(target_arch = "arm")]
 pub mod rt0;
+pub mod save;
+pub mod serial;
+pub mod sound;
+pub mod system;
 pub mod video;
 
+#[cfg(target_arch = "arm")]
 #[inline]
 pub fn swp(word: u32, addr: &mut u32) -> u32 {
   unsafe { a32_swp_r0_r0_r1(word, addr) }
 }
 
+#[cfg(target_arch = "arm")]
 #[naked]
 #[instruction_set(arm::a32)]
 pub unsafe extern "C" fn a32_swpb_r0_r0_r1(byte: u8, addr: *mut u8) -> u8 {
@@ -42,6 +82,7 @@ pub unsafe extern "C" fn a32_swpb_r0_r0_r1(byte: u8, addr: *mut u8) -> u8 {
   }
 }
 
+#[cfg(target_arch = "arm")]
 #[naked]
 #[instruction_set(arm::a32)]
 pub unsafe extern "C" fn a32_swp_r0_r0_r1(word: u32, addr: *mut u32) -> u32 {
@@ -54,6 +95,7 @@ pub unsafe extern "C" fn a32_swp_r0_r0_r1(word: u32, addr: *mut u32) -> u32 {
   }
 }
 
+#[cfg(target_arch = "arm")]
 #[naked]
 pub unsafe extern "C" fn t32_bx_r3<A, B, C, R>(
   a: A, b: B, c: C, f: unsafe extern "C" fn(A, B, C) -> R,
@@ -0,0 +1,46 @@
+use ::log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use super::{backend, log_fmt, LogLevel};
+use crate::interrupts::free;
+
+/// A `log` crate logger that sends messages to the emulator's log.
+///
+/// `Trace` messages are logged at the [Debug](LogLevel::Debug) level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmulatorLogger;
+
+static LOGGER: EmulatorLogger = EmulatorLogger;
+
+impl Log for EmulatorLogger {
+  #[inline]
+  fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+    backend().is_some()
+  }
+
+  fn log(&self, record: &Record<'_>) {
+    let level = match record.level() {
+      Level::Error => LogLevel::Error,
+      Level::Warn => LogLevel::Warn,
+      Level::Info => LogLevel::Info,
+      Level::Debug | Level::Trace => LogLevel::Debug,
+    };
+    log_fmt(level, *record.args());
+  }
+
+  #[inline]
+  fn flush(&self) {}
+}
+
+/// Makes [EmulatorLogger] the `log` crate's logger, showing messages up to
+/// the level given.
+///
+/// ## Failure
+/// * If a logger was already set.
+pub fn init_log_crate(max_level: LevelFilter) -> Result<(), SetLoggerError> {
+  // Safety: The GBA has no threads, and interrupts are off.
+  free(|_| unsafe {
+    ::log::set_logger_racy(&LOGGER)?;
+    ::log::set_max_level_racy(max_level);
+    Ok(())
+  })
+}
@@ -0,0 +1,289 @@
+#![warn(missing_docs)]
+
+//! Debug messages to an emulator's log.
+//!
+//! Two emulators have a way for the game to print messages:
+//!
+//! * [mGBA](https://mgba.io) has debug registers. Messages show in its log
+//!   window (**Tools > View Logs**), or on the terminal when run with `-l 31`.
+//! * no$gba has a message port. Messages show in its debug message window.
+//!
+//! The [gba_println!](crate::gba_println), [gba_eprintln!](crate::gba_eprintln)
+//! and [gba_log!](crate::gba_log) macros format a message and send it to
+//! whichever emulator is running. They're only compiled into builds with
+//! `debug_assertions` on, so release builds don't pay for them.
+//! [LogWriter] can be used directly to log in any build.
+//!
+//! On real hardware (and other emulators) neither debug interface is found,
+//! and nothing is printed. The detection only touches unused I/O addresses,
+//! which the GBA ignores writes to.
+//!
+//! With the `log` cargo feature on, [init_log_crate] makes the emulator log
+//! the output of the `log` crate's macros.
+//!
+//! Messages logged from the interrupt handler while the main program is part
+//! way through a message can get mixed together with it.
+
+use core::fmt::{Arguments, Write};
+
+use voladdress::*;
+
+use crate::interrupts::GbaCell;
+
+#[cfg(feature = "log")]
+mod facade;
+#[cfg(feature = "log")]
+pub use facade::*;
+
+/// mGBA: Write `0xC0DE` to turn on the debug registers. It then reads as
+/// `0x1DEA`.
+const MGBA_DEBUG_ENABLE: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x04FF_F780) };
+/// mGBA: Write a [LogLevel] with bit 8 set to send the message.
+const MGBA_DEBUG_FLAGS: VolAddress<u16, (), Safe> =
+  unsafe { VolAddress::new(0x04FF_F700) };
+/// mGBA: The message text.
+const MGBA_DEBUG_STRING: VolBlock<u8, (), Safe, 256> =
+  unsafe { VolBlock::new(0x04FF_F600) };
+/// no$gba: The emulator ID, such as `"no$gba v3.05"`.
+const NOCASH_ID: VolBlock<u8, Safe, (), 16> =
+  unsafe { VolBlock::new(0x04FF_FA00) };
+/// no$gba: Each byte written is printed.
+const NOCASH_CHAR: VolAddress<u8, (), Safe> =
+  unsafe { VolAddress::new(0x04FF_FA1C) };
+
+/// The current [LogBackend], or one of these.
+static BACKEND: GbaCell<u8> = GbaCell::new(BACKEND_UNCHECKED);
+const BACKEND_UNCHECKED: u8 = 0;
+const BACKEND_NONE: u8 = 1;
+const BACKEND_MGBA: u8 = 2;
+const BACKEND_NOCASH: u8 = 3;
+
+/// The severity of a message.
+///
+/// mGBA filters and colors messages by level. no$gba has no levels, so the
+/// level's name goes at the start of the message instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum LogLevel {
+  /// In mGBA, this also pauses the game and shows the message in a pop up.
+  Fatal = 0,
+  Error = 1,
+  Warn = 2,
+  Info = 3,
+  Debug = 4,
+}
+
+impl LogLevel {
+  /// The level's name, in upper case.
+  #[inline]
+  #[must_use]
+  pub const fn name(self) -> &'static str {
+    match self {
+      Self::Fatal => "FATAL",
+      Self::Error => "ERROR",
+      Self::Warn => "WARN",
+      Self::Info => "INFO",
+      Self::Debug => "DEBUG",
+    }
+  }
+}
+
+/// An emulator debug interface that messages can go to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogBackend {
+  /// mGBA's debug registers.
+  Mgba,
+  /// no$gba's message port.
+  NoCash,
+}
+
+/// Finds out which debug interface is present, if any.
+///
+/// The check is only done the first time, after which the result is
+/// remembered. Finding mGBA turns on its debug registers.
+pub fn backend() -> Option<LogBackend> {
+  let mut found = BACKEND.read();
+  if found == BACKEND_UNCHECKED {
+    found = if detect_mgba() {
+      BACKEND_MGBA
+    } else if detect_nocash() {
+      BACKEND_NOCASH
+    } else {
+      BACKEND_NONE
+    };
+    BACKEND.write(found);
+  }
+  match found {
+    BACKEND_MGBA => Some(LogBackend::Mgba),
+    BACKEND_NOCASH => Some(LogBackend::NoCash),
+    _ => None,
+  }
+}
+
+/// Tries to turn on mGBA's debug registers.
+fn detect_mgba() -> bool {
+  MGBA_DEBUG_ENABLE.write(0xC0DE);
+  MGBA_DEBUG_ENABLE.read() == 0x1DEA
+}
+
+/// Checks for no$gba's emulator ID.
+fn detect_nocash() -> bool {
+  b"no$gba".iter().enumerate().all(|(i, &b)| NOCASH_ID.index(i).read() == b)
+}
+
+/// Writes a message to the emulator's log.
+///
+/// Text is sent to the emulator each time there's a newline, and when the
+/// writer is dropped, so each line is one message. mGBA can only take 256
+/// bytes at once, so a longer line is split into more than one message.
+///
+/// ```no_run
+/// # use gba2k::log::*;
+/// use core::fmt::Write;
+/// if let Some(mut w) = LogWriter::new(LogLevel::Warn) {
+///   let hp = 0;
+///   writeln!(w, "hp is {hp}").ok();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LogWriter {
+  backend: LogBackend,
+  level: LogLevel,
+  /// The number of bytes in the current message.
+  len: usize,
+}
+
+impl LogWriter {
+  /// Makes a writer for messages of the level given.
+  ///
+  /// **Returns:** `None` if there's no emulator debug interface.
+  #[inline]
+  pub fn new(level: LogLevel) -> Option<Self> {
+    backend().map(|backend| Self { backend, level, len: 0 })
+  }
+
+  /// The level of messages from this writer.
+  #[inline]
+  #[must_use]
+  pub fn level(&self) -> LogLevel {
+    self.level
+  }
+
+  /// Changes the level for the rest of the messages from this writer.
+  ///
+  /// If part of a message was already written, it's sent first.
+  #[inline]
+  pub fn set_level(&mut self, level: LogLevel) {
+    if self.len > 0 {
+      self.end_message();
+    }
+    self.level = level;
+  }
+
+  /// Sends the text written so far as one message.
+  pub fn end_message(&mut self) {
+    match self.backend {
+      LogBackend::Mgba => {
+        if let Some(end) = MGBA_DEBUG_STRING.get(self.len) {
+          end.write(0);
+        }
+        MGBA_DEBUG_FLAGS.write(self.level as u16 | 0x100);
+      }
+      LogBackend::NoCash => NOCASH_CHAR.write(b'\n'),
+    }
+    self.len = 0;
+  }
+
+  fn write_byte(&mut self, b: u8) {
+    if b == b'\n' {
+      self.end_message();
+      return;
+    }
+    match self.backend {
+      LogBackend::Mgba => {
+        if self.len == MGBA_DEBUG_STRING.len() {
+          self.end_message();
+        }
+        MGBA_DEBUG_STRING.index(self.len).write(b);
+      }
+      LogBackend::NoCash => {
+        if self.len == 0 {
+          self.level.name().bytes().for_each(|b| NOCASH_CHAR.write(b));
+          NOCASH_CHAR.write(b':');
+          NOCASH_CHAR.write(b' ');
+        }
+        NOCASH_CHAR.write(b);
+      }
+    }
+    self.len += 1;
+  }
+}
+
+impl Write for LogWriter {
+  #[inline]
+  fn write_str(&mut self, s: &str) -> core::fmt::Result {
+    s.bytes().for_each(|b| self.write_byte(b));
+    Ok(())
+  }
+}
+
+impl Drop for LogWriter {
+  #[inline]
+  fn drop(&mut self) {
+    if self.len > 0 {
+      self.end_message();
+    }
+  }
+}
+
+/// Formats a message and sends it to the emulator's log, if there's an
+/// emulator debug interface.
+///
+/// The macros call this. Unlike the macros, it works in every build.
+#[inline]
+pub fn log_fmt(level: LogLevel, args: Arguments<'_>) {
+  if let Some(mut w) = LogWriter::new(level) {
+    w.write_fmt(args).ok();
+  }
+}
+
+/// Logs a message to the emulator with the [LogLevel] given.
+///
+/// Takes the same arguments as `format!` after the level. Does nothing
+/// without `debug_assertions`.
+///
+/// ```no_run
+/// # use gba2k::log::LogLevel;
+/// let frame = 7;
+/// gba2k::gba_log!(LogLevel::Debug, "frame {frame}");
+/// ```
+#[macro_export]
+macro_rules! gba_log {
+  ($level:expr, $($arg:tt)*) => {
+    if cfg!(debug_assertions) {
+      $crate::log::log_fmt($level, format_args!($($arg)*));
+    }
+  };
+}
+
+/// Logs a message to the emulator at the `Info` level, like `println!`.
+///
+/// Does nothing without `debug_assertions`.
+#[macro_export]
+macro_rules! gba_println {
+  ($($arg:tt)*) => {
+    $crate::gba_log!($crate::log::LogLevel::Info, $($arg)*)
+  };
+}
+
+/// Logs a message to the emulator at the `Error` level, like `eprintln!`.
+///
+/// Does nothing without `debug_assertions`.
+#[macro_export]
+macro_rules! gba_eprintln {
+  ($($arg:tt)*) => {
+    $crate::gba_log!($crate::log::LogLevel::Error, $($arg)*)
+  };
+}
@@ -140,6 +140,23 @@ macro_rules! u16_bool_field {
   };
 }
 
+macro_rules! u8_bool_field {
+  ($bit:literal, $get_name:ident, $with_name: ident) => {
+    #[inline]
+    #[must_use]
+    #[allow(missing_docs)]
+    pub const fn $get_name(self) -> bool {
+      bitfrob::u8_get_bit::<$bit>(self.0)
+    }
+    #[inline]
+    #[must_use]
+    #[allow(missing_docs)]
+    pub const fn $with_name(self, val: bool) -> Self {
+      Self(bitfrob::u8_with_bit::<$bit>(self.0, val))
+    }
+  };
+}
+
 /// Works like [u16_bool_field!] but inverts the meaning on input/output so that
 /// a stored "0" is active and a stored "1" is inactive.
 macro_rules! u16_low_active_bool_field {
@@ -0,0 +1,139 @@
+use crate::gpio::GpioPins;
+
+/// Starts a conversion when set.
+const START: u16 = 0b0001;
+/// The serial clock.
+const SCK: u16 = 0b0010;
+/// The serial data, from the gyro.
+const DATA: u16 = 0b0100;
+/// The rumble motor.
+const RUMBLE: u16 = 0b1000;
+
+/// Reads a gyro sample, keeping the rumble motor as given.
+pub(crate) fn read_gyro<P: GpioPins + ?Sized>(
+  pins: &mut P, rumble: bool,
+) -> u16 {
+  let rumble = if rumble { RUMBLE } else { 0 };
+  pins.set_direction(START | SCK | RUMBLE);
+  pins.write(START | SCK | rumble);
+  // The gyro puts each bit out on a falling clock edge, high bit first.
+  let mut sample = 0;
+  for _ in 0..16 {
+    pins.write(rumble);
+    sample = (sample << 1) | u16::from(pins.read() & DATA != 0);
+    pins.write(SCK | rumble);
+  }
+  sample & 0xFFF
+}
+
+/// A driver for the gyro sensor in WarioWare: Twisted.
+///
+/// The gyro measures turning around the axis that goes through the screen.
+/// Each sample is 12 bits: about `0x6C0` when the GBA is still, going up while
+/// it turns one way and down while it turns the other way. The exact center
+/// varies, so take a sample at rest and compare against that.
+///
+/// The cartridge's rumble motor shares the GPIO port, so it's also controlled
+/// here.
+#[derive(Debug)]
+pub struct Gyro<P: GpioPins> {
+  pins: P,
+  rumble: bool,
+}
+
+impl<P: GpioPins> Gyro<P> {
+  /// Makes a driver using the pins given, with the motor off.
+  pub fn new(mut pins: P) -> Self {
+    pins.set_direction(START | SCK | RUMBLE);
+    pins.write(0);
+    Self { pins, rumble: false }
+  }
+
+  /// Takes a sample.
+  #[inline]
+  pub fn read(&mut self) -> u16 {
+    read_gyro(&mut self.pins, self.rumble)
+  }
+
+  /// Turns the rumble motor on or off.
+  #[inline]
+  pub fn set_rumble(&mut self, on: bool) {
+    self.rumble = on;
+    self.pins.write(if on { RUMBLE } else { 0 });
+  }
+
+  /// Turns the motor off, and gives back the pins.
+  #[inline]
+  pub fn into_pins(mut self) -> P {
+    self.set_rumble(false);
+    self.pins
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A gyro that shifts out `sample` on falling clock edges, high bit first,
+  /// remembering every write.
+  struct FakeGyro {
+    sample: u16,
+    shifted: u32,
+    outputs: u16,
+    pins: u16,
+    writes: Vec<u16>,
+  }
+
+  impl FakeGyro {
+    fn new(sample: u16) -> Self {
+      Self { sample, shifted: 0, outputs: 0, pins: 0, writes: Vec::new() }
+    }
+  }
+
+  impl GpioPins for FakeGyro {
+    fn set_direction(&mut self, outputs: u16) {
+      self.outputs = outputs;
+    }
+    fn write(&mut self, pins: u16) {
+      if self.pins & SCK != 0 && pins & SCK == 0 {
+        self.shifted += 1;
+      }
+      self.pins = pins;
+      self.writes.push(pins);
+    }
+    fn read(&mut self) -> u16 {
+      let bit = match self.shifted {
+        1..=16 => (self.sample >> (16 - self.shifted)) & 1,
+        _ => 0,
+      };
+      if bit != 0 {
+        DATA
+      } else {
+        0
+      }
+    }
+  }
+
+  #[test]
+  fn reads_16_bits_high_first() {
+    for rumble in [false, true] {
+      let mut gyro = FakeGyro::new(0xA6C5);
+      assert_eq!(read_gyro(&mut gyro, rumble), 0x6C5);
+      assert_eq!(gyro.shifted, 16);
+      assert_eq!(gyro.outputs, START | SCK | RUMBLE);
+      // The motor stays as it was the whole time.
+      let motor = if rumble { RUMBLE } else { 0 };
+      assert!(gyro.writes.iter().all(|&w| w & RUMBLE == motor));
+    }
+  }
+
+  #[test]
+  fn rumble_is_kept_between_reads() {
+    let mut gyro = Gyro::new(FakeGyro::new(0x06C0));
+    gyro.set_rumble(true);
+    assert_eq!(gyro.read(), 0x6C0);
+    assert_eq!(gyro.pins.pins & RUMBLE, RUMBLE);
+    let pins = gyro.into_pins();
+    assert_eq!(pins.pins, 0);
+  }
+}
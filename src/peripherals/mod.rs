@@ -0,0 +1,231 @@
+#![warn(missing_docs)]
+
+//! Drivers for extra hardware in some cartridges.
+//!
+//! Each driver is behind a cargo feature of the same name:
+//!
+//! * `rumble`: The rumble motor in WarioWare: Twisted and Drill Dozer.
+//! * `gyro`: The gyro sensor in WarioWare: Twisted.
+//! * `solar`: The solar sensor in the Boktai games.
+//! * `tilt`: The tilt sensor in Yoshi Topsy-Turvy and Koro Koro Puzzle.
+//!
+//! The rumble, gyro, and solar drivers use the [GPIO port](crate::gpio), and
+//! can be given any [GpioPins], like [Rtc](crate::gpio::Rtc) can.
+//!
+//! [CartPeripherals] finds out which of these the cartridge has, and gives
+//! one API for all of them. Games for these cartridges are often played on
+//! reproduction cartridges of the original boards, so detection goes by the
+//! game code in the ROM header first, and then probes for the hardware.
+
+use voladdress::*;
+
+use crate::gpio::{Gpio, GpioPins};
+
+#[cfg(feature = "gyro")]
+mod gyro;
+#[cfg(feature = "gyro")]
+pub use gyro::*;
+
+#[cfg(feature = "rumble")]
+mod rumble;
+#[cfg(feature = "rumble")]
+pub use rumble::*;
+
+#[cfg(feature = "solar")]
+mod solar;
+#[cfg(feature = "solar")]
+pub use solar::*;
+
+#[cfg(feature = "tilt")]
+mod tilt;
+#[cfg(feature = "tilt")]
+pub use tilt::*;
+
+/// The game code in the ROM header.
+const GAME_CODE: VolBlock<u8, Safe, (), 4> =
+  unsafe { VolBlock::new(0x0800_00AC) };
+
+/// Games (by the first three letters of the game code), and their hardware.
+const KNOWN_GAMES: &[([u8; 3], PeripheralSet)] = &[
+  // WarioWare: Twisted
+  (*b"RZW", PeripheralSet::new().with_rumble(true).with_gyro(true)),
+  // Drill Dozer
+  (*b"V49", PeripheralSet::new().with_rumble(true)),
+  // Boktai 1, 2, and 3
+  (*b"U3I", PeripheralSet::new().with_solar(true)),
+  (*b"U32", PeripheralSet::new().with_solar(true)),
+  (*b"U33", PeripheralSet::new().with_solar(true)),
+  // Yoshi Topsy-Turvy
+  (*b"KYG", PeripheralSet::new().with_tilt(true)),
+  // Koro Koro Puzzle: Happy Panechu!
+  (*b"KHP", PeripheralSet::new().with_tilt(true)),
+];
+
+/// Which cartridge peripherals are present.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PeripheralSet(u8);
+
+impl PeripheralSet {
+  pub_const_fn_new!();
+  u8_bool_field!(0, rumble, with_rumble);
+  u8_bool_field!(1, gyro, with_gyro);
+  u8_bool_field!(2, solar, with_solar);
+  u8_bool_field!(3, tilt, with_tilt);
+
+  /// The peripherals of the game with the game code given, if it's one of the
+  /// games that has any.
+  #[must_use]
+  pub fn from_game_code(code: [u8; 4]) -> Self {
+    KNOWN_GAMES
+      .iter()
+      .find(|(prefix, _)| code[..3] == prefix[..])
+      .map_or(Self::new(), |&(_, set)| set)
+  }
+
+  /// If no peripherals are present.
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+}
+
+/// Reads the game code from the ROM header.
+#[must_use]
+pub fn game_code() -> [u8; 4] {
+  core::array::from_fn(|i| GAME_CODE.index(i).read())
+}
+
+/// All of the cartridge's peripherals, through one API.
+///
+/// Each method returns `None` (or `false`) if the cartridge doesn't have that
+/// peripheral, so a game can call them all and use whatever is there. The
+/// methods only exist when the matching cargo feature is on.
+///
+/// ```no_run
+/// # use gba2k::peripherals::*;
+/// let mut cart = CartPeripherals::detect();
+/// # #[cfg(feature = "solar")]
+/// if let Some(light) = cart.light_level() {
+///   // brighter light gives a smaller number
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CartPeripherals<P: GpioPins = Gpio> {
+  pins: P,
+  found: PeripheralSet,
+  /// The gyro sample reads keep the motor in this state.
+  #[cfg_attr(not(feature = "gyro"), allow(dead_code))]
+  rumble: bool,
+}
+
+impl CartPeripherals<Gpio> {
+  /// Finds out which peripherals the cartridge has.
+  ///
+  /// The game code in the ROM header is checked first. If it isn't a known
+  /// game, this probes for the peripherals that can be safely probed for,
+  /// which is only the solar sensor. Rumble can't be detected, and probing for
+  /// the tilt sensor can change SRAM (see `Tilt::probe`), so for those use
+  /// [new](CartPeripherals::new) with the peripherals that you know are there.
+  pub fn detect() -> Self {
+    #[allow(unused_mut)]
+    let mut pins = Gpio::new();
+    #[allow(unused_mut)]
+    let mut found = PeripheralSet::from_game_code(game_code());
+    #[cfg(feature = "solar")]
+    if found.is_empty() && solar::probe_solar(&mut pins) {
+      found = found.with_solar(true);
+    }
+    Self::new(pins, found)
+  }
+}
+
+impl<P: GpioPins> CartPeripherals<P> {
+  /// Uses the pins given, with the peripherals given.
+  #[inline]
+  pub fn new(pins: P, found: PeripheralSet) -> Self {
+    Self { pins, found, rumble: false }
+  }
+
+  /// The peripherals that are present.
+  #[inline]
+  #[must_use]
+  pub fn found(&self) -> PeripheralSet {
+    self.found
+  }
+
+  /// Gives back the pins.
+  #[inline]
+  pub fn into_pins(self) -> P {
+    self.pins
+  }
+
+  /// Turns the rumble motor on or off.
+  ///
+  /// **Returns:** If there's a rumble motor.
+  #[cfg(feature = "rumble")]
+  pub fn set_rumble(&mut self, on: bool) -> bool {
+    if !self.found.rumble() {
+      return false;
+    }
+    self.rumble = on;
+    rumble::write_rumble(&mut self.pins, on);
+    true
+  }
+
+  /// Takes a gyro sample. See [Gyro].
+  #[cfg(feature = "gyro")]
+  pub fn gyro(&mut self) -> Option<u16> {
+    if !self.found.gyro() {
+      return None;
+    }
+    Some(gyro::read_gyro(&mut self.pins, self.rumble))
+  }
+
+  /// Reads the solar sensor. See [SolarSensor].
+  #[cfg(feature = "solar")]
+  pub fn light_level(&mut self) -> Option<u8> {
+    if !self.found.solar() {
+      return None;
+    }
+    Some(solar::read_solar(&mut self.pins))
+  }
+
+  /// Takes a tilt sample, and waits for it. See [Tilt].
+  #[cfg(feature = "tilt")]
+  pub fn tilt(&mut self) -> Option<TiltReading> {
+    if !self.found.tilt() {
+      return None;
+    }
+    Tilt::new().read()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn game_codes() {
+    let warioware = PeripheralSet::from_game_code(*b"RZWE");
+    assert!(warioware.rumble() && warioware.gyro());
+    assert!(!warioware.solar() && !warioware.tilt());
+    // The region letter doesn't matter.
+    assert_eq!(PeripheralSet::from_game_code(*b"RZWJ"), warioware);
+    assert_eq!(
+      PeripheralSet::from_game_code(*b"V49E"),
+      PeripheralSet::new().with_rumble(true)
+    );
+    assert_eq!(
+      PeripheralSet::from_game_code(*b"U33J"),
+      PeripheralSet::new().with_solar(true)
+    );
+    assert_eq!(
+      PeripheralSet::from_game_code(*b"KYGP"),
+      PeripheralSet::new().with_tilt(true)
+    );
+    assert!(PeripheralSet::from_game_code(*b"BPEE").is_empty());
+    assert!(PeripheralSet::from_game_code([0; 4]).is_empty());
+  }
+}
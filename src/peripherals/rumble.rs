@@ -0,0 +1,83 @@
+use crate::gpio::GpioPins;
+
+/// The rumble motor pin.
+const RUMBLE: u16 = 0b1000;
+/// The pins that a rumble cartridge's GBA drives. Pins 0 and 1 are the gyro's
+/// on WarioWare: Twisted, and are unused otherwise.
+const RUMBLE_OUTPUTS: u16 = 0b1011;
+
+/// Turns the rumble motor on or off.
+pub(crate) fn write_rumble<P: GpioPins + ?Sized>(pins: &mut P, on: bool) {
+  pins.set_direction(RUMBLE_OUTPUTS);
+  pins.write(if on { RUMBLE } else { 0 });
+}
+
+/// A driver for a cartridge rumble motor.
+///
+/// This is the motor in WarioWare: Twisted and Drill Dozer, on GPIO pin 3.
+/// For WarioWare: Twisted, use [Gyro](super::Gyro) instead, which also
+/// controls the motor.
+#[derive(Debug)]
+pub struct Rumble<P: GpioPins> {
+  pins: P,
+}
+
+impl<P: GpioPins> Rumble<P> {
+  /// Makes a driver using the pins given, with the motor off.
+  pub fn new(mut pins: P) -> Self {
+    write_rumble(&mut pins, false);
+    Self { pins }
+  }
+
+  /// Turns the motor on or off.
+  ///
+  /// Pulsing the motor on and off (for example, on for a frame and off for
+  /// two) makes a weaker rumble.
+  #[inline]
+  pub fn set(&mut self, on: bool) {
+    write_rumble(&mut self.pins, on);
+  }
+
+  /// Turns the motor off, and gives back the pins.
+  #[inline]
+  pub fn into_pins(mut self) -> P {
+    self.set(false);
+    self.pins
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Pins that remember what they were last set to.
+  #[derive(Default)]
+  struct Pins {
+    outputs: u16,
+    pins: u16,
+  }
+
+  impl GpioPins for Pins {
+    fn set_direction(&mut self, outputs: u16) {
+      self.outputs = outputs;
+    }
+    fn write(&mut self, pins: u16) {
+      self.pins = pins;
+    }
+    fn read(&mut self) -> u16 {
+      self.pins & self.outputs
+    }
+  }
+
+  #[test]
+  fn only_drives_pin_3() {
+    let mut rumble = Rumble::new(Pins::default());
+    assert_eq!((rumble.pins.outputs, rumble.pins.pins), (0b1011, 0));
+    rumble.set(true);
+    assert_eq!(rumble.pins.pins, 0b1000);
+    rumble.set(false);
+    assert_eq!(rumble.pins.pins, 0);
+    rumble.set(true);
+    assert_eq!(rumble.into_pins().pins, 0);
+  }
+}
@@ -0,0 +1,136 @@
+use crate::gpio::GpioPins;
+
+/// The counter's clock.
+const SCK: u16 = 0b0001;
+/// Resets the counter and takes a new light level.
+const RESET: u16 = 0b0010;
+/// Deselects the sensor when set.
+const CS: u16 = 0b0100;
+/// Set by the sensor once the counter reaches the light level.
+const FLAG: u16 = 0b1000;
+
+/// Reads the light level.
+pub(crate) fn read_solar<P: GpioPins + ?Sized>(pins: &mut P) -> u8 {
+  pins.set_direction(SCK | RESET | CS);
+  pins.write(RESET);
+  pins.write(0);
+  let mut count = 0_u8;
+  while pins.read() & FLAG == 0 && count < u8::MAX {
+    pins.write(SCK);
+    pins.write(0);
+    count += 1;
+  }
+  pins.write(CS);
+  count
+}
+
+/// Checks for a solar sensor.
+///
+/// After a reset the flag must be clear, and it must be set after counting
+/// all the way up. Without a sensor, the pins don't change.
+///
+/// The probe clocks pins 0 to 2, so any other chip on the port must ignore
+/// them while pin 2 (its chip select) is low. Afterwards all the pins are
+/// driven low and made inputs again, which is how the port starts out.
+pub(crate) fn probe_solar<P: GpioPins + ?Sized>(pins: &mut P) -> bool {
+  pins.set_direction(SCK | RESET | CS);
+  pins.write(RESET);
+  pins.write(0);
+  let before = pins.read() & FLAG;
+  for _ in 0..=u8::MAX {
+    pins.write(SCK);
+    pins.write(0);
+  }
+  let after = pins.read() & FLAG;
+  pins.write(0);
+  pins.set_direction(0);
+  before == 0 && after != 0
+}
+
+/// A driver for the solar sensor in the Boktai games.
+///
+/// The sensor compares a counter against the amount of light, so reading
+/// takes up to 255 steps. The result goes *down* as the light gets brighter:
+/// complete darkness is around `0xE8`, and direct sunlight is close to 0.
+#[derive(Debug)]
+pub struct SolarSensor<P: GpioPins> {
+  pins: P,
+}
+
+impl<P: GpioPins> SolarSensor<P> {
+  /// Makes a driver using the pins given.
+  #[inline]
+  pub fn new(pins: P) -> Self {
+    Self { pins }
+  }
+
+  /// Reads the light level.
+  #[inline]
+  pub fn read(&mut self) -> u8 {
+    read_solar(&mut self.pins)
+  }
+
+  /// Checks if there's a sensor.
+  #[inline]
+  pub fn probe(&mut self) -> bool {
+    probe_solar(&mut self.pins)
+  }
+
+  /// Gives back the pins.
+  #[inline]
+  pub fn into_pins(self) -> P {
+    self.pins
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A sensor whose flag sets after `level` clocks, remembering what the
+  /// pins were last set to.
+  struct Sensor {
+    level: u8,
+    count: u8,
+    outputs: u16,
+    pins: u16,
+  }
+
+  impl GpioPins for Sensor {
+    fn set_direction(&mut self, outputs: u16) {
+      self.outputs = outputs;
+    }
+    fn write(&mut self, pins: u16) {
+      if pins & RESET != 0 {
+        self.count = 0;
+      } else if pins & SCK != 0 && self.pins & SCK == 0 {
+        self.count = self.count.saturating_add(1);
+      }
+      self.pins = pins;
+    }
+    fn read(&mut self) -> u16 {
+      if self.count >= self.level {
+        FLAG
+      } else {
+        0
+      }
+    }
+  }
+
+  #[test]
+  fn probe_restores_pins() {
+    // A level of 0 means the flag never clears, like pins with no sensor.
+    for (level, found) in [(0xE8, true), (0, false)] {
+      let mut sensor = Sensor { level, count: 0, outputs: 0, pins: 0 };
+      assert_eq!(probe_solar(&mut sensor), found);
+      assert_eq!((sensor.outputs, sensor.pins), (0, 0));
+    }
+  }
+
+  #[test]
+  fn read_counts_to_level() {
+    let mut sensor = Sensor { level: 0x40, count: 0, outputs: 0, pins: 0 };
+    assert_eq!(read_solar(&mut sensor), 0x40);
+    assert_eq!(sensor.pins, CS);
+  }
+}
@@ -0,0 +1,105 @@
+use voladdress::*;
+
+/// Write `0x55` here, then `0xAA` to [TILT_START_2], to take a sample.
+const TILT_START_1: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8000) };
+const TILT_START_2: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8100) };
+const TILT_X_LOW: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8200) };
+/// Bits 0-3 are the top of X, and bit 7 is set once the sample is ready.
+const TILT_X_HIGH: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8300) };
+const TILT_Y_LOW: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8400) };
+const TILT_Y_HIGH: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0E00_8500) };
+
+/// How many times to check for a sample before giving up.
+const TILT_TIMEOUT: u32 = 0x1000;
+
+/// A sample from the tilt sensor.
+///
+/// Each axis is 12 bits, and is about `0x3A0` when the GBA is flat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TiltReading {
+  /// Tilting left and right.
+  pub x: u16,
+  /// Tilting forward and back.
+  pub y: u16,
+}
+
+/// A driver for the tilt sensor in Yoshi Topsy-Turvy (Yoshi's Universal
+/// Gravitation) and Koro Koro Puzzle.
+///
+/// The sensor isn't on the GPIO port. It's in the save memory area, so it
+/// needs the slow wait states that the rt0 sets for SRAM.
+///
+/// Taking a sample takes a while. Either [start](Tilt::start) a sample and
+/// [poll](Tilt::poll) for it on a later frame, or use [read](Tilt::read) to
+/// wait for it.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct Tilt {
+  _private: (),
+}
+
+impl Tilt {
+  /// Makes a driver.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { _private: () }
+  }
+
+  /// Starts taking a sample.
+  #[inline]
+  pub fn start(&self) {
+    TILT_START_1.write(0x55);
+    TILT_START_2.write(0xAA);
+  }
+
+  /// Gets the sample, if it's ready.
+  #[inline]
+  pub fn poll(&self) -> Option<TiltReading> {
+    let x_high = TILT_X_HIGH.read();
+    if x_high & 0x80 == 0 {
+      return None;
+    }
+    let x = u16::from(x_high & 0xF) << 8 | u16::from(TILT_X_LOW.read());
+    let y =
+      u16::from(TILT_Y_HIGH.read() & 0xF) << 8 | u16::from(TILT_Y_LOW.read());
+    Some(TiltReading { x, y })
+  }
+
+  /// Takes a sample, and waits for it.
+  ///
+  /// **Returns:** `None` if the sample never got ready.
+  pub fn read(&self) -> Option<TiltReading> {
+    self.start();
+    (0..TILT_TIMEOUT).find_map(|_| self.poll())
+  }
+
+  /// Checks if there's a sensor, by taking a sample.
+  ///
+  /// Without a sensor the reads usually give all 1 bits, which isn't a real
+  /// sample.
+  ///
+  /// This writes two bytes in the save memory area. On a cartridge with SRAM
+  /// those bytes of the save are overwritten, so only probe when the
+  /// cartridge doesn't have SRAM.
+  #[inline]
+  pub fn probe(&self) -> bool {
+    match self.read() {
+      Some(TiltReading { x, y }) => x != 0xFFF || y != 0xFFF,
+      None => false,
+    }
+  }
+}
+
+impl Default for Tilt {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
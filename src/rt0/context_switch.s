@@ -0,0 +1,47 @@
+/* unsafe extern "C" fn(save_sp: *mut u32, load_sp: u32) */
+.global rt0_context_switch
+/* Marked as a function so that the linker makes a Thumb to ARM interworking
+veneer when Thumb code calls it. */
+.type rt0_context_switch, %function
+
+/* This is never called directly, it's the first "return address" of every
+new coroutine's stack. */
+.global rt0_coroutine_start
+.type rt0_coroutine_start, %function
+
+/* import: extern "C" fn() -> ! */
+.global rt0_coroutine_finished
+
+.section ".text.rt0_context_switch"
+  .code 32
+  .balign 4
+  rt0_context_switch:
+    /* We save all the callee-saved registers and the return address on the
+    current stack, then switch stacks and pop the same registers off of the
+    other stack. That's 9 registers, so r3 is also saved just to keep the
+    stack 8-byte aligned. Everything else is caller-saved, so the compiler
+    already assumes that it's lost across the call.
+    * r0, save_sp
+    * r1, load_sp
+    */
+    push {r3-r11, lr} @push regs (old stack)
+    str sp, [r0]      @*save_sp=sp
+    mov sp, r1        @sp=load_sp
+    pop {r3-r11, lr}  @pop regs (new stack)
+    bx lr             @return (on the new stack)
+
+  rt0_coroutine_start:
+    /* A new coroutine's stack is set up so that the first switch to it pops
+    the entry fn into r4 and the entry argument into r5.
+    * r4, entry_fn
+    * r5, entry_arg
+    */
+    mov r0, r5
+    adr lr, 1f
+    bx r4
+    1:
+    /* If the entry fn returns, the coroutine is finished. */
+    ldr r0, =rt0_coroutine_finished
+    bx r0
+  .code 16
+.previous
@@ -9,21 +9,65 @@
   .balign 4
   __start:
     b system_init
-    /* This space can be overwritten later with actual header data by `gbafix`.
-    mgba doesn't actually run the header checksum that happens on hardware, so
-    just having sufficient blank space is fine for development builds. */
-    .space 0xE0
+.previous
+
+/* The header data from `gba_header!` goes between these two sections, and the
+linker script places this section at 0xC0 within the ROM. */
+.section .text.gba_rom_init
+  .code 32
+  .balign 4
+    /* The rest of the header is only used by multiboot programs. When a
+    program is sent over the link cable, the BIOS jumps to 0xC0 instead of 0x0,
+    and writes the boot mode and client number into the next two bytes. */
+    .L_multiboot_entry:
+      b system_init
+    .L_multiboot_boot_mode:
+      .byte 0
+    .L_multiboot_client_number:
+      .byte 0
+    .space 26
+    /* Joybus mode jumps here, but we don't do anything special for it. */
+    .L_joybus_entry:
+      b system_init
 
   system_init:
     /* We're gonna "pin" r12 to be MMIO_BASE during our initialization. */
     mov r12, #0x04000000
 
     .L_set_waitcnt:
-      /* This is the best default WAITCNT setting for most GBA carts.*/
+      /* The linker script defaults this to 0x4317, which is the best setting
+      for most GBA carts, but a program can define its own value. */
       add r0, r12, #0x204
-      ldr r1, =0x4317
+      ldr r1, =__waitcnt_boot_value
       strh r1, [r0]
 
+    .L_set_stacks:
+      /* The BIOS has already set a stack for each mode, but we set our own so
+      that their sizes can be configured in the linker script. We're in System
+      mode at boot, and we go back to System mode when we're done. */
+      mov r0, #0b11010010 /* IRQ mode, IRQ and FIQ masked */
+      msr CPSR_c, r0
+      ldr sp, =__irq_stack_top
+      mov r0, #0b11010011 /* SVC mode, IRQ and FIQ masked */
+      msr CPSR_c, r0
+      ldr sp, =__svc_stack_top
+      mov r0, #0b00011111 /* System mode */
+      msr CPSR_c, r0
+      ldr sp, =__sys_stack_top
+
+    .L_stack_canary_fill:
+      /* The stacks are all next to each other, so one DMA fills all of them
+      with the canary value. Nothing is on any stack yet, so this is fine. */
+      ldr r4, =__stacks_word_fill_count
+      ldr r0, =__sys_stack_bottom
+      add r3, r12, #0xD4 /* DMA3_BASE */
+      adr r2, .L_stack_canary
+      str r2, [r3] /* set source */
+      str r0, [r3, #4] /* set destination */
+      strh r4, [r3, #8] /* set word count */
+      mov r5, #0x8500 /* 32-bit transfers, Fixed Source, DMA Enabled */
+      strh r5, [r3, #10] /* set control bits */
+
     .L_iwram_copy:
       /* If our iwram copy is 0 words we skip this bit. But, it's almost never
       gonna be zero words. */
@@ -56,6 +100,42 @@
       bne .L_write_loop
       1:
 
+    .L_ewram_copy:
+      /* This works just like the iwram copy. EWRAM is 256K, so the word count
+      can be as much as 0x10000. That's 0 when stored as a 16-bit value, but
+      DMA3 treats a word count of 0 as 0x10000, so it still works. */
+      ldr r4, =__ewram_word_copy_count
+      cmp r4, #0
+      beq 1f
+      ldr r0, =__ewram_start
+      add r3, r12, #0xD4 /* DMA3_BASE */
+      ldr r2, =__ewram_position_in_rom
+      str r2, [r3] /* set source */
+      str r0, [r3, #4] /* set destination */
+      strh r4, [r3, #8] /* set word count */
+      mov r5, #0x8400 /* 32-bit transfers, DMA Enabled */
+      strh r5, [r3, #10] /* set control bits */
+      1:
+
+    .L_ewram_bss_zeroing:
+      /* DMA can fill memory by using a fixed source address, so we put a zero
+      on the stack and use that as the source. */
+      ldr r4, =__ewram_bss_word_clear_count
+      cmp r4, #0
+      beq 1f
+      ldr r0, =__ewram_bss_start
+      add r3, r12, #0xD4 /* DMA3_BASE */
+      mov r2, #0
+      push {r2}
+      str sp, [r3] /* set source */
+      str r0, [r3, #4] /* set destination */
+      strh r4, [r3, #8] /* set word count */
+      mov r5, #0x8500 /* 32-bit transfers, Fixed Source, DMA Enabled */
+      strh r5, [r3, #10] /* set control bits */
+      /* The CPU is paused during the DMA, so the zero is used up by now. */
+      add sp, sp, #4
+      1:
+
     .L_set_rt0_interrupt_handler:
       ldr r1, =rt0_irq_handler
       str r1, [r12, #-4]
@@ -72,6 +152,10 @@
   the constants inserted by the assembler when we use "ldr reg,=label" and
   similar will end up going just after this label, and then objdump will list
   all those constants in their own paragraph. */
+  .L_stack_canary:
+    /* This must match `rt0::STACK_CANARY` */
+    .word 0xDEADBEEF
+
   system_init_literal_pool:
   .code 16
 .previous
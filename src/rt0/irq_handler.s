@@ -7,6 +7,33 @@
     .space 4
 .previous
 
+/* RUST_IRQ_WAKE_FLAGS: IrqBits = IrqBits::new(); */
+.global RUST_IRQ_WAKE_FLAGS
+
+.section ".bss.rust_irq_wake_flags"
+  .balign 4
+  RUST_IRQ_WAKE_FLAGS:
+    .space 4
+.previous
+
+/* RUST_IRQ_NESTED_MASK: IrqBits = IrqBits::new(); */
+.global RUST_IRQ_NESTED_MASK
+
+.section ".bss.rust_irq_nested_mask"
+  .balign 4
+  RUST_IRQ_NESTED_MASK:
+    .space 4
+.previous
+
+/* RUST_IRQ_HANDLER_TABLE: [Option<extern "C" fn()>; 14] = [None; 14]; */
+.global RUST_IRQ_HANDLER_TABLE
+
+.section ".bss.rust_irq_handler_table"
+  .balign 4
+  RUST_IRQ_HANDLER_TABLE:
+    .space 4*14
+.previous
+
 /* This fn can only be called by the GBA's BIOS IRQ handling. */
 .global rt0_irq_handler
 
@@ -44,43 +71,113 @@
     * r0, irq_flags
     */
 
+    .L_read_update_wake_flags:
+    ldr  r2, =RUST_IRQ_WAKE_FLAGS
+    ldrh r1, [r2]            @r1=RUST_IRQ_WAKE_FLAGS
+    orr  r1, r1, r0          @r1=r1|r0
+    strh r1, [r2]            @RUST_IRQ_WAKE_FLAGS=r1
+    /* Still Important
+    * r12, IME
+    * r3, ime_previous
+    * r0, irq_flags
+    */
+
     .L_get_rust_fn_ptr:
+    cmp r0, #0         @if no irq_flags
+    beq .L_end_of_rt0  @then branch
     ldr r1, =RUST_IRQ_HANDLER
     ldr r1, [r1]       @r1==RUST_IRQ_HANDLER
-    cmp r1, #0         @if r1==0
-    beq .L_end_of_rt0  @then branch
     /* Still Important
     * r12, IME
     * r3, ime_previous
-    * r1, rust_irq_fn
+    * r1, rust_irq_fn (or 0 to use the table)
     * r0, irq_flags
     */
 
     .L_call_rust_fn_in_sys_mode:
-    mrs r2, SPSR      @save SPSR
-    push {r0, r2}     @push SPSR (SVC)
+    /* A nested interrupt would overwrite the SPSR and LR of IRQ mode, so we
+    carry both over to SYS mode and save them on the SYS mode stack. This also
+    keeps the IRQ mode stack usage down to just what the BIOS pushes. */
+    mrs r2, SPSR        @r2=SPSR
+    mov r12, lr         @r12=LR_irq
+    mov lr, #0b00011111 @ SYS mode + no masking
+    msr CPSR_cf, lr     @set SYS mode
+    /* Still Important
+    * r12, LR_irq
+    * r3, ime_previous
+    * r2, SPSR_irq
+    * r1, rust_irq_fn (or 0 to use the table)
+    * r0, irq_flags
+    */
 
-    mov r2, #0b00011111 @ SYS mode + no masking
-    msr CPSR_cf, r2   @set SYS mode
+    /* We need to push an even number of registers here. Other than the IRQ
+    mode values and lr (return_address), we need to save r3 (ime_previous), and
+    the table dispatch keeps its state in r4 and r5 across calls.
+    */
+    push {r2, r3, r4, r5, r12, lr} @push regs (SYS)
 
-    /* We need to push an even number of registers here. We also need to save,
-    at minimum, r3 (ime_previous) and lr (return_address). We could also save
-    r12 and any junk register, but that costs +2 cycles before *and* after the
-    call, and just rebuilding the r12 value after is only 2 cycles.
+    .L_enable_nested_irqs:
+    /* If any nested interrupts are allowed, IE is limited to just those
+    interrupts and IME is turned on while rust code runs. The interrupts that
+    we're currently handling are never allowed to nest.
+    * r12, &IE
+    * r3, ie_previous (when nesting)
+    * r2, ie_nested (0 when not nesting)
     */
-    push {r3, lr} @push regs (SYS)
+    mov r12, #0x04000000
+    add r12, r12, #0x200 @r12=&IE
+    ldr r2, =RUST_IRQ_NESTED_MASK
+    ldrh r2, [r2]        @r2=nested_mask
+    bics r2, r2, r0      @r2=nested_mask & !irq_flags
+    ldrhne r3, [r12]     @r3=IE
+    andsne r2, r2, r3    @r2=r2 & IE
+    strhne r2, [r12]     @IE=r2
+    movne lr, #1
+    strhne lr, [r12, #8] @IME=1
+    push {r2, r3}        @push nested state (SYS)
 
-    adr lr, 1f
+    cmp r1, #0                     @if there's no single handler
+    beq .L_dispatch_handler_table  @then use the table
+    adr lr, .L_done_calling_rust
     bx r1
+
+    .L_dispatch_handler_table:
+    /* Sources are checked from bit 0 (v-blank) up to bit 13 (game pak), which
+    is the same priority order that the hardware uses.
+    * r4, irq_flags not yet checked
+    * r5, table entry for the lowest bit of r4
+    */
+    mov r4, r0
+    ldr r5, =RUST_IRQ_HANDLER_TABLE
     1:
+    movs r4, r4, LSR #1 @shift the next source's bit into carry
+    bcc 2f              @skip sources that didn't fire
+    ldr r1, [r5]        @r1=table entry
+    cmp r1, #0          @if there's no handler
+    beq 2f              @then skip it
+    adr lr, 2f
+    bx r1
+    2:
+    add r5, r5, #4      @next table entry
+    cmp r4, #0          @if any irq_flags are left
+    bne 1b              @then keep going
+
+    .L_done_calling_rust:
+    pop {r2, r3}         @pop nested state (SYS)
+    cmp r2, #0           @if we were nesting
+    movne r12, #0x04000000
+    addne r12, r12, #0x200
+    movne r1, #0
+    strhne r1, [r12, #8] @then IME=0
+    strhne r3, [r12]     @and IE=ie_previous
 
-    pop {r3, lr} @pop regs (SYS)
+    pop {r2, r3, r4, r5, r12, lr} @pop regs (SYS)
 
-    mov r2, #0b10010010 @ SVC mode + IRQ masked
-    msr CPSR_cf, r2   @set SVC mode
+    mov r1, #0b10010010 @ IRQ mode + IRQ masked
+    msr CPSR_cf, r1     @set IRQ mode
 
-    pop {r0, r2}      @pop SPSR (SVC)
-    msr SPSR, r2      @restore SPSR
+    msr SPSR, r2        @restore SPSR
+    mov lr, r12         @restore LR_irq
     /* Still Important
     * r3, ime_previous
     */
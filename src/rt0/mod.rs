@@ -5,9 +5,15 @@
 //! This module also serves as the home of any other handwritten assembly files
 //! that the crate adds over time.
 
-use core::arch::global_asm;
+use core::{arch::global_asm, ptr::addr_of};
 
-use crate::interrupts::{GbaCell, IrqBits};
+use crate::{
+  interrupts::{GbaCell, IrqBits, IrqSource},
+  system::WaitControl,
+};
+
+mod rom_header;
+pub use rom_header::*;
 
 arm7tdmi_aeabi::generate_fns!(section_prefix = ".iwram");
 
@@ -20,8 +26,43 @@ global_asm! {
   include_str!("irq_handler.s"),
   options(raw)
 }
+
+global_asm! {
+  include_str!("context_switch.s"),
+  options(raw)
+}
+
+#[cfg(all(feature = "waitcnt-bios", feature = "waitcnt-cart-safe"))]
+compile_error!("only one `waitcnt-*` feature can be used at a time");
+
+// These override the linker script's default, and must match the presets of
+// the same name.
+#[cfg(feature = "waitcnt-bios")]
+global_asm! {
+  ".global __waitcnt_boot_value",
+  ".set __waitcnt_boot_value, 0x0000",
+}
+#[cfg(feature = "waitcnt-cart-safe")]
+global_asm! {
+  ".global __waitcnt_boot_value",
+  ".set __waitcnt_boot_value, 0x4303",
+}
 extern "C" {
   pub(crate) static RUST_IRQ_HANDLER: GbaCell<Option<extern "C" fn(IrqBits)>>;
+  /// Like the BIOS's `IntrWait` flags, the rt0 handler adds all interrupts
+  /// that occur to this value. Only the [executor](crate::executor) clears
+  /// it.
+  pub(crate) static RUST_IRQ_WAKE_FLAGS: GbaCell<IrqBits>;
+  pub(crate) static RUST_IRQ_NESTED_MASK: GbaCell<IrqBits>;
+  pub(crate) static RUST_IRQ_HANDLER_TABLE:
+    [GbaCell<Option<extern "C" fn()>>; 14];
+  pub(crate) fn rt0_context_switch(save_sp: *mut u32, load_sp: u32);
+  pub(crate) fn rt0_coroutine_start();
+  static __svc_stack_bottom: u32;
+  static __irq_stack_bottom: u32;
+  static __sys_stack_bottom: u32;
+  static __svc_stack_top: u32;
+  static __waitcnt_boot_value: u8;
 }
 /// Sets the rust function to run when a hardware interrupt occurs.
 ///
@@ -30,9 +71,153 @@ extern "C" {
 /// then call your handler with the [IrqBits] of what interrupt(s) just
 /// occurred.
 ///
+/// This single handler gives you full control over interrupt handling: while
+/// it's set, the per-source handlers of [set_irq_handler] are *not* called.
+///
 /// If you set the handler to `None` the rt0 handler will still acknowledge any
-/// interrupt, but will just skip calling back into rust.
+/// interrupt, and then it will call any per-source handlers instead.
 #[inline]
 pub fn set_rust_irq_handler(opt_f: Option<extern "C" fn(IrqBits)>) {
   unsafe { RUST_IRQ_HANDLER.write(opt_f) };
 }
+
+/// Sets the rust function to run when a particular interrupt source occurs.
+///
+/// There's one handler for each [IrqSource]. When more than one interrupt
+/// occurs at once, the handlers are called in priority order (from
+/// [IrqSource::VBlank] to [IrqSource::GamePak]), each one called at most once.
+///
+/// These handlers are only used while the [set_rust_irq_handler] handler is
+/// `None`. Setting a source's handler to `None` means that the interrupt is
+/// still acknowledged, but no rust code is called for it.
+#[inline]
+pub fn set_irq_handler(source: IrqSource, opt_f: Option<extern "C" fn()>) {
+  unsafe { RUST_IRQ_HANDLER_TABLE[source as usize].write(opt_f) };
+}
+
+/// Sets which interrupts can interrupt the rust interrupt handler(s).
+///
+/// By default this is empty, and `IME` stays off for the entire time that the
+/// rt0 handler is running, including while your rust code runs. That means a
+/// long v-blank handler will delay any other interrupt until it's done.
+///
+/// When the mask is non-empty, then while rust code is running the rt0
+/// handler limits [`IE`](crate::interrupts::IE) to just the interrupts in the
+/// mask and turns `IME` on. After the rust code returns, `IME` is turned off
+/// again and the previous `IE` value is restored. Any interrupt that was held
+/// back will happen as soon as the handler fully returns. The interrupts
+/// currently being handled are always left out of the nesting mask, so a
+/// handler is never interrupted by another call to itself.
+///
+/// Usually you'd set this to the interrupts that are more time-sensitive than
+/// the others, such as the timer driving your audio.
+///
+/// ## Stack Usage
+/// Every level of nested interrupt uses extra stack space:
+/// * 24 bytes of the IRQ mode stack, which the BIOS uses to save registers
+///   before it calls the rt0 handler. The default IRQ stack is only 160 bytes,
+///   so no more than about 6 interrupts should be nested at once unless you
+///   make the stack bigger (see [StackMode]).
+/// * 32 bytes of the SYS mode stack (the same stack as `main` uses), plus the
+///   stack used by your rust handler itself.
+#[inline]
+pub fn set_nested_irq_mask(mask: IrqBits) {
+  unsafe { RUST_IRQ_NESTED_MASK.write(mask) };
+}
+
+/// The value that unused stack space is filled with.
+///
+/// The rt0 fills the stack of each [StackMode] with this during startup.
+pub const STACK_CANARY: u32 = 0xDEAD_BEEF;
+
+/// A CPU mode that has its own stack.
+///
+/// The size of each stack is set in the linker script. The defaults are 4K
+/// for SYS, 160 bytes for IRQ, and 64 bytes for SVC, which are the same
+/// stacks that the BIOS would set up. To change a size, define its symbol
+/// anywhere in your program. Sizes must be multiples of 8.
+/// ```no_run
+/// core::arch::global_asm! {
+///   ".global __sys_stack_size",
+///   ".set __sys_stack_size, 0x2000",
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StackMode {
+  /// Used by the BIOS and the rt0 interrupt handler. Set the size with
+  /// `__irq_stack_size`.
+  Irq,
+  /// Used by BIOS functions. Set the size with `__svc_stack_size`.
+  Svc,
+  /// Used by `main`, and by any rust interrupt handlers. Set the size with
+  /// `__sys_stack_size`.
+  Sys,
+}
+
+impl StackMode {
+  /// All the modes, from the lowest stack in memory to the highest.
+  pub const ALL: [Self; 3] = [Self::Sys, Self::Irq, Self::Svc];
+
+  /// The lowest and highest address of this mode's stack.
+  #[inline]
+  #[must_use]
+  fn bounds(self) -> (*mut u32, *mut u32) {
+    // The linker script defines these symbols, only their addresses matter.
+    let (bottom, top) = match self {
+      Self::Sys => (addr_of!(__sys_stack_bottom), addr_of!(__irq_stack_bottom)),
+      Self::Irq => (addr_of!(__irq_stack_bottom), addr_of!(__svc_stack_bottom)),
+      Self::Svc => (addr_of!(__svc_stack_bottom), addr_of!(__svc_stack_top)),
+    };
+    (bottom.cast_mut(), top.cast_mut())
+  }
+}
+
+/// The size of a mode's stack, in bytes.
+#[inline]
+#[must_use]
+pub fn stack_size(mode: StackMode) -> usize {
+  let (bottom, top) = mode.bounds();
+  top as usize - bottom as usize
+}
+
+/// The most stack space a mode has ever used, in bytes.
+///
+/// This counts the stack words that don't hold the [STACK_CANARY] value
+/// anymore, starting from the bottom of the stack. Compare it with
+/// [read_sp!](crate::read_sp) for the current stack use.
+#[must_use]
+pub fn stack_high_water_mark(mode: StackMode) -> usize {
+  let (bottom, _) = mode.bounds();
+  let words = stack_size(mode) / 4;
+  let untouched = (0..words)
+    .take_while(|&i| unsafe { bottom.add(i).read_volatile() } == STACK_CANARY)
+    .count();
+  (words - untouched) * 4
+}
+
+/// Checks the lowest word of each mode's stack for the [STACK_CANARY].
+///
+/// Calling this once per frame will catch most stack overflows soon after
+/// they happen, though whatever is just below the overflowing stack may have
+/// already been damaged.
+///
+/// ## Panics
+/// * If any stack's canary was overwritten.
+pub fn check_stack_canaries() {
+  for mode in StackMode::ALL {
+    let (bottom, _) = mode.bounds();
+    let canary = unsafe { bottom.read_volatile() };
+    assert_eq!(canary, STACK_CANARY, "{mode:?} stack overflow");
+  }
+}
+
+/// The value that the rt0 put in [`WAITCNT`](crate::system::WAITCNT) at
+/// startup.
+///
+/// See [WaitControl] for how to choose this value.
+#[inline]
+#[must_use]
+pub fn boot_wait_control() -> WaitControl {
+  // The linker script defines this symbol, only its address matters.
+  WaitControl::from(addr_of!(__waitcnt_boot_value) as usize as u16)
+}
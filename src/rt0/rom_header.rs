@@ -0,0 +1,184 @@
+/// The Nintendo logo, which must be in the header for a ROM to boot.
+pub const NINTENDO_LOGO: [u8; 156] = [
+  0x24, 0xFF, 0xAE, 0x51, 0x69, 0x9A, 0xA2, 0x21, 0x3D, 0x84, 0x82, 0x0A, 0x84,
+  0xE4, 0x09, 0xAD, 0x11, 0x24, 0x8B, 0x98, 0xC0, 0x81, 0x7F, 0x21, 0xA3, 0x52,
+  0xBE, 0x19, 0x93, 0x09, 0xCE, 0x20, 0x10, 0x46, 0x4A, 0x4A, 0xF8, 0x27, 0x31,
+  0xEC, 0x58, 0xC7, 0xE8, 0x33, 0x82, 0xE3, 0xCE, 0xBF, 0x85, 0xF4, 0xDF, 0x94,
+  0xCE, 0x4B, 0x09, 0xC1, 0x94, 0x56, 0x8A, 0xC0, 0x13, 0x72, 0xA7, 0xFC, 0x9F,
+  0x84, 0x4D, 0x73, 0xA3, 0xCA, 0x9A, 0x61, 0x58, 0x97, 0xA3, 0x27, 0xFC, 0x03,
+  0x98, 0x76, 0x23, 0x1D, 0xC7, 0x61, 0x03, 0x04, 0xAE, 0x56, 0xBF, 0x38, 0x84,
+  0x00, 0x40, 0xA7, 0x0E, 0xFD, 0xFF, 0x52, 0xFE, 0x03, 0x6F, 0x95, 0x30, 0xF1,
+  0x97, 0xFB, 0xC0, 0x85, 0x60, 0xD6, 0x80, 0x25, 0xA9, 0x63, 0xBE, 0x03, 0x01,
+  0x4E, 0x38, 0xE2, 0xF9, 0xA2, 0x34, 0xFF, 0xBB, 0x3E, 0x03, 0x44, 0x78, 0x00,
+  0x90, 0xCB, 0x88, 0x11, 0x3A, 0x94, 0x65, 0xC0, 0x7C, 0x63, 0x87, 0xF0, 0x3C,
+  0xAF, 0xD6, 0x25, 0xE4, 0x8B, 0x38, 0x0A, 0xAC, 0x72, 0x21, 0xD4, 0xF8, 0x07,
+];
+
+/// The ROM header data, which goes just after the rt0's first instruction.
+///
+/// This covers bytes `0x04..0xC0` of the ROM. Usually you'd make one with the
+/// [gba_header!](crate::gba_header) macro, which places it correctly.
+///
+/// Each `with_` method fixes up the complement check for you, so a header made
+/// with these methods is always valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct RomHeader {
+  /// Must be [NINTENDO_LOGO].
+  pub logo: [u8; 156],
+  /// Uppercase ASCII, padded with zeroes.
+  pub title: [u8; 12],
+  /// Uppercase ASCII.
+  pub game_code: [u8; 4],
+  /// Uppercase ASCII.
+  pub maker_code: [u8; 2],
+  /// Must be `0x96`.
+  pub fixed_value: u8,
+  /// Should be 0.
+  pub main_unit_code: u8,
+  /// Should be 0.
+  pub device_type: u8,
+  /// Should be zeroes.
+  pub reserved1: [u8; 7],
+  /// The version number of the game.
+  pub software_version: u8,
+  /// The header's checksum. The BIOS won't boot the ROM if this is wrong.
+  pub complement_check: u8,
+  /// Should be zeroes.
+  pub reserved2: [u8; 2],
+}
+
+impl RomHeader {
+  /// Makes a header with the title and game code given.
+  ///
+  /// The maker code is `"00"` and the version is 0 unless you set them.
+  ///
+  /// ## Panics
+  /// * The title can be at most 12 bytes.
+  /// * The game code must be exactly 4 bytes.
+  /// * Both must be ASCII.
+  #[inline]
+  #[must_use]
+  pub const fn new(title: &str, game_code: &str) -> Self {
+    let mut header = Self {
+      logo: NINTENDO_LOGO,
+      title: [0; 12],
+      game_code: [0; 4],
+      maker_code: *b"00",
+      fixed_value: 0x96,
+      main_unit_code: 0,
+      device_type: 0,
+      reserved1: [0; 7],
+      software_version: 0,
+      complement_check: 0,
+      reserved2: [0; 2],
+    };
+    let title = ascii_bytes(title);
+    assert!(title.len() <= 12, "the title can be at most 12 bytes");
+    let mut i = 0;
+    while i < title.len() {
+      header.title[i] = title[i];
+      i += 1;
+    }
+    let game_code = ascii_bytes(game_code);
+    assert!(game_code.len() == 4, "the game code must be 4 bytes");
+    header.game_code = [game_code[0], game_code[1], game_code[2], game_code[3]];
+    header.with_checksum()
+  }
+
+  /// Sets the maker code.
+  ///
+  /// ## Panics
+  /// * The maker code must be exactly 2 ASCII bytes.
+  #[inline]
+  #[must_use]
+  pub const fn with_maker_code(mut self, maker_code: &str) -> Self {
+    let maker_code = ascii_bytes(maker_code);
+    assert!(maker_code.len() == 2, "the maker code must be 2 bytes");
+    self.maker_code = [maker_code[0], maker_code[1]];
+    self.with_checksum()
+  }
+
+  /// Sets the software version.
+  #[inline]
+  #[must_use]
+  pub const fn with_version(mut self, version: u8) -> Self {
+    self.software_version = version;
+    self.with_checksum()
+  }
+
+  /// Computes the complement check of the header's current data.
+  ///
+  /// This is the sum of ROM bytes `0xA0..=0xBC`, plus `0x19`, negated.
+  #[must_use]
+  pub const fn checksum(&self) -> u8 {
+    let sum = sum_bytes(&self.title)
+      .wrapping_add(sum_bytes(&self.game_code))
+      .wrapping_add(sum_bytes(&self.maker_code))
+      .wrapping_add(self.fixed_value)
+      .wrapping_add(self.main_unit_code)
+      .wrapping_add(self.device_type)
+      .wrapping_add(sum_bytes(&self.reserved1))
+      .wrapping_add(self.software_version);
+    0_u8.wrapping_sub(sum.wrapping_add(0x19))
+  }
+
+  #[inline]
+  #[must_use]
+  const fn with_checksum(mut self) -> Self {
+    self.complement_check = self.checksum();
+    self
+  }
+}
+
+#[inline]
+#[must_use]
+const fn sum_bytes(bytes: &[u8]) -> u8 {
+  let mut sum = 0_u8;
+  let mut i = 0;
+  while i < bytes.len() {
+    sum = sum.wrapping_add(bytes[i]);
+    i += 1;
+  }
+  sum
+}
+
+#[inline]
+#[must_use]
+const fn ascii_bytes(s: &str) -> &[u8] {
+  assert!(s.is_ascii(), "ROM header strings must be ASCII");
+  s.as_bytes()
+}
+
+/// Puts a [RomHeader] into the ROM.
+///
+/// The title and game code are required, and the maker code and version are
+/// optional. The header's checksum is computed at compile time, so the ROM will
+/// boot on real hardware without using `gbafix`.
+///
+/// ```no_run
+/// gba2k::gba_header!(
+///   title = "MY GAME",
+///   code = "AMGE",
+///   maker = "01",
+///   version = 1
+/// );
+/// ```
+///
+/// Use this macro once, in your binary. If there's no header the space is left
+/// blank, which emulators will still run.
+#[macro_export]
+macro_rules! gba_header {
+  (
+    title = $title:expr, code = $code:expr
+    $(, maker = $maker:expr)? $(, version = $version:expr)? $(,)?
+  ) => {
+    #[used]
+    #[no_mangle]
+    #[link_section = ".gba_rom_header_data"]
+    static GBA_ROM_HEADER: $crate::rt0::RomHeader =
+      $crate::rt0::RomHeader::new($title, $code)
+        $(.with_maker_code($maker))?
+        $(.with_version($version))?;
+  };
+}
@@ -0,0 +1,85 @@
+/// The lookup table for [Crc32], built at compile time.
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+  let mut table = [0_u32; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut crc = i as u32;
+    let mut bit = 0;
+    while bit < 8 {
+      crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+      bit += 1;
+    }
+    table[i] = crc;
+    i += 1;
+  }
+  table
+}
+
+/// A CRC-32 checksum, computed a piece at a time.
+///
+/// This is the common CRC-32 (the one used by zip and PNG), so the checksum of
+/// `b"123456789"` is `0xCBF43926`.
+///
+/// ```no_run
+/// # use gba2k::save::*;
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finish(), 0xCBF43926);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Crc32(u32);
+
+impl Default for Crc32 {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Crc32 {
+  /// Starts a new checksum.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self(u32::MAX)
+  }
+
+  /// Adds bytes to the checksum.
+  #[inline]
+  pub fn update(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      let i = (self.0 as u8 ^ byte) as usize;
+      self.0 = (self.0 >> 8) ^ CRC32_TABLE[i];
+    }
+  }
+
+  /// The checksum of all of the bytes so far.
+  #[inline]
+  #[must_use]
+  pub const fn finish(self) -> u32 {
+    !self.0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_value() {
+    let mut crc = Crc32::new();
+    crc.update(b"123456789");
+    assert_eq!(crc.finish(), 0xCBF4_3926);
+    // Split into pieces.
+    let mut crc = Crc32::default();
+    crc.update(b"1234");
+    crc.update(b"");
+    crc.update(b"56789");
+    assert_eq!(crc.finish(), 0xCBF4_3926);
+    assert_eq!(Crc32::new().finish(), 0);
+  }
+}
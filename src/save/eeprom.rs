@@ -0,0 +1,223 @@
+use voladdress::*;
+
+use crate::interrupts::{free, CriticalSection};
+
+use super::{check_bounds, check_sectors, SaveError, SaveMemory};
+
+/// The EEPROM's address.
+///
+/// Any address in `0x0D00_0000..0x0E00_0000` works for carts with ROMs of up
+/// to 16MB, but 32MB carts only put the EEPROM at the very end of that range.
+const EEPROM: usize = 0x0DFF_FF00;
+
+/// "DMA 3 Source Address"
+const DMA3SAD: VolAddress<usize, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_00D4) };
+
+/// "DMA 3 Destination Address"
+const DMA3DAD: VolAddress<usize, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_00D8) };
+
+/// "DMA 3 Count"
+const DMA3CNT_L: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_00DC) };
+
+/// "DMA 3 Control"
+const DMA3CNT_H: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_00DE) };
+
+/// Enabled, 16-bit units, both addresses increment.
+const DMA_ENABLE_16BIT: u16 = 0x8000;
+
+/// About 40 milliseconds of polling, well over the EEPROM's write time.
+const WRITE_TIMEOUT: u32 = 0x8000;
+
+/// EEPROM is accessed in blocks of this many bytes.
+const BLOCK_SIZE: usize = 8;
+
+/// The size of an EEPROM chip.
+///
+/// There's no reliable way to detect this, so the game has to know it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EepromSize {
+  /// 512 bytes, with 6-bit block addresses.
+  Size512B,
+  /// 8K, with 14-bit block addresses.
+  Size8K,
+}
+
+/// 512 bytes or 8K of EEPROM.
+///
+/// The EEPROM is accessed over a serial protocol, one bit per halfword, which
+/// has to be sent and received with DMA 3. Each access moves an 8 byte block,
+/// and writing a block takes a few milliseconds. Blocks that already hold the
+/// data being written are skipped.
+///
+/// DMA 3 is used, with interrupts disabled, during each block transfer. A
+/// read's request and reply are sent in one go, so an interrupt handler can't
+/// use DMA 3 in between them. Interrupts are turned back on while waiting for a
+/// write to finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Eeprom {
+  size: EepromSize,
+}
+
+impl Eeprom {
+  /// Makes the driver for an EEPROM of the size given.
+  #[inline]
+  #[must_use]
+  pub const fn new(size: EepromSize) -> Self {
+    Self { size }
+  }
+
+  /// The size of the EEPROM.
+  #[inline]
+  #[must_use]
+  pub const fn eeprom_size(&self) -> EepromSize {
+    self.size
+  }
+
+  #[inline]
+  #[must_use]
+  const fn address_bits(&self) -> usize {
+    match self.size {
+      EepromSize::Size512B => 6,
+      EepromSize::Size8K => 14,
+    }
+  }
+
+  /// Reads the 8 byte block at the block index given.
+  fn read_block(&mut self, block: usize) -> [u8; BLOCK_SIZE] {
+    // `11`, the address, then a `0`.
+    let bits = self.address_bits();
+    let mut request = [0_u16; 2 + 14 + 1];
+    request[0] = 1;
+    request[1] = 1;
+    put_bits(&mut request[2..2 + bits], block as u64, bits);
+    // 4 ignored bits, then 64 data bits.
+    let mut reply = [0_u16; 4 + 64];
+    free(|cs| {
+      dma3_halfwords(cs, request.as_ptr(), EEPROM as *mut u16, 3 + bits);
+      dma3_halfwords(cs, EEPROM as *const u16, reply.as_mut_ptr(), reply.len());
+    });
+    let mut data = 0_u64;
+    for bit in &reply[4..] {
+      data = (data << 1) | u64::from(bit & 1);
+    }
+    data.to_be_bytes()
+  }
+
+  /// Writes the 8 byte block at the block index given.
+  fn write_block(
+    &mut self, block: usize, data: &[u8; BLOCK_SIZE],
+  ) -> Result<(), SaveError> {
+    // `10`, the address, the 64 data bits, then a `0`.
+    let bits = self.address_bits();
+    let mut request = [0_u16; 2 + 14 + 64 + 1];
+    request[0] = 1;
+    put_bits(&mut request[2..2 + bits], block as u64, bits);
+    put_bits(
+      &mut request[2 + bits..2 + bits + 64],
+      u64::from_be_bytes(*data),
+      64,
+    );
+    free(|cs| {
+      dma3_halfwords(cs, request.as_ptr(), EEPROM as *mut u16, 3 + bits + 64);
+    });
+    // The EEPROM reads as 1 once the write is done.
+    let eeprom = EEPROM as *const u16;
+    let mut waited = 0;
+    while unsafe { eeprom.read_volatile() } & 1 == 0 {
+      waited += 1;
+      if waited == WRITE_TIMEOUT {
+        return Err(SaveError::Timeout);
+      }
+    }
+    Ok(())
+  }
+
+  /// Changes bytes within blocks, writing each block that changed.
+  fn update(
+    &mut self, offset: usize, len: usize, mut f: impl FnMut(usize, &mut u8),
+  ) -> Result<(), SaveError> {
+    let mut done = 0;
+    while done < len {
+      let offset = offset + done;
+      let block = offset / BLOCK_SIZE;
+      let start = offset % BLOCK_SIZE;
+      let n = (len - done).min(BLOCK_SIZE - start);
+      let old = self.read_block(block);
+      let mut new = old;
+      for (i, byte) in new[start..start + n].iter_mut().enumerate() {
+        f(done + i, byte);
+      }
+      if new != old {
+        self.write_block(block, &new)?;
+      }
+      done += n;
+    }
+    Ok(())
+  }
+}
+
+impl SaveMemory for Eeprom {
+  #[inline]
+  fn size(&self) -> usize {
+    match self.size {
+      EepromSize::Size512B => 512,
+      EepromSize::Size8K => 8 * 1024,
+    }
+  }
+
+  #[inline]
+  fn sector_size(&self) -> usize {
+    BLOCK_SIZE
+  }
+
+  fn read(
+    &mut self, offset: usize, buffer: &mut [u8],
+  ) -> Result<(), SaveError> {
+    check_bounds(offset, buffer.len(), self.size())?;
+    let mut done = 0;
+    while done < buffer.len() {
+      let offset = offset + done;
+      let start = offset % BLOCK_SIZE;
+      let n = (buffer.len() - done).min(BLOCK_SIZE - start);
+      let block = self.read_block(offset / BLOCK_SIZE);
+      buffer[done..done + n].copy_from_slice(&block[start..start + n]);
+      done += n;
+    }
+    Ok(())
+  }
+
+  fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError> {
+    check_bounds(offset, data.len(), self.size())?;
+    self.update(offset, data.len(), |i, byte| *byte = data[i])
+  }
+
+  fn erase(&mut self, offset: usize, len: usize) -> Result<(), SaveError> {
+    check_sectors(offset, len, self.size(), BLOCK_SIZE)?;
+    self.update(offset, len, |_, byte| *byte = 0xFF)
+  }
+}
+
+/// Puts the low `count` bits of `value` into the halfwords, high bit first.
+#[inline]
+fn put_bits(halfwords: &mut [u16], value: u64, count: usize) {
+  for (i, halfword) in halfwords.iter_mut().enumerate() {
+    *halfword = ((value >> (count - 1 - i)) & 1) as u16;
+  }
+}
+
+/// Copies halfwords with DMA 3, which the EEPROM needs.
+fn dma3_halfwords(
+  _cs: CriticalSection<'_>, src: *const u16, dest: *mut u16, count: usize,
+) {
+  DMA3SAD.write(src as usize);
+  DMA3DAD.write(dest as usize);
+  DMA3CNT_L.write(count as u16);
+  DMA3CNT_H.write(DMA_ENABLE_16BIT);
+  // The CPU is paused during the transfer, but wait for it to be marked done
+  // in case it didn't start right away.
+  while DMA3CNT_H.read() & DMA_ENABLE_16BIT != 0 {}
+}
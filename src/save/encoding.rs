@@ -0,0 +1,602 @@
+use super::{SaveError, SaveMemory};
+
+/// Somewhere that saved bytes can be written to.
+pub trait ByteSink {
+  /// Writes all of the bytes.
+  ///
+  /// ## Failure
+  /// * [SaveError::OutOfBounds] if there isn't room for the bytes.
+  fn put(&mut self, bytes: &[u8]) -> Result<(), SaveError>;
+}
+
+/// Somewhere that saved bytes can be read from.
+pub trait ByteSource {
+  /// Fills the buffer with the next bytes.
+  ///
+  /// ## Failure
+  /// * [SaveError::OutOfBounds] if there aren't enough bytes left.
+  fn take(&mut self, buffer: &mut [u8]) -> Result<(), SaveError>;
+}
+
+/// A [ByteSink] that writes into a byte slice.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct SliceSink<'a> {
+  bytes: &'a mut [u8],
+  position: usize,
+}
+
+impl<'a> SliceSink<'a> {
+  /// Starts writing at the start of the slice.
+  #[inline]
+  #[must_use]
+  pub fn new(bytes: &'a mut [u8]) -> Self {
+    Self { bytes, position: 0 }
+  }
+
+  /// The bytes written so far.
+  #[inline]
+  #[must_use]
+  pub fn written(&self) -> &[u8] {
+    &self.bytes[..self.position]
+  }
+}
+
+impl ByteSink for SliceSink<'_> {
+  #[inline]
+  fn put(&mut self, bytes: &[u8]) -> Result<(), SaveError> {
+    let end = self.position + bytes.len();
+    let dest =
+      self.bytes.get_mut(self.position..end).ok_or(SaveError::OutOfBounds)?;
+    dest.copy_from_slice(bytes);
+    self.position = end;
+    Ok(())
+  }
+}
+
+/// A [ByteSource] that reads from a byte slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SliceSource<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> SliceSource<'a> {
+  /// Starts reading at the start of the slice.
+  #[inline]
+  #[must_use]
+  pub const fn new(bytes: &'a [u8]) -> Self {
+    Self { bytes }
+  }
+
+  /// The bytes that haven't been read yet.
+  #[inline]
+  #[must_use]
+  pub const fn remaining(&self) -> &[u8] {
+    self.bytes
+  }
+}
+
+impl ByteSource for SliceSource<'_> {
+  #[inline]
+  fn take(&mut self, buffer: &mut [u8]) -> Result<(), SaveError> {
+    if buffer.len() > self.bytes.len() {
+      return Err(SaveError::OutOfBounds);
+    }
+    let (taken, rest) = self.bytes.split_at(buffer.len());
+    buffer.copy_from_slice(taken);
+    self.bytes = rest;
+    Ok(())
+  }
+}
+
+/// A [ByteSink] that writes straight into a [SaveMemory].
+///
+/// With [Sram](super::Sram) this writes with `gba_memcpy_sram`. Each `put` is a
+/// separate write, so on memory where writes are slow it's usually better to
+/// save into a [SliceSink] and write the whole buffer at once.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MemorySink<'a, M: ?Sized> {
+  memory: &'a mut M,
+  offset: usize,
+}
+
+impl<'a, M: SaveMemory + ?Sized> MemorySink<'a, M> {
+  /// Starts writing at the offset given.
+  #[inline]
+  #[must_use]
+  pub fn new(memory: &'a mut M, offset: usize) -> Self {
+    Self { memory, offset }
+  }
+
+  /// The offset that the next byte will be written to.
+  #[inline]
+  #[must_use]
+  pub const fn offset(&self) -> usize {
+    self.offset
+  }
+}
+
+impl<M: SaveMemory + ?Sized> ByteSink for MemorySink<'_, M> {
+  #[inline]
+  fn put(&mut self, bytes: &[u8]) -> Result<(), SaveError> {
+    self.memory.write(self.offset, bytes)?;
+    self.offset += bytes.len();
+    Ok(())
+  }
+}
+
+/// A [ByteSource] that reads straight from a [SaveMemory].
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MemorySource<'a, M: ?Sized> {
+  memory: &'a mut M,
+  offset: usize,
+}
+
+impl<'a, M: SaveMemory + ?Sized> MemorySource<'a, M> {
+  /// Starts reading at the offset given.
+  #[inline]
+  #[must_use]
+  pub fn new(memory: &'a mut M, offset: usize) -> Self {
+    Self { memory, offset }
+  }
+
+  /// The offset that the next byte will be read from.
+  #[inline]
+  #[must_use]
+  pub const fn offset(&self) -> usize {
+    self.offset
+  }
+}
+
+impl<M: SaveMemory + ?Sized> ByteSource for MemorySource<'_, M> {
+  #[inline]
+  fn take(&mut self, buffer: &mut [u8]) -> Result<(), SaveError> {
+    self.memory.read(self.offset, buffer)?;
+    self.offset += buffer.len();
+    Ok(())
+  }
+}
+
+/// A type that can be saved as bytes and loaded back.
+///
+/// Numbers are saved little-endian, `bool` is 1 byte, arrays save each element
+/// in order, and `Option` saves a tag byte followed by the value if there is
+/// one. For your own structs, use the [gba_save!](crate::gba_save) macro.
+pub trait GbaSave: Sized {
+  /// The most bytes that [save](GbaSave::save) writes.
+  const SIZE: usize;
+
+  /// Writes the value to the sink.
+  fn save<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<(), SaveError>;
+
+  /// Reads a value from the source.
+  ///
+  /// ## Failure
+  /// * [SaveError::InvalidData] if the bytes aren't a valid value.
+  fn load<S: ByteSource + ?Sized>(source: &mut S) -> Result<Self, SaveError>;
+}
+
+macro_rules! impl_gba_save_for_int {
+  ($($t:ty),* $(,)?) => {
+    $(
+      impl GbaSave for $t {
+        const SIZE: usize = core::mem::size_of::<$t>();
+        #[inline]
+        fn save<S: ByteSink + ?Sized>(
+          &self, sink: &mut S,
+        ) -> Result<(), SaveError> {
+          sink.put(&self.to_le_bytes())
+        }
+        #[inline]
+        fn load<S: ByteSource + ?Sized>(
+          source: &mut S,
+        ) -> Result<Self, SaveError> {
+          let mut bytes = [0; core::mem::size_of::<$t>()];
+          source.take(&mut bytes)?;
+          Ok(Self::from_le_bytes(bytes))
+        }
+      }
+    )*
+  };
+}
+impl_gba_save_for_int!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl GbaSave for bool {
+  const SIZE: usize = 1;
+  #[inline]
+  fn save<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<(), SaveError> {
+    sink.put(&[u8::from(*self)])
+  }
+  #[inline]
+  fn load<S: ByteSource + ?Sized>(source: &mut S) -> Result<Self, SaveError> {
+    match u8::load(source)? {
+      0 => Ok(false),
+      1 => Ok(true),
+      _ => Err(SaveError::InvalidData),
+    }
+  }
+}
+
+impl<T: GbaSave, const N: usize> GbaSave for [T; N] {
+  const SIZE: usize = T::SIZE * N;
+  #[inline]
+  fn save<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<(), SaveError> {
+    for t in self {
+      t.save(sink)?;
+    }
+    Ok(())
+  }
+  #[inline]
+  fn load<S: ByteSource + ?Sized>(source: &mut S) -> Result<Self, SaveError> {
+    let mut error = None;
+    let loaded: [Option<T>; N] = core::array::from_fn(|_| {
+      if error.is_some() {
+        return None;
+      }
+      T::load(source).map_err(|e| error = Some(e)).ok()
+    });
+    match error {
+      Some(e) => Err(e),
+      None => Ok(loaded.map(|t| t.unwrap())),
+    }
+  }
+}
+
+impl<T: GbaSave> GbaSave for Option<T> {
+  const SIZE: usize = 1 + T::SIZE;
+  #[inline]
+  fn save<S: ByteSink + ?Sized>(&self, sink: &mut S) -> Result<(), SaveError> {
+    match self {
+      None => false.save(sink),
+      Some(t) => {
+        true.save(sink)?;
+        t.save(sink)
+      }
+    }
+  }
+  #[inline]
+  fn load<S: ByteSource + ?Sized>(source: &mut S) -> Result<Self, SaveError> {
+    if bool::load(source)? {
+      Ok(Some(T::load(source)?))
+    } else {
+      Ok(None)
+    }
+  }
+}
+
+/// A [GbaSave] type with a version number, so that old saves can still be
+/// loaded after the type changes.
+///
+/// When the type changes, increase [VERSION](VersionedSave::VERSION), keep the
+/// old type around under a new name, and convert from it in
+/// [migrate](VersionedSave::migrate).
+///
+/// ```no_run
+/// # use gba2k::save::*;
+/// gba2k::gba_save! {
+///   pub struct PlayerV1 {
+///     pub hp: u16,
+///   }
+/// }
+///
+/// gba2k::gba_save! {
+///   pub struct Player {
+///     pub hp: u16,
+///     pub max_hp: u16,
+///   }
+/// }
+///
+/// impl VersionedSave for Player {
+///   const VERSION: u16 = 2;
+///
+///   fn migrate<S: ByteSource + ?Sized>(
+///     version: u16, source: &mut S,
+///   ) -> Result<Self, SaveError> {
+///     match version {
+///       1 => {
+///         let old = PlayerV1::load(source)?;
+///         Ok(Player { hp: old.hp, max_hp: 100 })
+///       }
+///       _ => Err(SaveError::InvalidData),
+///     }
+///   }
+/// }
+/// ```
+pub trait VersionedSave: GbaSave {
+  /// The version of the type's current format.
+  const VERSION: u16;
+
+  /// Loads a value that was saved by an older version of the type.
+  ///
+  /// The default returns [SaveError::InvalidData].
+  #[inline]
+  fn migrate<S: ByteSource + ?Sized>(
+    version: u16, source: &mut S,
+  ) -> Result<Self, SaveError> {
+    let _ = (version, source);
+    Err(SaveError::InvalidData)
+  }
+
+  /// Loads a value that was saved with the version given.
+  ///
+  /// This uses [load](GbaSave::load) for the current version, and
+  /// [migrate](VersionedSave::migrate) for any other version.
+  #[inline]
+  fn load_version<S: ByteSource + ?Sized>(
+    version: u16, source: &mut S,
+  ) -> Result<Self, SaveError> {
+    if version == Self::VERSION {
+      Self::load(source)
+    } else {
+      Self::migrate(version, source)
+    }
+  }
+
+  /// Writes the version number, then the value.
+  #[inline]
+  fn save_versioned<S: ByteSink + ?Sized>(
+    &self, sink: &mut S,
+  ) -> Result<(), SaveError> {
+    Self::VERSION.save(sink)?;
+    self.save(sink)
+  }
+
+  /// Reads a version number, then a value of that version.
+  #[inline]
+  fn load_versioned<S: ByteSource + ?Sized>(
+    source: &mut S,
+  ) -> Result<Self, SaveError> {
+    let version = u16::load(source)?;
+    Self::load_version(version, source)
+  }
+}
+
+/// Declares a struct that implements [GbaSave](crate::save::GbaSave).
+///
+/// The fields are saved in the order that they're declared, and each field's
+/// type must also implement `GbaSave`. The `SIZE` is the sum of the fields'
+/// sizes, with no padding.
+///
+/// ```no_run
+/// gba2k::gba_save! {
+///   #[derive(Debug, Clone, Copy, Default)]
+///   pub struct GameState {
+///     pub level: u8,
+///     pub score: u32,
+///     pub items: [u8; 16],
+///     pub beat_the_game: bool,
+///   }
+/// }
+/// ```
+#[macro_export]
+macro_rules! gba_save {
+  (
+    $(#[$meta:meta])*
+    $vis:vis struct $name:ident {
+      $(
+        $(#[$field_meta:meta])*
+        $field_vis:vis $field:ident : $ty:ty
+      ),* $(,)?
+    }
+  ) => {
+    $(#[$meta])*
+    $vis struct $name {
+      $(
+        $(#[$field_meta])*
+        $field_vis $field: $ty,
+      )*
+    }
+
+    impl $crate::save::GbaSave for $name {
+      const SIZE: usize = 0 $(+ <$ty as $crate::save::GbaSave>::SIZE)*;
+
+      #[inline]
+      fn save<S: $crate::save::ByteSink + ?Sized>(
+        &self, sink: &mut S,
+      ) -> ::core::result::Result<(), $crate::save::SaveError> {
+        $( $crate::save::GbaSave::save(&self.$field, sink)?; )*
+        let _ = sink;
+        ::core::result::Result::Ok(())
+      }
+
+      #[inline]
+      fn load<S: $crate::save::ByteSource + ?Sized>(
+        source: &mut S,
+      ) -> ::core::result::Result<Self, $crate::save::SaveError> {
+        let _ = &source;
+        // Struct fields are evaluated in the order they're written.
+        ::core::result::Result::Ok(Self {
+          $( $field: $crate::save::GbaSave::load(source)?, )*
+        })
+      }
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::save::{SaveSlots, SliceMemory};
+
+  /// The macro must not pick up a `Result` or `Ok` from where it's used.
+  #[allow(dead_code)]
+  mod shadowed {
+    pub type Result<T> = core::result::Result<T, ()>;
+    pub struct Ok;
+
+    crate::gba_save! {
+      #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+      pub struct GameState {
+        pub level: u8,
+        pub score: u32,
+        pub items: [u8; 4],
+        pub beat_the_game: bool,
+      }
+    }
+  }
+  use shadowed::GameState;
+
+  #[test]
+  fn gba_save_round_trip() {
+    let state = GameState {
+      level: 3,
+      score: 0x0102_0304,
+      items: [9, 8, 7, 6],
+      beat_the_game: true,
+    };
+    assert_eq!(GameState::SIZE, 10);
+    let mut bytes = [0; GameState::SIZE];
+    let mut sink = SliceSink::new(&mut bytes);
+    state.save(&mut sink).unwrap();
+    assert_eq!(sink.written().len(), GameState::SIZE);
+    let mut source = SliceSource::new(&bytes);
+    assert_eq!(GameState::load(&mut source), Ok(state));
+  }
+
+  crate::gba_save! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct PlayerV1 {
+      hp: u16,
+    }
+  }
+
+  crate::gba_save! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Player {
+      hp: u16,
+      max_hp: u16,
+    }
+  }
+
+  impl VersionedSave for Player {
+    const VERSION: u16 = 2;
+
+    fn migrate<S: ByteSource + ?Sized>(
+      version: u16, source: &mut S,
+    ) -> Result<Self, SaveError> {
+      match version {
+        1 => {
+          let old = PlayerV1::load(source)?;
+          Ok(Player { hp: old.hp, max_hp: 100 })
+        }
+        _ => Err(SaveError::InvalidData),
+      }
+    }
+  }
+
+  /// The bytes that a value saves as.
+  fn saved<T: GbaSave>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![0; T::SIZE];
+    let mut sink = SliceSink::new(&mut bytes);
+    value.save(&mut sink).unwrap();
+    let len = sink.written().len();
+    bytes.truncate(len);
+    bytes
+  }
+
+  #[test]
+  fn load_version_migrates() {
+    let old = saved(&PlayerV1 { hp: 40 });
+    let current = saved(&Player { hp: 40, max_hp: 50 });
+    assert_eq!(
+      Player::load_version(1, &mut SliceSource::new(&old)),
+      Ok(Player { hp: 40, max_hp: 100 })
+    );
+    assert_eq!(
+      Player::load_version(2, &mut SliceSource::new(&current)),
+      Ok(Player { hp: 40, max_hp: 50 })
+    );
+    assert_eq!(
+      Player::load_version(3, &mut SliceSource::new(&current)),
+      Err(SaveError::InvalidData)
+    );
+  }
+
+  #[test]
+  fn versioned_round_trip() {
+    let player = Player { hp: 7, max_hp: 9 };
+    let mut bytes = [0; 2 + Player::SIZE];
+    let mut sink = SliceSink::new(&mut bytes);
+    player.save_versioned(&mut sink).unwrap();
+    assert_eq!(sink.written(), [2, 0, 7, 0, 9, 0]);
+    let mut source = SliceSource::new(&bytes);
+    assert_eq!(Player::load_versioned(&mut source), Ok(player));
+    assert!(source.remaining().is_empty());
+    // A save from version 1 is migrated.
+    let mut source = SliceSource::new(&[1, 0, 40, 0]);
+    assert_eq!(
+      Player::load_versioned(&mut source),
+      Ok(Player { hp: 40, max_hp: 100 })
+    );
+  }
+
+  #[test]
+  fn slots_store_values() {
+    let mut memory = [0xFF; 256];
+    let mut slots =
+      SaveSlots::new(SliceMemory::new(&mut memory, 64), 2, 2, 32).unwrap();
+    let mut buffer = [0; 32];
+    assert_eq!(slots.read_value::<Player>(0, &mut buffer), Ok(None));
+    let player = Player { hp: 3, max_hp: 4 };
+    let info = slots.write_value(0, &player, &mut buffer).unwrap();
+    assert_eq!((info.version, info.len), (2, Player::SIZE));
+    assert_eq!(slots.read_value(0, &mut buffer), Ok(Some(player)));
+    // A slot written by version 1 of the game.
+    slots.write(1, 1, &saved(&PlayerV1 { hp: 5 })).unwrap();
+    assert_eq!(
+      slots.read_value(1, &mut buffer),
+      Ok(Some(Player { hp: 5, max_hp: 100 }))
+    );
+    // The value must fit in the buffer.
+    assert_eq!(
+      slots.write_value(0, &player, &mut buffer[..3]),
+      Err(SaveError::OutOfBounds)
+    );
+  }
+
+  #[test]
+  fn bool_must_be_0_or_1() {
+    assert_eq!(saved(&true), [1]);
+    assert_eq!(saved(&false), [0]);
+    assert_eq!(bool::load(&mut SliceSource::new(&[1])), Ok(true));
+    assert_eq!(
+      bool::load(&mut SliceSource::new(&[2])),
+      Err(SaveError::InvalidData)
+    );
+    assert_eq!(
+      <[bool; 2]>::load(&mut SliceSource::new(&[0, 0xFF])),
+      Err(SaveError::InvalidData)
+    );
+  }
+
+  #[test]
+  fn option_has_tag_byte() {
+    assert_eq!(Option::<u16>::SIZE, 3);
+    assert_eq!(saved(&None::<u16>), [0]);
+    assert_eq!(saved(&Some(0x1234_u16)), [1, 0x34, 0x12]);
+    assert_eq!(
+      Option::<u16>::load(&mut SliceSource::new(&[1, 0x34, 0x12])),
+      Ok(Some(0x1234))
+    );
+    assert_eq!(Option::<u16>::load(&mut SliceSource::new(&[0])), Ok(None));
+    assert_eq!(
+      Option::<u16>::load(&mut SliceSource::new(&[2, 0, 0])),
+      Err(SaveError::InvalidData)
+    );
+  }
+
+  #[test]
+  fn slices_run_out() {
+    let mut bytes = [0; 3];
+    let mut sink = SliceSink::new(&mut bytes);
+    assert_eq!(0x0102_0304_u32.save(&mut sink), Err(SaveError::OutOfBounds));
+    assert!(sink.written().is_empty());
+    assert_eq!([1_u16, 2].save(&mut sink), Err(SaveError::OutOfBounds));
+    assert_eq!(sink.written(), [1, 0]);
+
+    let mut source = SliceSource::new(&[1]);
+    assert_eq!(u16::load(&mut source), Err(SaveError::OutOfBounds));
+    assert_eq!(source.remaining(), [1]);
+    let mut source = SliceSource::new(&[1, 0, 2]);
+    assert_eq!(<[u16; 2]>::load(&mut source), Err(SaveError::OutOfBounds));
+  }
+}
@@ -0,0 +1,338 @@
+use voladdress::*;
+
+use crate::{interrupts::free, rt0::gba_memcpy_sram, t32_bx_r3};
+
+use super::{check_bounds, check_sectors, SaveError, SaveMemory, SAVE_MEMORY};
+
+/// The first command address.
+const FLASH_5555: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(SAVE_MEMORY + 0x5555) };
+
+/// The second command address.
+const FLASH_2AAA: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(SAVE_MEMORY + 0x2AAA) };
+
+/// Where the bank number goes after a bank switch command.
+const FLASH_BANK: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(SAVE_MEMORY) };
+
+/// The size of one bank of a 128K chip.
+const BANK_SIZE: usize = 64 * 1024;
+
+/// About 1.5 seconds of polling from IWRAM.
+const ERASE_TIMEOUT: u32 = 0x10_0000;
+
+/// About 25 milliseconds of polling from IWRAM.
+const WRITE_TIMEOUT: u32 = 0x4000;
+
+/// A Flash chip that the driver knows how to use.
+///
+/// The chips all use the same basic commands, with a few differences:
+/// * Atmel chips are written in 128 byte pages, and can't erase single sectors.
+/// * Macronix and Sanyo chips need a "terminate" command if an operation times
+///   out.
+/// * 128K chips have two 64K banks, and a command to switch between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum FlashChip {
+  Sst64K,
+  Macronix64K,
+  Panasonic64K,
+  Atmel64K,
+  Sanyo128K,
+  Macronix128K,
+}
+
+impl FlashChip {
+  /// Gets the chip with the ID given, if it's a known chip.
+  ///
+  /// The ID is the device code in the high byte and the manufacturer code in
+  /// the low byte.
+  #[inline]
+  #[must_use]
+  pub const fn from_id(id: u16) -> Option<Self> {
+    Some(match id {
+      0xD4BF => Self::Sst64K,
+      0x1CC2 => Self::Macronix64K,
+      0x1B32 => Self::Panasonic64K,
+      0x3D1F => Self::Atmel64K,
+      0x1362 => Self::Sanyo128K,
+      0x09C2 => Self::Macronix128K,
+      _ => return None,
+    })
+  }
+
+  /// The ID of the chip.
+  #[inline]
+  #[must_use]
+  pub const fn id(self) -> u16 {
+    match self {
+      Self::Sst64K => 0xD4BF,
+      Self::Macronix64K => 0x1CC2,
+      Self::Panasonic64K => 0x1B32,
+      Self::Atmel64K => 0x3D1F,
+      Self::Sanyo128K => 0x1362,
+      Self::Macronix128K => 0x09C2,
+    }
+  }
+
+  /// The size of the chip, in bytes.
+  #[inline]
+  #[must_use]
+  pub const fn size(self) -> usize {
+    match self {
+      Self::Sanyo128K | Self::Macronix128K => 128 * 1024,
+      _ => 64 * 1024,
+    }
+  }
+
+  /// The size of an erasable sector, in bytes.
+  ///
+  /// This is 4K, except for Atmel chips, which use 128 byte pages.
+  #[inline]
+  #[must_use]
+  pub const fn sector_size(self) -> usize {
+    match self {
+      Self::Atmel64K => 128,
+      _ => 4 * 1024,
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  const fn needs_terminate(self) -> bool {
+    matches!(self, Self::Macronix64K | Self::Macronix128K | Self::Sanyo128K)
+  }
+}
+
+/// 64K or 128K of Flash memory.
+///
+/// Flash bytes can only be written from `0xFF` to some other value. To write
+/// them again they must be erased back to `0xFF`, a whole sector at a time.
+///
+/// On 128K chips this tracks which 64K bank is mapped, so there can't be
+/// copies of it that disagree about the bank.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_copy_implementations)]
+pub struct Flash {
+  chip: FlashChip,
+  bank: u8,
+}
+
+impl Flash {
+  /// Reads the chip's ID to find out which Flash chip the cartridge has.
+  ///
+  /// ## Failure
+  /// * [SaveError::UnknownChip] if the ID isn't a known chip, which includes
+  ///   when the cartridge doesn't have Flash at all.
+  pub fn detect() -> Result<Self, SaveError> {
+    let id = free(|_| unsafe { t32_bx_r3(0, 0, 0, flash_read_id) });
+    match FlashChip::from_id(id) {
+      Some(chip) => {
+        let mut flash = Self { chip, bank: 0 };
+        flash.force_bank(0);
+        Ok(flash)
+      }
+      None => Err(SaveError::UnknownChip(id)),
+    }
+  }
+
+  /// The chip that was detected.
+  #[inline]
+  #[must_use]
+  pub const fn chip(&self) -> FlashChip {
+    self.chip
+  }
+
+  /// Erases the entire chip.
+  ///
+  /// This is faster than erasing each sector.
+  pub fn erase_all(&mut self) -> Result<(), SaveError> {
+    free(|_| {
+      command(0x80);
+      command(0x10);
+    });
+    self.wait(SAVE_MEMORY as *const u8, 0xFF, ERASE_TIMEOUT)
+  }
+
+  /// Switches banks if the offset is in a different bank.
+  ///
+  /// **Returns:** The address of the offset within the bank.
+  fn bank_address(&mut self, offset: usize) -> *mut u8 {
+    let bank = (offset / BANK_SIZE) as u8;
+    if bank != self.bank {
+      self.force_bank(bank);
+    }
+    (SAVE_MEMORY + offset % BANK_SIZE) as *mut u8
+  }
+
+  fn force_bank(&mut self, bank: u8) {
+    if self.chip.size() > BANK_SIZE {
+      free(|_| {
+        command(0xB0);
+        FLASH_BANK.write(bank);
+      });
+    }
+    self.bank = bank;
+  }
+
+  /// Waits for the byte at `addr` to read as `expected`.
+  fn wait(
+    &self, addr: *const u8, expected: u8, timeout: u32,
+  ) -> Result<(), SaveError> {
+    if unsafe { t32_bx_r3(addr, expected, timeout, flash_wait) } {
+      Ok(())
+    } else {
+      if self.chip.needs_terminate() {
+        FLASH_5555.write(0xF0);
+      }
+      Err(SaveError::Timeout)
+    }
+  }
+
+  /// Writes one 128 byte page of an Atmel chip.
+  fn write_atmel_page(
+    &mut self, page_offset: usize, page: &[u8; 128],
+  ) -> Result<(), SaveError> {
+    let addr = self.bank_address(page_offset);
+    // The whole page has to be sent quickly, so interrupts are off.
+    free(|_| {
+      command(0xA0);
+      for (i, &byte) in page.iter().enumerate() {
+        unsafe { addr.add(i).write_volatile(byte) };
+      }
+    });
+    self.wait(unsafe { addr.add(127) }, page[127], WRITE_TIMEOUT)
+  }
+}
+
+impl SaveMemory for Flash {
+  #[inline]
+  fn size(&self) -> usize {
+    self.chip.size()
+  }
+
+  #[inline]
+  fn sector_size(&self) -> usize {
+    self.chip.sector_size()
+  }
+
+  /// Atmel chips erase each page as part of writing it, so they don't need a
+  /// separate erase.
+  #[inline]
+  fn needs_erase(&self) -> bool {
+    self.chip != FlashChip::Atmel64K
+  }
+
+  fn read(
+    &mut self, offset: usize, buffer: &mut [u8],
+  ) -> Result<(), SaveError> {
+    check_bounds(offset, buffer.len(), self.size())?;
+    let mut done = 0;
+    while done < buffer.len() {
+      let offset = offset + done;
+      let n = (buffer.len() - done).min(BANK_SIZE - offset % BANK_SIZE);
+      let src = self.bank_address(offset);
+      let dest = buffer[done..].as_mut_ptr();
+      unsafe { t32_bx_r3(dest, src as *const u8, n, gba_memcpy_sram) };
+      done += n;
+    }
+    Ok(())
+  }
+
+  fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError> {
+    check_bounds(offset, data.len(), self.size())?;
+    if self.chip == FlashChip::Atmel64K {
+      let mut done = 0;
+      while done < data.len() {
+        let offset = offset + done;
+        let page_offset = offset - offset % 128;
+        let start = offset - page_offset;
+        let n = (data.len() - done).min(128 - start);
+        let mut page = [0xFF_u8; 128];
+        self.read(page_offset, &mut page)?;
+        if page[start..start + n] != data[done..done + n] {
+          page[start..start + n].copy_from_slice(&data[done..done + n]);
+          self.write_atmel_page(page_offset, &page)?;
+        }
+        done += n;
+      }
+      return Ok(());
+    }
+    for (i, &byte) in data.iter().enumerate() {
+      let addr = self.bank_address(offset + i);
+      let old = unsafe { addr.read_volatile() };
+      if old == byte {
+        continue;
+      }
+      if old & byte != byte {
+        return Err(SaveError::NotErased);
+      }
+      free(|_| {
+        command(0xA0);
+        unsafe { addr.write_volatile(byte) };
+      });
+      self.wait(addr, byte, WRITE_TIMEOUT)?;
+    }
+    Ok(())
+  }
+
+  fn erase(&mut self, offset: usize, len: usize) -> Result<(), SaveError> {
+    let sector_size = self.sector_size();
+    check_sectors(offset, len, self.size(), sector_size)?;
+    for sector in (offset..offset + len).step_by(sector_size) {
+      if self.chip == FlashChip::Atmel64K {
+        self.write_atmel_page(sector, &[0xFF; 128])?;
+        continue;
+      }
+      let addr = self.bank_address(sector);
+      free(|_| {
+        command(0x80);
+        FLASH_5555.write(0xAA);
+        FLASH_2AAA.write(0x55);
+        unsafe { addr.write_volatile(0x30) };
+      });
+      self.wait(addr, 0xFF, ERASE_TIMEOUT)?;
+    }
+    Ok(())
+  }
+}
+
+/// Sends a command to the chip.
+#[inline(always)]
+fn command(cmd: u8) {
+  FLASH_5555.write(0xAA);
+  FLASH_2AAA.write(0x55);
+  FLASH_5555.write(cmd);
+}
+
+// The chip can't be read normally while it's in ID mode or busy with an
+// erase or write, so the code that does these is kept in IWRAM instead of
+// being fetched from the cartridge.
+
+#[link_section = ".iwram.gba2k_flash_read_id"]
+#[inline(never)]
+unsafe extern "C" fn flash_read_id(_: u32, _: u32, _: u32) -> u16 {
+  command(0x90);
+  let base = SAVE_MEMORY as *const u8;
+  let id =
+    u16::from_le_bytes([base.read_volatile(), base.add(1).read_volatile()]);
+  command(0xF0);
+  FLASH_5555.write(0xF0);
+  id
+}
+
+#[link_section = ".iwram.gba2k_flash_wait"]
+#[inline(never)]
+unsafe extern "C" fn flash_wait(
+  addr: *const u8, expected: u8, timeout: u32,
+) -> bool {
+  let mut waited = 0;
+  while addr.read_volatile() != expected {
+    waited += 1;
+    if waited == timeout {
+      return false;
+    }
+  }
+  true
+}
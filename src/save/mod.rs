@@ -0,0 +1,168 @@
+#![warn(missing_docs)]
+
+//! Drivers for the cartridge's save memory.
+//!
+//! A cartridge has (at most) one kind of save chip, and the game has to know
+//! which kind it is, because they're all accessed differently:
+//!
+//! * [Sram]: 32K of battery backed RAM. This is the simplest, any byte can be
+//!   read or written at any time.
+//! * [Flash]: 64K or 128K of flash memory. Bytes must be erased (in whole
+//!   sectors) before they can be written again. Use [Flash::detect] to find out
+//!   which chip the cartridge has.
+//! * [Eeprom]: 512 bytes or 8K of EEPROM. This is accessed 8 bytes at a time
+//!   over a bit-serial protocol, and writes are slow.
+//!
+//! All of the drivers implement the [SaveMemory] trait, so the rest of your
+//! save code doesn't need to care which one it's using. [SliceMemory] also
+//! implements the trait, using a byte slice, which is useful for testing save
+//! code on the host.
+//!
+//! Rather than using a [SaveMemory] directly, most games will want
+//! [SaveSlots], which keeps each save safe from power loss during a write.
+//!
+//! Game data can be turned into bytes and back with the [GbaSave] trait, and
+//! the [gba_save!](crate::gba_save) macro implements it for your structs.
+//!
+//! Emulators usually guess the save type by searching the ROM for a marker
+//! string, which the `gba-rom` tool's `--save-type` option adds.
+//!
+//! ## Wait States
+//!
+//! The save chips need the slowest wait state settings for their part of the
+//! memory map. The rt0's default `WAITCNT`
+//! ([WaitControl::FAST](crate::system::WaitControl::FAST)) works with SRAM,
+//! Flash, and EEPROM. If you choose a different value, keep SRAM and wait
+//! state 2 at 8 cycles, which `BIOS_DEFAULT` doesn't.
+
+mod eeprom;
+pub use eeprom::*;
+
+mod crc32;
+pub use crc32::*;
+
+mod encoding;
+pub use encoding::*;
+
+#[cfg(target_arch = "arm")]
+mod flash;
+#[cfg(target_arch = "arm")]
+pub use flash::*;
+
+mod slice;
+pub use slice::*;
+
+mod slots;
+pub use slots::*;
+
+#[cfg(target_arch = "arm")]
+mod sram;
+#[cfg(target_arch = "arm")]
+pub use sram::*;
+
+/// The address of SRAM and Flash save memory.
+#[cfg(target_arch = "arm")]
+const SAVE_MEMORY: usize = 0x0E00_0000;
+
+/// An error from a [SaveMemory] operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SaveError {
+  /// The range isn't entirely within the save memory.
+  OutOfBounds,
+  /// The range of an erase doesn't start and end on sector boundaries.
+  Unaligned,
+  /// Flash bytes must be erased before they can be written again.
+  NotErased,
+  /// The Flash chip's ID isn't one of the known chips.
+  UnknownChip(u16),
+  /// The chip didn't finish an erase or a write in time.
+  Timeout,
+  /// The data read back didn't match the data written.
+  VerifyFailed,
+  /// Every copy of the save data was damaged, such as by losing power during
+  /// a write.
+  Corrupted,
+  /// Saved bytes didn't load as a valid value.
+  InvalidData,
+}
+
+/// A cartridge save memory.
+///
+/// Offsets are in bytes from the start of the save memory.
+pub trait SaveMemory {
+  /// The size of the save memory, in bytes.
+  fn size(&self) -> usize;
+
+  /// The size of an erasable sector, in bytes.
+  ///
+  /// Ranges passed to [erase](SaveMemory::erase) must start and end on a
+  /// multiple of this.
+  fn sector_size(&self) -> usize;
+
+  /// Reads bytes from the save memory into the buffer.
+  fn read(&mut self, offset: usize, buffer: &mut [u8])
+    -> Result<(), SaveError>;
+
+  /// Writes bytes to the save memory.
+  ///
+  /// If [needs_erase](SaveMemory::needs_erase) is true then the bytes must
+  /// have been erased first, except for bytes that already hold the value
+  /// being written.
+  fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError>;
+
+  /// Sets a range of bytes to `0xFF`.
+  ///
+  /// ## Failure
+  /// * The range must start and end on a multiple of the
+  ///   [sector_size](SaveMemory::sector_size).
+  fn erase(&mut self, offset: usize, len: usize) -> Result<(), SaveError>;
+
+  /// If bytes must be erased before they can be written again.
+  ///
+  /// This is true for [Flash], and false for memory where any byte can be
+  /// written at any time.
+  #[inline]
+  fn needs_erase(&self) -> bool {
+    false
+  }
+
+  /// Checks that the save memory holds the data given.
+  ///
+  /// ## Failure
+  /// * [SaveError::VerifyFailed] if any byte is different.
+  fn verify(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError> {
+    const CHUNK: usize = 32;
+    let mut buffer = [0_u8; CHUNK];
+    for (i, chunk) in data.chunks(CHUNK).enumerate() {
+      let buffer = &mut buffer[..chunk.len()];
+      self.read(offset + i * CHUNK, buffer)?;
+      if buffer != chunk {
+        return Err(SaveError::VerifyFailed);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Checks that `offset..offset+len` is within a memory of `size` bytes.
+#[inline]
+fn check_bounds(
+  offset: usize, len: usize, size: usize,
+) -> Result<(), SaveError> {
+  match offset.checked_add(len) {
+    Some(end) if end <= size => Ok(()),
+    _ => Err(SaveError::OutOfBounds),
+  }
+}
+
+/// Checks that `offset..offset+len` is within bounds and sector aligned.
+#[inline]
+fn check_sectors(
+  offset: usize, len: usize, size: usize, sector_size: usize,
+) -> Result<(), SaveError> {
+  check_bounds(offset, len, size)?;
+  if !offset.is_multiple_of(sector_size) || !len.is_multiple_of(sector_size) {
+    return Err(SaveError::Unaligned);
+  }
+  Ok(())
+}
@@ -0,0 +1,96 @@
+use super::{check_bounds, check_sectors, SaveError, SaveMemory};
+
+/// A [SaveMemory] that uses a byte slice.
+///
+/// This can stand in for a real save chip when testing save code on the host,
+/// or be used as a cache of the save data in RAM. With
+/// [with_flash_rules](SliceMemory::with_flash_rules) it acts like [Flash]:
+/// writes can only clear bits, and bytes must be erased to set them again.
+///
+/// [Flash]: super::Flash
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct SliceMemory<'a> {
+  bytes: &'a mut [u8],
+  sector_size: usize,
+  flash_rules: bool,
+}
+
+impl<'a> SliceMemory<'a> {
+  /// Uses the bytes as save memory, with the sector size given.
+  ///
+  /// ## Panics
+  /// * The sector size must be non-zero, and the length of the bytes must be a
+  ///   multiple of it.
+  #[inline]
+  #[must_use]
+  pub fn new(bytes: &'a mut [u8], sector_size: usize) -> Self {
+    assert!(sector_size != 0 && bytes.len().is_multiple_of(sector_size));
+    Self { bytes, sector_size, flash_rules: false }
+  }
+
+  /// Sets if this acts like Flash memory.
+  #[inline]
+  #[must_use]
+  pub fn with_flash_rules(mut self, flash_rules: bool) -> Self {
+    self.flash_rules = flash_rules;
+    self
+  }
+
+  /// The bytes of the memory.
+  #[inline]
+  #[must_use]
+  pub fn bytes(&self) -> &[u8] {
+    self.bytes
+  }
+}
+
+impl SaveMemory for SliceMemory<'_> {
+  #[inline]
+  fn size(&self) -> usize {
+    self.bytes.len()
+  }
+
+  #[inline]
+  fn sector_size(&self) -> usize {
+    self.sector_size
+  }
+
+  #[inline]
+  fn read(
+    &mut self, offset: usize, buffer: &mut [u8],
+  ) -> Result<(), SaveError> {
+    check_bounds(offset, buffer.len(), self.size())?;
+    buffer.copy_from_slice(&self.bytes[offset..offset + buffer.len()]);
+    Ok(())
+  }
+
+  #[inline]
+  fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError> {
+    check_bounds(offset, data.len(), self.size())?;
+    let dest = &mut self.bytes[offset..offset + data.len()];
+    if self.flash_rules {
+      // Like real Flash, the bytes before a failure are still written.
+      for (old, &new) in dest.iter_mut().zip(data) {
+        if *old & new != new {
+          return Err(SaveError::NotErased);
+        }
+        *old = new;
+      }
+    } else {
+      dest.copy_from_slice(data);
+    }
+    Ok(())
+  }
+
+  #[inline]
+  fn erase(&mut self, offset: usize, len: usize) -> Result<(), SaveError> {
+    check_sectors(offset, len, self.size(), self.sector_size)?;
+    self.bytes[offset..offset + len].fill(0xFF);
+    Ok(())
+  }
+
+  #[inline]
+  fn needs_erase(&self) -> bool {
+    self.flash_rules
+  }
+}
@@ -0,0 +1,492 @@
+use super::{
+  Crc32, SaveError, SaveMemory, SliceSink, SliceSource, VersionedSave,
+};
+
+/// The first bytes of each copy's header: `"G2K"`, then the header format
+/// version.
+const MAGIC: [u8; 4] = *b"G2K\x01";
+
+/// The size of the header at the start of each copy.
+///
+/// * `0..4`: [MAGIC]
+/// * `4..8`: The sequence number.
+/// * `8..10`: The game's data version.
+/// * `10..12`: The data length.
+/// * `12..16`: The CRC-32 of bytes `0..12` followed by the data.
+const HEADER_SIZE: usize = 16;
+
+/// Information about the data in a save slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SlotInfo {
+  /// The version number that the game gave when writing the data.
+  pub version: u16,
+  /// The length of the data, in bytes.
+  pub len: usize,
+  /// Counts up by 1 with each write to the slot.
+  pub sequence: u32,
+}
+
+/// Save slots that survive losing power during a write.
+///
+/// The save memory is split into a number of slots, and each slot holds
+/// several copies of its data. Each write goes to the copy after the newest
+/// one, and the header (with a CRC-32 of the data) is written last. If power
+/// is lost during a write, that copy fails its CRC check, and reads use the
+/// previous copy instead. This needs at least 2 copies per slot.
+///
+/// Each copy takes up whole sectors. With more than 2 copies, writes are spread
+/// over more sectors, so each Flash sector wears out more slowly.
+///
+/// The layout only depends on the slot count, the copy count, the data size,
+/// and the memory's sector size, so these must stay the same between versions
+/// of a game for old saves to be found. The data inside a slot can change
+/// format; the version number stored with each write tells you which format
+/// it's in.
+///
+/// ```no_run
+/// # use gba2k::save::*;
+/// # fn f() -> Result<(), SaveError> {
+/// let flash = Flash::detect()?;
+/// let mut slots = SaveSlots::new(flash, 3, 2, 1024)?;
+/// slots.write(0, 1, b"player data")?;
+/// let mut buffer = [0; 1024];
+/// if let Some(info) = slots.read(0, &mut buffer)? {
+///   let data = &buffer[..info.len];
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SaveSlots<M> {
+  memory: M,
+  slot_count: usize,
+  copies: usize,
+  data_size: usize,
+  copy_size: usize,
+}
+
+impl<M: SaveMemory> SaveSlots<M> {
+  /// Lays out slots within the memory.
+  ///
+  /// Each slot holds up to `data_size` bytes of data, with `copies` copies of
+  /// it. Nothing is written until the first [write](SaveSlots::write).
+  ///
+  /// ## Panics
+  /// * There must be at least 1 slot.
+  /// * There must be at least 2 copies.
+  ///
+  /// ## Failure
+  /// * [SaveError::OutOfBounds] if the slots don't fit in the memory, or if
+  ///   `data_size` is more than `u16::MAX`.
+  pub fn new(
+    memory: M, slot_count: usize, copies: usize, data_size: usize,
+  ) -> Result<Self, SaveError> {
+    assert!(slot_count >= 1, "there must be at least 1 slot");
+    assert!(copies >= 2, "there must be at least 2 copies of each slot");
+    if data_size > usize::from(u16::MAX) {
+      return Err(SaveError::OutOfBounds);
+    }
+    let copy_size =
+      (HEADER_SIZE + data_size).next_multiple_of(memory.sector_size());
+    match copy_size.checked_mul(copies).and_then(|n| n.checked_mul(slot_count))
+    {
+      Some(total) if total <= memory.size() => (),
+      _ => return Err(SaveError::OutOfBounds),
+    }
+    Ok(Self { memory, slot_count, copies, data_size, copy_size })
+  }
+
+  /// The number of slots.
+  #[inline]
+  #[must_use]
+  pub const fn slot_count(&self) -> usize {
+    self.slot_count
+  }
+
+  /// The most data that a slot can hold, in bytes.
+  #[inline]
+  #[must_use]
+  pub const fn data_size(&self) -> usize {
+    self.data_size
+  }
+
+  /// The memory that the slots are in.
+  #[inline]
+  #[must_use]
+  pub fn memory(&mut self) -> &mut M {
+    &mut self.memory
+  }
+
+  /// Unwraps the memory that the slots are in.
+  #[inline]
+  #[must_use]
+  pub fn into_memory(self) -> M {
+    self.memory
+  }
+
+  /// Gets information about the newest good copy in a slot.
+  ///
+  /// **Returns:** `None` if the slot has never been written, or was cleared.
+  /// Memory that was never set up (such as the leftover bytes in a new SRAM
+  /// cart) counts as never written.
+  ///
+  /// ## Failure
+  /// * [SaveError::Corrupted] if a copy in the slot has a header, but no copy
+  ///   passes its CRC check.
+  ///
+  /// ## Panics
+  /// * The slot index must be less than the slot count.
+  pub fn info(&mut self, slot: usize) -> Result<Option<SlotInfo>, SaveError> {
+    Ok(self.newest(slot)?.map(|(_, info)| info))
+  }
+
+  /// Reads the newest good copy of a slot's data into the buffer.
+  ///
+  /// **Returns:** Information about the data, or `None` if the slot has never
+  /// been written, or was cleared.
+  ///
+  /// ## Failure
+  /// * [SaveError::Corrupted] if a copy in the slot has a header, but no copy
+  ///   passes its CRC check.
+  /// * [SaveError::OutOfBounds] if the buffer is smaller than the data.
+  ///
+  /// ## Panics
+  /// * The slot index must be less than the slot count.
+  pub fn read(
+    &mut self, slot: usize, buffer: &mut [u8],
+  ) -> Result<Option<SlotInfo>, SaveError> {
+    let Some((copy, info)) = self.newest(slot)? else {
+      return Ok(None);
+    };
+    let buffer = buffer.get_mut(..info.len).ok_or(SaveError::OutOfBounds)?;
+    self.memory.read(self.copy_offset(slot, copy) + HEADER_SIZE, buffer)?;
+    Ok(Some(info))
+  }
+
+  /// Writes new data to a slot.
+  ///
+  /// The data goes into the copy after the newest good copy, so the previous
+  /// data is still there if this is interrupted.
+  ///
+  /// **Returns:** Information about the data written.
+  ///
+  /// ## Failure
+  /// * [SaveError::OutOfBounds] if the data is bigger than the data size.
+  /// * [SaveError::VerifyFailed] if the data didn't read back correctly. The
+  ///   previous data is still available.
+  ///
+  /// ## Panics
+  /// * The slot index must be less than the slot count.
+  pub fn write(
+    &mut self, slot: usize, version: u16, data: &[u8],
+  ) -> Result<SlotInfo, SaveError> {
+    if data.len() > self.data_size {
+      return Err(SaveError::OutOfBounds);
+    }
+    let (copy, sequence) = match self.newest(slot) {
+      Ok(Some((copy, info))) => {
+        ((copy + 1) % self.copies, info.sequence.wrapping_add(1))
+      }
+      Ok(None) | Err(SaveError::Corrupted) => (0, 1),
+      Err(e) => return Err(e),
+    };
+    let offset = self.copy_offset(slot, copy);
+
+    // Make sure that the copy's old header is gone before its data changes.
+    let erase_len = if self.memory.needs_erase() {
+      self.copy_size
+    } else {
+      HEADER_SIZE.next_multiple_of(self.memory.sector_size())
+    };
+    self.memory.erase(offset, erase_len)?;
+    self.memory.write(offset + HEADER_SIZE, data)?;
+    self.memory.verify(offset + HEADER_SIZE, data)?;
+
+    let info = SlotInfo { version, len: data.len(), sequence };
+    let mut header = header_bytes(&info);
+    let mut crc = Crc32::new();
+    crc.update(&header[..12]);
+    crc.update(data);
+    header[12..].copy_from_slice(&crc.finish().to_le_bytes());
+    self.memory.write(offset, &header)?;
+    self.memory.verify(offset, &header)?;
+    Ok(info)
+  }
+
+  /// Saves a value into a slot, using its [VERSION](VersionedSave::VERSION).
+  ///
+  /// The value is saved into the buffer first, and then written to the slot.
+  ///
+  /// ## Failure
+  /// * [SaveError::OutOfBounds] if the value doesn't fit in the buffer, or in
+  ///   the data size.
+  pub fn write_value<T: VersionedSave>(
+    &mut self, slot: usize, value: &T, buffer: &mut [u8],
+  ) -> Result<SlotInfo, SaveError> {
+    let mut sink = SliceSink::new(buffer);
+    value.save(&mut sink)?;
+    self.write(slot, T::VERSION, sink.written())
+  }
+
+  /// Loads a value from a slot, migrating it if it was saved with an older
+  /// version.
+  ///
+  /// The buffer must be big enough to hold the slot's data.
+  ///
+  /// **Returns:** `None` if the slot has never been written, or was cleared.
+  pub fn read_value<T: VersionedSave>(
+    &mut self, slot: usize, buffer: &mut [u8],
+  ) -> Result<Option<T>, SaveError> {
+    let Some(info) = self.read(slot, buffer)? else {
+      return Ok(None);
+    };
+    let mut source = SliceSource::new(&buffer[..info.len]);
+    T::load_version(info.version, &mut source).map(Some)
+  }
+
+  /// Erases every copy in a slot.
+  ///
+  /// ## Panics
+  /// * The slot index must be less than the slot count.
+  pub fn clear(&mut self, slot: usize) -> Result<(), SaveError> {
+    let offset = self.copy_offset(slot, 0);
+    self.memory.erase(offset, self.copy_size * self.copies)
+  }
+
+  #[inline]
+  #[must_use]
+  fn copy_offset(&self, slot: usize, copy: usize) -> usize {
+    assert!(slot < self.slot_count, "the slot index is out of bounds");
+    (slot * self.copies + copy) * self.copy_size
+  }
+
+  /// Finds the good copy with the highest sequence number.
+  ///
+  /// Only a copy that starts with [MAGIC] counts as written, so that the
+  /// random bytes in memory that was never set up don't look like corruption.
+  fn newest(
+    &mut self, slot: usize,
+  ) -> Result<Option<(usize, SlotInfo)>, SaveError> {
+    let mut newest: Option<(usize, SlotInfo)> = None;
+    let mut any_used = false;
+    for copy in 0..self.copies {
+      let offset = self.copy_offset(slot, copy);
+      let mut header = [0_u8; HEADER_SIZE];
+      self.memory.read(offset, &mut header)?;
+      if header[0..4] == MAGIC {
+        any_used = true;
+      }
+      let Some(info) = self.check_copy(offset, &header)? else {
+        continue;
+      };
+      // Sequence numbers wrap, so compare them by their difference.
+      let is_newer = match newest {
+        Some((_, best)) => {
+          (info.sequence.wrapping_sub(best.sequence) as i32) > 0
+        }
+        None => true,
+      };
+      if is_newer {
+        newest = Some((copy, info));
+      }
+    }
+    match newest {
+      None if any_used => Err(SaveError::Corrupted),
+      newest => Ok(newest),
+    }
+  }
+
+  /// Checks a copy's header and CRC.
+  ///
+  /// **Returns:** The copy's info, or `None` if it's not a good copy.
+  fn check_copy(
+    &mut self, offset: usize, header: &[u8; HEADER_SIZE],
+  ) -> Result<Option<SlotInfo>, SaveError> {
+    if header[0..4] != MAGIC {
+      return Ok(None);
+    }
+    let info = SlotInfo {
+      sequence: u32::from_le_bytes([
+        header[4], header[5], header[6], header[7],
+      ]),
+      version: u16::from_le_bytes([header[8], header[9]]),
+      len: usize::from(u16::from_le_bytes([header[10], header[11]])),
+    };
+    if info.len > self.data_size {
+      return Ok(None);
+    }
+    let mut crc = Crc32::new();
+    crc.update(&header[..12]);
+    let mut buffer = [0_u8; 32];
+    let mut done = 0;
+    while done < info.len {
+      let n = (info.len - done).min(buffer.len());
+      self.memory.read(offset + HEADER_SIZE + done, &mut buffer[..n])?;
+      crc.update(&buffer[..n]);
+      done += n;
+    }
+    let expected =
+      u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+    Ok(if crc.finish() == expected { Some(info) } else { None })
+  }
+}
+
+/// The header bytes for the info, with the CRC left blank.
+#[inline]
+#[must_use]
+fn header_bytes(info: &SlotInfo) -> [u8; HEADER_SIZE] {
+  let mut header = [0_u8; HEADER_SIZE];
+  header[0..4].copy_from_slice(&MAGIC);
+  header[4..8].copy_from_slice(&info.sequence.to_le_bytes());
+  header[8..10].copy_from_slice(&info.version.to_le_bytes());
+  header[10..12].copy_from_slice(&(info.len as u16).to_le_bytes());
+  header
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::save::SliceMemory;
+
+  /// With 64 byte sectors, each copy of 32 bytes of data takes 1 sector.
+  const SECTOR: usize = 64;
+  const DATA: usize = 32;
+
+  fn slots(bytes: &mut [u8]) -> SaveSlots<SliceMemory<'_>> {
+    SaveSlots::new(SliceMemory::new(bytes, SECTOR), 2, 2, DATA).unwrap()
+  }
+
+  /// Writes a whole copy with the info given, like a finished write would.
+  fn put_copy(
+    slots: &mut SaveSlots<SliceMemory<'_>>, copy: usize, info: SlotInfo,
+    data: &[u8],
+  ) {
+    let offset = slots.copy_offset(0, copy);
+    let mut header = header_bytes(&info);
+    let mut crc = Crc32::new();
+    crc.update(&header[..12]);
+    crc.update(data);
+    header[12..].copy_from_slice(&crc.finish().to_le_bytes());
+    slots.memory().write(offset, &header).unwrap();
+    slots.memory().write(offset + HEADER_SIZE, data).unwrap();
+  }
+
+  #[test]
+  fn fresh_slot_is_empty() {
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let mut slots = slots(&mut bytes);
+    let mut buffer = [0; DATA];
+    assert_eq!(slots.info(0), Ok(None));
+    assert_eq!(slots.read(1, &mut buffer), Ok(None));
+  }
+
+  #[test]
+  fn unformatted_memory_is_empty() {
+    let mut state = 12345_u32;
+    let random = core::array::from_fn(|_| {
+      state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+      (state >> 16) as u8
+    });
+    for mut bytes in [[0; 4 * SECTOR], random] {
+      let mut slots = slots(&mut bytes);
+      let mut buffer = [0; DATA];
+      assert_eq!(slots.info(0), Ok(None));
+      assert_eq!(slots.read(1, &mut buffer), Ok(None));
+      let info = slots.write(1, 1, b"first save").unwrap();
+      assert_eq!(info.sequence, 1);
+      assert_eq!(slots.read(1, &mut buffer), Ok(Some(info)));
+      assert_eq!(slots.info(0), Ok(None));
+    }
+  }
+
+  #[test]
+  fn write_then_read() {
+    for flash_rules in [false, true] {
+      let mut bytes = [0xFF; 4 * SECTOR];
+      let memory =
+        SliceMemory::new(&mut bytes, SECTOR).with_flash_rules(flash_rules);
+      let mut slots = SaveSlots::new(memory, 2, 2, DATA).unwrap();
+      let mut buffer = [0; DATA];
+      for (n, data) in [&b"first"[..], b"second", b"third"].iter().enumerate() {
+        let info = slots.write(0, 7, data).unwrap();
+        assert_eq!(
+          info,
+          SlotInfo { version: 7, len: data.len(), sequence: n as u32 + 1 }
+        );
+        assert_eq!(slots.read(0, &mut buffer), Ok(Some(info)));
+        assert_eq!(&buffer[..info.len], *data);
+      }
+      // The other slot is untouched.
+      assert_eq!(slots.info(1), Ok(None));
+    }
+  }
+
+  #[test]
+  fn torn_write_keeps_previous_copy() {
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let mut slots = slots(&mut bytes);
+    let info = slots.write(0, 1, b"saved").unwrap();
+    // Power is lost after the next write's data, before its header.
+    let offset = slots.copy_offset(0, 1);
+    slots.memory().write(offset + HEADER_SIZE, b"half done").unwrap();
+    let mut buffer = [0; DATA];
+    assert_eq!(slots.read(0, &mut buffer), Ok(Some(info)));
+    assert_eq!(&buffer[..info.len], b"saved");
+    // The next write goes over the torn copy.
+    let info = slots.write(0, 1, b"again").unwrap();
+    assert_eq!(info.sequence, 2);
+    assert_eq!(slots.read(0, &mut buffer), Ok(Some(info)));
+    assert_eq!(&buffer[..info.len], b"again");
+  }
+
+  #[test]
+  fn bad_crc_is_corrupted() {
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let mut slots = slots(&mut bytes);
+    slots.write(0, 1, b"saved").unwrap();
+    let offset = slots.copy_offset(0, 0);
+    slots.memory().write(offset + HEADER_SIZE, b"X").unwrap();
+    let mut buffer = [0; DATA];
+    assert_eq!(slots.info(0), Err(SaveError::Corrupted));
+    assert_eq!(slots.read(0, &mut buffer), Err(SaveError::Corrupted));
+    // Writing again starts the slot over.
+    let info = slots.write(0, 1, b"fresh").unwrap();
+    assert_eq!(info.sequence, 1);
+    assert_eq!(slots.read(0, &mut buffer), Ok(Some(info)));
+  }
+
+  #[test]
+  fn sequence_wraps() {
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let mut slots = slots(&mut bytes);
+    let old = SlotInfo { version: 1, len: 3, sequence: u32::MAX };
+    let new = SlotInfo { version: 1, len: 3, sequence: 0 };
+    put_copy(&mut slots, 1, old, b"old");
+    put_copy(&mut slots, 0, new, b"new");
+    let mut buffer = [0; DATA];
+    assert_eq!(slots.read(0, &mut buffer), Ok(Some(new)));
+    assert_eq!(&buffer[..3], b"new");
+    // The next write replaces the older copy.
+    let info = slots.write(0, 1, b"next").unwrap();
+    assert_eq!(info.sequence, 1);
+    assert_eq!(slots.newest(0), Ok(Some((1, info))));
+  }
+
+  #[test]
+  fn oversize_is_out_of_bounds() {
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let mut slots = slots(&mut bytes);
+    assert_eq!(slots.write(0, 1, &[0; DATA + 1]), Err(SaveError::OutOfBounds));
+    assert_eq!(slots.info(0), Ok(None));
+    slots.write(0, 1, &[0; DATA]).unwrap();
+    let mut small = [0; DATA - 1];
+    assert_eq!(slots.read(0, &mut small), Err(SaveError::OutOfBounds));
+    // Slots that don't fit in the memory.
+    let mut bytes = [0xFF; 4 * SECTOR];
+    let memory = SliceMemory::new(&mut bytes, SECTOR);
+    assert_eq!(
+      SaveSlots::new(memory, 3, 2, DATA).map(|_| ()),
+      Err(SaveError::OutOfBounds)
+    );
+  }
+}
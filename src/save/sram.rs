@@ -0,0 +1,70 @@
+use crate::{rt0::gba_memcpy_sram, t32_bx_r3};
+
+use super::{check_bounds, check_sectors, SaveError, SaveMemory, SAVE_MEMORY};
+
+/// 32K of battery backed SRAM.
+///
+/// SRAM is on an 8-bit bus, so it must only be accessed one byte at a time.
+/// This driver copies with `gba_memcpy_sram`, which does that.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sram;
+
+impl Sram {
+  /// The size of SRAM, in bytes.
+  pub const SIZE: usize = 32 * 1024;
+
+  /// Makes the driver.
+  ///
+  /// SRAM doesn't need any setup, and there's no way to check if the
+  /// cartridge actually has SRAM.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self
+  }
+}
+
+impl SaveMemory for Sram {
+  #[inline]
+  fn size(&self) -> usize {
+    Self::SIZE
+  }
+
+  #[inline]
+  fn sector_size(&self) -> usize {
+    1
+  }
+
+  #[inline]
+  fn read(
+    &mut self, offset: usize, buffer: &mut [u8],
+  ) -> Result<(), SaveError> {
+    check_bounds(offset, buffer.len(), Self::SIZE)?;
+    let src = (SAVE_MEMORY + offset) as *const u8;
+    unsafe {
+      t32_bx_r3(buffer.as_mut_ptr(), src, buffer.len(), gba_memcpy_sram)
+    };
+    Ok(())
+  }
+
+  #[inline]
+  fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), SaveError> {
+    check_bounds(offset, data.len(), Self::SIZE)?;
+    let dest = (SAVE_MEMORY + offset) as *mut u8;
+    unsafe { t32_bx_r3(dest, data.as_ptr(), data.len(), gba_memcpy_sram) };
+    Ok(())
+  }
+
+  #[inline]
+  fn erase(&mut self, offset: usize, len: usize) -> Result<(), SaveError> {
+    check_sectors(offset, len, Self::SIZE, 1)?;
+    let blank = [0xFF_u8; 32];
+    let mut done = 0;
+    while done < len {
+      let n = (len - done).min(blank.len());
+      self.write(offset + done, &blank[..n])?;
+      done += n;
+    }
+    Ok(())
+  }
+}
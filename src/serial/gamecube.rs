@@ -0,0 +1,123 @@
+//! A host side simulation of the JOY Bus.
+//!
+//! Nothing in this file touches the hardware, so it can be used to test a JOY
+//! Bus responder on the host.
+
+use super::{JoyBusPort, JoyControl, JoyStatus, JOY_DEVICE_GBA};
+
+/// A simulated GameCube, connected to simulated GBA JOY Bus registers.
+///
+/// The GameCube side is the command methods
+/// ([send_reset](GameCubeSim::send_reset),
+/// [send_status](GameCubeSim::send_status),
+/// [send_read](GameCubeSim::send_read), and
+/// [send_write](GameCubeSim::send_write)), which return the bytes that the GBA
+/// hardware would reply with. The GBA side is the [JoyBusPort] impl, which can
+/// be passed to [joybus_respond](super::joybus_respond) whenever
+/// [irq_pending](GameCubeSim::irq_pending) says that the GBA would get an
+/// interrupt.
+///
+/// This is only available with the `sim` cargo feature (or in the crate's own
+/// tests).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameCubeSim {
+  control: JoyControl,
+  status: JoyStatus,
+  recv: u32,
+  trans: u32,
+}
+
+impl GameCubeSim {
+  /// Makes a simulation with all of the registers clear.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      control: JoyControl::new(),
+      status: JoyStatus::new(),
+      recv: 0,
+      trans: 0,
+    }
+  }
+
+  /// If the GBA would have a serial interrupt waiting.
+  #[inline]
+  #[must_use]
+  pub const fn irq_pending(&self) -> bool {
+    self.control.irq() && self.control.0 & 0b111 != 0
+  }
+
+  /// Sets the general purpose bits of the GBA's [JOYSTAT](super::JOYSTAT).
+  #[inline]
+  pub fn set_flags(&mut self, flags: u16) {
+    self.status = self.status.with_flags(flags);
+  }
+
+  /// The GameCube sends a Reset command (`0xFF`).
+  ///
+  /// **Returns:** The device type (big-endian), and then `JOYSTAT`.
+  pub fn send_reset(&mut self) -> [u8; 3] {
+    self.control = self.control.with_reset(true);
+    self.device_reply()
+  }
+
+  /// The GameCube sends a Status command (`0x00`).
+  ///
+  /// **Returns:** The device type (big-endian), and then `JOYSTAT`.
+  pub fn send_status(&mut self) -> [u8; 3] {
+    self.device_reply()
+  }
+
+  /// The GameCube sends a Read command (`0x14`).
+  ///
+  /// **Returns:** The GBA's [JOY_TRANS](super::JOY_TRANS) (little-endian), and
+  /// then `JOYSTAT` as it was before the read.
+  pub fn send_read(&mut self) -> [u8; 5] {
+    let [a, b, c, d] = self.trans.to_le_bytes();
+    let status = self.status.0 as u8;
+    self.status = self.status.with_send(false);
+    self.control = self.control.with_sent(true);
+    [a, b, c, d, status]
+  }
+
+  /// The GameCube sends a Write command (`0x15`) with a value.
+  ///
+  /// **Returns:** `JOYSTAT`, after the write.
+  pub fn send_write(&mut self, value: u32) -> u8 {
+    self.recv = value;
+    self.status = self.status.with_recv(true);
+    self.control = self.control.with_received(true);
+    self.status.0 as u8
+  }
+
+  fn device_reply(&self) -> [u8; 3] {
+    let [hi, lo] = JOY_DEVICE_GBA.to_be_bytes();
+    [hi, lo, self.status.0 as u8]
+  }
+}
+
+impl JoyBusPort for GameCubeSim {
+  #[inline]
+  fn control(&self) -> JoyControl {
+    self.control
+  }
+  #[inline]
+  fn set_control(&mut self, control: JoyControl) {
+    let flags = self.control.0 & !control.0 & 0b111;
+    self.control = JoyControl(flags | control.0 & !0b111);
+  }
+  #[inline]
+  fn status(&self) -> JoyStatus {
+    self.status
+  }
+  #[inline]
+  fn read_recv(&mut self) -> u32 {
+    self.status = self.status.with_recv(false);
+    self.recv
+  }
+  #[inline]
+  fn write_trans(&mut self, value: u32) {
+    self.trans = value;
+    self.status = self.status.with_send(true);
+  }
+}
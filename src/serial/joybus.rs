@@ -0,0 +1,321 @@
+#[cfg(target_arch = "arm")]
+use crate::interrupts::{free, GbaCell, SpscQueue};
+
+#[cfg(target_arch = "arm")]
+use super::{start_serial_irq, stop_serial_irq, ModeSelect, RCNT};
+use super::{JoyControl, JoyStatus, JOYCNT, JOYSTAT, JOY_RECV, JOY_TRANS};
+
+#[cfg(target_arch = "arm")]
+static JOY_RX: SpscQueue<u32, 16> = SpscQueue::new();
+#[cfg(target_arch = "arm")]
+static JOY_TX: SpscQueue<u32, 16> = SpscQueue::new();
+#[cfg(target_arch = "arm")]
+static JOY_RESETS: GbaCell<u16> = GbaCell::new(0);
+#[cfg(target_arch = "arm")]
+static JOY_ERRORS: GbaCell<u16> = GbaCell::new(0);
+
+/// The GBA side of the JOY Bus registers.
+///
+/// [JoyBusRegisters] is the real hardware, and `GameCubeSim` (with the `sim`
+/// cargo feature) simulates it (and a GameCube) on the host, so that
+/// [joybus_respond] can be tested without a GameCube.
+pub trait JoyBusPort {
+  /// Reads [JOYCNT].
+  fn control(&self) -> JoyControl;
+  /// Writes [JOYCNT]. Flags written as 1 are cleared.
+  fn set_control(&mut self, control: JoyControl);
+  /// Reads [JOYSTAT].
+  fn status(&self) -> JoyStatus;
+  /// Reads [JOY_RECV], which clears [JoyStatus::recv].
+  fn read_recv(&mut self) -> u32;
+  /// Writes [JOY_TRANS], which sets [JoyStatus::send].
+  fn write_trans(&mut self, value: u32);
+}
+
+/// The JOY Bus hardware registers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JoyBusRegisters;
+
+impl JoyBusPort for JoyBusRegisters {
+  #[inline]
+  fn control(&self) -> JoyControl {
+    JOYCNT.read()
+  }
+  #[inline]
+  fn set_control(&mut self, control: JoyControl) {
+    JOYCNT.write(control)
+  }
+  #[inline]
+  fn status(&self) -> JoyStatus {
+    JOYSTAT.read()
+  }
+  #[inline]
+  fn read_recv(&mut self) -> u32 {
+    JOY_RECV.read()
+  }
+  #[inline]
+  fn write_trans(&mut self, value: u32) {
+    JOY_TRANS.write(value)
+  }
+}
+
+/// Responds to the JOY Bus commands that have happened.
+///
+/// The hardware answers the GameCube's commands by itself, so responding just
+/// means taking each received value, loading the next value to send once the
+/// GameCube has read the last one, and clearing the flags.
+///
+/// * A received value is passed to `on_recv`.
+/// * If [JOY_TRANS] is free, `next_send` is called for the value to load.
+///
+/// **Returns:** The flags that were handled.
+pub fn joybus_respond<P: JoyBusPort + ?Sized>(
+  port: &mut P, mut on_recv: impl FnMut(u32),
+  next_send: impl FnOnce() -> Option<u32>,
+) -> JoyControl {
+  let events = port.control();
+  if events.received() {
+    on_recv(port.read_recv());
+  }
+  if !port.status().send() {
+    if let Some(value) = next_send() {
+      port.write_trans(value);
+    }
+  }
+  // Writing back the flags that were read clears exactly those flags, and
+  // keeps the other settings.
+  port.set_control(events);
+  events
+}
+
+/// An interrupt driven JOY Bus responder, for talking to a GameCube.
+///
+/// In JOY Bus mode the GameCube sends all of the commands, and the GBA
+/// hardware answers them:
+///
+/// * Reset (`0xFF`) and Status (`0x00`): The GBA's device type and [JOYSTAT].
+/// * Read (`0x14`): The value in [JOY_TRANS].
+/// * Write (`0x15`): The GameCube's value goes into [JOY_RECV].
+///
+/// The driver puts each value that the GameCube writes into a receive queue,
+/// and loads queued values into `JOY_TRANS` for the GameCube to read.
+#[cfg(target_arch = "arm")]
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct JoyBus {
+  _private: (),
+}
+
+#[cfg(target_arch = "arm")]
+impl JoyBus {
+  /// Puts the serial port in JOY Bus mode, and starts the driver.
+  ///
+  /// This sets the serial interrupt handler, and enables the serial interrupt
+  /// in [`IE`](crate::interrupts::IE).
+  pub fn start() -> Self {
+    stop_serial_irq();
+    while JOY_RX.pop().is_some() {}
+    while JOY_TX.pop().is_some() {}
+    JOY_RESETS.write(0);
+    JOY_ERRORS.write(0);
+    RCNT.write(ModeSelect::JOY_BUS);
+    JOYCNT.write(
+      JoyControl::new()
+        .with_reset(true)
+        .with_received(true)
+        .with_sent(true)
+        .with_irq(true),
+    );
+    start_serial_irq(joybus_irq_handler);
+    Self { _private: () }
+  }
+
+  /// Queues a value for the GameCube to read.
+  ///
+  /// ## Failure
+  /// * If the send queue is full you get the value back as an error.
+  pub fn send(&self, value: u32) -> Result<(), u32> {
+    JOY_TX.push(value)?;
+    free(|_| {
+      if !JOYSTAT.read().send() {
+        if let Some(value) = JOY_TX.pop() {
+          JOY_TRANS.write(value);
+        }
+      }
+    });
+    Ok(())
+  }
+
+  /// Takes the oldest value that the GameCube wrote.
+  #[inline]
+  pub fn recv(&self) -> Option<u32> {
+    JOY_RX.pop()
+  }
+
+  /// Sets the two general purpose bits of [JOYSTAT], which the GameCube gets
+  /// after every command.
+  #[inline]
+  pub fn set_flags(&self, flags: u16) {
+    JOYSTAT.write(JoyStatus::new().with_flags(flags));
+  }
+
+  /// The number of reset commands from the GameCube.
+  #[inline]
+  #[must_use]
+  pub fn resets(&self) -> u16 {
+    JOY_RESETS.read()
+  }
+
+  /// The number of received values lost because the receive queue was full.
+  #[inline]
+  #[must_use]
+  pub fn errors(&self) -> u16 {
+    JOY_ERRORS.read()
+  }
+
+  /// Stops the driver.
+  ///
+  /// This disables the serial interrupt and clears the serial interrupt
+  /// handler.
+  pub fn stop(self) {
+    stop_serial_irq();
+    JOYCNT.write(JoyControl::new());
+    RCNT.write(ModeSelect::SIO);
+  }
+}
+
+#[cfg(target_arch = "arm")]
+extern "C" fn joybus_irq_handler() {
+  let events = joybus_respond(
+    &mut JoyBusRegisters,
+    |value| {
+      if JOY_RX.push(value).is_err() {
+        JOY_ERRORS.write(JOY_ERRORS.read().wrapping_add(1));
+      }
+    },
+    || JOY_TX.pop(),
+  );
+  if events.reset() {
+    JOY_RESETS.write(JOY_RESETS.read().wrapping_add(1));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serial::{GameCubeSim, JOY_DEVICE_GBA};
+
+  /// A simulation with the interrupt on, like after [JoyBus::start].
+  fn sim() -> GameCubeSim {
+    let mut sim = GameCubeSim::new();
+    sim.set_control(JoyControl::new().with_irq(true));
+    sim
+  }
+
+  /// Responds until the interrupt is handled, like the interrupt handler.
+  fn respond(
+    sim: &mut GameCubeSim, received: &mut Vec<u32>, to_send: &mut Vec<u32>,
+  ) -> JoyControl {
+    assert!(sim.irq_pending());
+    let events = joybus_respond(
+      sim,
+      |value| received.push(value),
+      || (!to_send.is_empty()).then(|| to_send.remove(0)),
+    );
+    assert!(!sim.irq_pending());
+    events
+  }
+
+  fn read_value(sim: &mut GameCubeSim) -> (u32, JoyStatus) {
+    let [a, b, c, d, status] = sim.send_read();
+    (u32::from_le_bytes([a, b, c, d]), JoyStatus(u16::from(status)))
+  }
+
+  #[test]
+  fn reset_and_status_reply_with_the_device_type() {
+    let mut sim = sim();
+    sim.set_flags(0b10);
+    let [hi, lo, status] = sim.send_reset();
+    assert_eq!(u16::from_be_bytes([hi, lo]), JOY_DEVICE_GBA);
+    assert_eq!(JoyStatus(u16::from(status)).flags(), 0b10);
+    let events = respond(&mut sim, &mut Vec::new(), &mut Vec::new());
+    assert!(events.reset());
+    assert!(!events.received() && !events.sent());
+    // Status doesn't raise an interrupt.
+    let [hi, lo, _] = sim.send_status();
+    assert_eq!(u16::from_be_bytes([hi, lo]), JOY_DEVICE_GBA);
+    assert!(!sim.irq_pending());
+  }
+
+  #[test]
+  fn writes_are_received() {
+    let mut sim = sim();
+    let mut received = Vec::new();
+    let status = JoyStatus(u16::from(sim.send_write(0x1234_5678)));
+    assert!(status.recv());
+    let events = respond(&mut sim, &mut received, &mut Vec::new());
+    assert!(events.received());
+    assert_eq!(received, [0x1234_5678]);
+    assert!(!sim.status().recv());
+    sim.send_write(0xCAFE_F00D);
+    respond(&mut sim, &mut received, &mut Vec::new());
+    assert_eq!(received, [0x1234_5678, 0xCAFE_F00D]);
+  }
+
+  #[test]
+  fn reads_get_each_queued_value_once() {
+    let mut sim = sim();
+    let mut to_send = vec![1, 2];
+    // The first value is loaded on any interrupt, such as a reset.
+    sim.send_reset();
+    respond(&mut sim, &mut Vec::new(), &mut to_send);
+    let (value, status) = read_value(&mut sim);
+    assert_eq!(value, 1);
+    assert!(status.send());
+    let events = respond(&mut sim, &mut Vec::new(), &mut to_send);
+    assert!(events.sent());
+    assert_eq!(read_value(&mut sim).0, 2);
+    respond(&mut sim, &mut Vec::new(), &mut to_send);
+    // Nothing left to send, so the next read gets the old value, and the GBA
+    // says that it isn't new.
+    let (value, status) = read_value(&mut sim);
+    assert_eq!(value, 2);
+    assert!(!status.send());
+  }
+
+  #[test]
+  fn full_trans_is_not_overwritten() {
+    let mut sim = sim();
+    sim.write_trans(7);
+    sim.send_write(0);
+    let events = joybus_respond(&mut sim, |_| (), || panic!("JOY_TRANS full"));
+    assert!(events.received());
+    assert_eq!(read_value(&mut sim).0, 7);
+  }
+
+  #[test]
+  fn set_control_clears_only_flags_written_as_1() {
+    let mut sim = sim();
+    sim.send_reset();
+    sim.send_write(0);
+    sim.send_read();
+    let all = JoyControl::new()
+      .with_reset(true)
+      .with_received(true)
+      .with_sent(true)
+      .with_irq(true);
+    assert_eq!(sim.control(), all);
+    // Writing 0 to a flag leaves it set.
+    sim.set_control(JoyControl::new().with_irq(true));
+    assert_eq!(sim.control(), all);
+    // A flag that was set after the handler read JOYCNT stays set.
+    sim.set_control(JoyControl::new().with_received(true).with_irq(true));
+    assert_eq!(sim.control(), all.with_received(false));
+    assert!(sim.irq_pending());
+    // The interrupt enable bit is just written.
+    sim.set_control(JoyControl::new().with_reset(true).with_sent(true));
+    assert_eq!(sim.control(), JoyControl::new());
+    sim.send_write(0);
+    assert!(!sim.irq_pending());
+  }
+}
@@ -0,0 +1,120 @@
+use super::{
+  BaudRate, MultiplayerSerial, Packet, Session, MULTIPLAYER_NO_DATA,
+};
+
+/// Collects the words of a [Packet] from one player.
+#[derive(Debug, Clone, Copy, Default)]
+struct Assembler {
+  words: [u16; 4],
+  len: usize,
+}
+
+impl Assembler {
+  /// Adds a received word.
+  ///
+  /// **Returns:** The packet's words, once all four have arrived.
+  fn push(&mut self, word: u16) -> Option<[u16; 4]> {
+    if word == MULTIPLAYER_NO_DATA {
+      return None;
+    }
+    if word & Packet::START_BIT != 0 {
+      self.words[0] = word;
+      self.len = 1;
+    } else if (1..4).contains(&self.len) {
+      self.words[self.len] = word;
+      self.len += 1;
+      if self.len == 4 {
+        self.len = 0;
+        return Some(self.words);
+      }
+    }
+    None
+  }
+}
+
+/// A [Session] running over the link cable, with [MultiplayerSerial].
+///
+/// Call [update](LinkSession::update) once per frame, after vblank, and then
+/// use [session](LinkSession::session) to take the frames that are ready and
+/// to send and receive messages.
+///
+/// The parent sends a packet every update. Each child sends a packet back when
+/// it gets one from the parent, so the parent's vblank sets the pace. A child
+/// doesn't know its player ID until the first transfer, so its session isn't
+/// made until then.
+#[derive(Debug)]
+pub struct LinkSession {
+  serial: MultiplayerSerial,
+  session: Option<Session>,
+  timeout: u16,
+  assemblers: [Assembler; 4],
+}
+
+impl LinkSession {
+  /// Starts the multiplayer serial driver, and looks for players.
+  ///
+  /// All of the GBAs must use the same baud rate. `timeout` is the number of
+  /// updates without hearing from a player before that player is considered
+  /// disconnected.
+  pub fn start(baud_rate: BaudRate, timeout: u16) -> Self {
+    Self {
+      serial: MultiplayerSerial::start(baud_rate),
+      session: None,
+      timeout,
+      assemblers: [Assembler::default(); 4],
+    }
+  }
+
+  /// Exchanges packets with the other players.
+  ///
+  /// `input` is this player's input for the session's current frame. See
+  /// [Session::next_packet].
+  pub fn update(&mut self, input: u16) {
+    let mut packets = [None; 4];
+    let mut transferred = false;
+    while let Some(values) = self.serial.recv() {
+      transferred = true;
+      for ((packet, assembler), word) in
+        packets.iter_mut().zip(self.assemblers.iter_mut()).zip(values)
+      {
+        if let Some(words) = assembler.push(word) {
+          *packet = Packet::decode(words).or(*packet);
+        }
+      }
+    }
+    let is_parent = self.serial.is_parent();
+    if self.session.is_none() && (is_parent || transferred) {
+      let player_id = if is_parent { 0 } else { self.serial.player_id() as u8 };
+      self.session = Some(Session::new(player_id, self.timeout));
+    }
+    let Some(session) = self.session.as_mut() else {
+      return;
+    };
+    session.receive(&packets);
+    if is_parent || packets[0].is_some() {
+      for word in session.next_packet(input).encode() {
+        // A full queue means the other side has stopped, and the timeout
+        // will catch that.
+        let _ = self.serial.send(word);
+      }
+    }
+  }
+
+  /// The session, once this GBA's player ID is known.
+  #[inline]
+  pub fn session(&mut self) -> Option<&mut Session> {
+    self.session.as_mut()
+  }
+
+  /// The number of serial transfers that had errors.
+  #[inline]
+  #[must_use]
+  pub fn errors(&self) -> u16 {
+    self.serial.errors()
+  }
+
+  /// Stops the serial driver.
+  pub fn stop(self) {
+    self.serial.stop();
+  }
+}
@@ -0,0 +1,363 @@
+#![warn(missing_docs)]
+
+//! Module for the serial port (the link cable).
+//!
+//! The serial port has several modes, and [`SIOCNT`] means something different
+//! in each one, so there's a typed view of it for each mode. There are also
+//! interrupt driven drivers for the common modes:
+//!
+//! * [NormalSerial]: 8-bit or 32-bit transfers between two GBAs (or a GBA and
+//!   another device). One side provides the clock.
+//! * [MultiplayerSerial]: 16-bit transfers between up to four GBAs, where every
+//!   player gets every other player's data.
+//! * [UartSerial]: 8-bit bytes at a standard baud rate, for talking to a PC or
+//!   other serial device.
+//! * [JoyBus]: 32-bit values to and from a GameCube, which sends the commands.
+//!
+//! On top of multiplayer mode, [LinkSession] runs a [Session]: players find
+//! each other, then exchange their inputs every frame so that every GBA runs
+//! the game in lockstep, with a reliable channel for small messages.
+//!
+//! Each driver's interrupt handler moves data between the hardware and queues,
+//! and the main program drains the received data with `recv`. The drivers use
+//! [set_irq_handler](crate::rt0::set_irq_handler) for
+//! [IrqSource::Serial](crate::interrupts::IrqSource::Serial), so only one of
+//! them can be used at a time.
+//!
+//! There's also [send_multiboot] for sending a multiboot program to other
+//! GBAs.
+//!
+//! ## Multiboot Programs
+//!
+//! A multiboot program is sent over the link cable and runs entirely from
+//! EWRAM, so it can be at most 256K. To build one, link with
+//! `gba_multiboot_link_script.ld` instead of the normal linker script. The
+//! same rt0 works for both kinds of program, because the BIOS starts a
+//! multiboot program at the multiboot entry point just after the header.
+
+use voladdress::*;
+
+#[cfg(target_arch = "arm")]
+use crate::{
+  interrupts::{IrqBits, IrqSource, IE},
+  rt0::set_irq_handler,
+};
+
+#[cfg(any(test, feature = "sim"))]
+mod gamecube;
+#[cfg(any(test, feature = "sim"))]
+pub use gamecube::*;
+
+mod joybus;
+pub use joybus::*;
+
+#[cfg(target_arch = "arm")]
+mod link;
+#[cfg(target_arch = "arm")]
+pub use link::*;
+
+#[cfg(target_arch = "arm")]
+mod multiboot;
+#[cfg(target_arch = "arm")]
+pub use multiboot::*;
+
+#[cfg(target_arch = "arm")]
+mod multiplayer;
+#[cfg(target_arch = "arm")]
+pub use multiplayer::*;
+
+#[cfg(target_arch = "arm")]
+mod normal;
+#[cfg(target_arch = "arm")]
+pub use normal::*;
+
+mod session;
+pub use session::*;
+
+#[cfg(target_arch = "arm")]
+mod uart;
+#[cfg(target_arch = "arm")]
+pub use uart::*;
+
+/// "Serial Data 32"
+///
+/// The data of a 32-bit normal mode transfer.
+pub const SIODATA32: VolAddress<u32, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0120) };
+
+/// "Serial Multiplayer Data"
+///
+/// In multiplayer mode, index `i` holds the value sent by player `i` in the
+/// most recent transfer. Player 0 is the parent.
+pub const SIOMULTI: VolBlock<u16, Safe, Safe, 4> =
+  unsafe { VolBlock::new(0x0400_0120) };
+
+/// "Serial Control"
+///
+/// The meaning of the bits depends on the serial mode. See [SIOCNT_NORMAL],
+/// [SIOCNT_MULTI], and [SIOCNT_UART] for each mode's bits.
+pub const SIOCNT: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0128) };
+
+/// [SIOCNT] in normal mode.
+pub const SIOCNT_NORMAL: VolAddress<NormalControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0128) };
+
+/// [SIOCNT] in multiplayer mode.
+pub const SIOCNT_MULTI: VolAddress<MultiplayerControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0128) };
+
+/// [SIOCNT] in UART mode.
+pub const SIOCNT_UART: VolAddress<UartControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0128) };
+
+/// "Serial Multiplayer Send"
+///
+/// The value to send in the next multiplayer mode transfer.
+pub const SIOMLT_SEND: VolAddress<u16, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_012A) };
+
+/// "Serial Data 8"
+///
+/// The data of an 8-bit normal mode transfer, or the UART data. This is the
+/// same address as [SIOMLT_SEND].
+pub const SIODATA8: VolAddress<u8, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_012A) };
+
+/// "Serial Mode Select"
+///
+/// Along with [SIOCNT], this selects the serial mode. It must be
+/// [ModeSelect::SIO] to use the normal, multiplayer, or UART modes.
+pub const RCNT: VolAddress<ModeSelect, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0134) };
+
+/// "JOY Bus Control"
+///
+/// The flags are cleared by writing 1 to them.
+pub const JOYCNT: VolAddress<JoyControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0140) };
+
+/// "JOY Bus Receive"
+///
+/// The last value written by the GameCube. Reading it clears
+/// [JoyStatus::recv].
+pub const JOY_RECV: VolAddress<u32, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0150) };
+
+/// "JOY Bus Transmit"
+///
+/// The value for the GameCube to read next. Writing it sets
+/// [JoyStatus::send].
+pub const JOY_TRANS: VolAddress<u32, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0154) };
+
+/// The device type that a GBA answers the GameCube's Reset and Status
+/// commands with.
+pub const JOY_DEVICE_GBA: u16 = 0x0004;
+
+/// "JOY Bus Status"
+///
+/// This is also sent to the GameCube after every command.
+pub const JOYSTAT: VolAddress<JoyStatus, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0158) };
+
+/// The serial mode select bits, and the general purpose mode pins.
+///
+/// In general purpose mode, each of the four pins can be read, and can be set
+/// as an output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ModeSelect(u16);
+
+impl ModeSelect {
+  /// Use the mode selected by [SIOCNT].
+  pub const SIO: Self = Self(0);
+  /// Use the pins directly.
+  pub const GENERAL_PURPOSE: Self = Self(0x8000);
+  /// Use the JOY Bus protocol (for a GameCube).
+  pub const JOY_BUS: Self = Self(0xC000);
+
+  pub_const_fn_new!();
+  u16_bool_field!(0, sc, with_sc);
+  u16_bool_field!(1, sd, with_sd);
+  u16_bool_field!(2, si, with_si);
+  u16_bool_field!(3, so, with_so);
+  u16_bool_field!(4, sc_output, with_sc_output);
+  u16_bool_field!(5, sd_output, with_sd_output);
+  u16_bool_field!(6, si_output, with_si_output);
+  u16_bool_field!(7, so_output, with_so_output);
+  u16_bool_field!(8, si_irq, with_si_irq);
+}
+
+/// A serial baud rate, in bits per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum BaudRate {
+  #[default]
+  Bps9600 = 0,
+  Bps38400 = 1,
+  Bps57600 = 2,
+  Bps115200 = 3,
+}
+
+/// [SIOCNT] settings for normal mode.
+///
+/// * `internal_clock`: This side provides the clock (it's the "master").
+/// * `clock_2mhz`: With the internal clock, use 2MHz instead of 256KHz.
+/// * `si`: (read-only) The other side's SO line. A slave uses this to say that
+///   it's ready.
+/// * `so_inactive`: What to output on SO while not transferring.
+/// * `start`: Set to start a transfer. Reads as set until it's done. A slave
+///   sets this to be ready for the master's next transfer.
+/// * `transfer_32bit`: Use 32-bit transfers with [SIODATA32], instead of 8-bit
+///   transfers with [SIODATA8].
+/// * `irq`: Send an interrupt when a transfer is done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct NormalControl(u16);
+
+impl NormalControl {
+  pub_const_fn_new!();
+  u16_bool_field!(0, internal_clock, with_internal_clock);
+  u16_bool_field!(1, clock_2mhz, with_clock_2mhz);
+  u16_bool_field!(2, si, with_si);
+  u16_bool_field!(3, so_inactive, with_so_inactive);
+  u16_bool_field!(7, start, with_start);
+  u16_bool_field!(12, transfer_32bit, with_transfer_32bit);
+  u16_bool_field!(14, irq, with_irq);
+}
+
+/// [SIOCNT] settings for multiplayer mode.
+///
+/// * `is_child`: (read-only) If this GBA isn't the parent (player 0).
+/// * `all_ready`: (read-only) If all of the GBAs are in multiplayer mode.
+/// * `player_id`: (read-only) This GBA's player number, after the first
+///   transfer.
+/// * `error`: (read-only) If the last transfer had an error.
+/// * `start`: The parent sets this to start a transfer. Reads as set until it's
+///   done.
+/// * `irq`: Send an interrupt when a transfer is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct MultiplayerControl(u16);
+
+impl Default for MultiplayerControl {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MultiplayerControl {
+  /// Makes a value with the multiplayer mode bits set.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self(1 << 13)
+  }
+  unsafe_u16_enum_field!(0 - 1: BaudRate, baud_rate, with_baud_rate);
+  u16_bool_field!(2, is_child, with_is_child);
+  u16_bool_field!(3, all_ready, with_all_ready);
+  u16_val_field!(4 - 5, player_id, with_player_id);
+  u16_bool_field!(6, error, with_error);
+  u16_bool_field!(7, start, with_start);
+  u16_bool_field!(14, irq, with_irq);
+}
+
+/// [SIOCNT] settings for UART mode.
+///
+/// * `cts`: Only send while the other side's CTS (on the SC line) is low.
+/// * `parity_odd`: Use odd parity, instead of even.
+/// * `send_full`: (read-only) If there's no room to send another byte.
+/// * `recv_empty`: (read-only) If there are no received bytes.
+/// * `error`: (read-only) If there was a receive error. Reading clears it.
+/// * `data_8bit`: Use 8 data bits, instead of 7.
+/// * `fifo`: Use the 4 byte send and receive FIFOs.
+/// * `parity`: Use a parity bit.
+/// * `send_enable`: Enable sending.
+/// * `recv_enable`: Enable receiving.
+/// * `irq`: Send an interrupt when a byte is received, when there's room to
+///   send, or when there's an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct UartControl(u16);
+
+impl Default for UartControl {
+  #[inline]
+  #[must_use]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl UartControl {
+  /// Makes a value with the UART mode bits set.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self(0b11 << 12)
+  }
+  unsafe_u16_enum_field!(0 - 1: BaudRate, baud_rate, with_baud_rate);
+  u16_bool_field!(2, cts, with_cts);
+  u16_bool_field!(3, parity_odd, with_parity_odd);
+  u16_bool_field!(4, send_full, with_send_full);
+  u16_bool_field!(5, recv_empty, with_recv_empty);
+  u16_bool_field!(6, error, with_error);
+  u16_bool_field!(7, data_8bit, with_data_8bit);
+  u16_bool_field!(8, fifo, with_fifo);
+  u16_bool_field!(9, parity, with_parity);
+  u16_bool_field!(10, send_enable, with_send_enable);
+  u16_bool_field!(11, recv_enable, with_recv_enable);
+  u16_bool_field!(14, irq, with_irq);
+}
+
+/// [JOYCNT] flags and settings.
+///
+/// * `reset`: The GameCube sent a reset command.
+/// * `received`: The GameCube wrote to [JOY_RECV].
+/// * `sent`: The GameCube read [JOY_TRANS].
+/// * `irq`: Send an interrupt when any of the flags is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct JoyControl(u16);
+
+impl JoyControl {
+  pub_const_fn_new!();
+  u16_bool_field!(0, reset, with_reset);
+  u16_bool_field!(1, received, with_received);
+  u16_bool_field!(2, sent, with_sent);
+  u16_bool_field!(6, irq, with_irq);
+}
+
+/// [JOYSTAT] bits.
+///
+/// * `recv`: (read-only) [JOY_RECV] holds a value that the GBA hasn't read.
+/// * `send`: (read-only) [JOY_TRANS] holds a value that the GameCube hasn't
+///   read.
+/// * `flags`: Two bits for the program to use however it likes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct JoyStatus(u16);
+
+impl JoyStatus {
+  pub_const_fn_new!();
+  u16_bool_field!(1, recv, with_recv);
+  u16_bool_field!(3, send, with_send);
+  u16_val_field!(4 - 5, flags, with_flags);
+}
+
+/// Sets the serial interrupt handler, and enables the serial interrupt.
+#[cfg(target_arch = "arm")]
+fn start_serial_irq(handler: extern "C" fn()) {
+  set_irq_handler(IrqSource::Serial, Some(handler));
+  IE.write(IE.read() | IrqBits::SERIAL);
+}
+
+/// Disables the serial interrupt, and clears the handler.
+#[cfg(target_arch = "arm")]
+fn stop_serial_irq() {
+  IE.write(IE.read() & !IrqBits::SERIAL);
+  set_irq_handler(IrqSource::Serial, None);
+}
@@ -0,0 +1,234 @@
+use crate::{
+  bios::{MultiBoot, MultiBootMode, MultiBootParam},
+  video::VCOUNT,
+};
+
+use super::{
+  BaudRate, ModeSelect, MultiplayerControl, RCNT, SIOCNT_MULTI, SIOMLT_SEND,
+  SIOMULTI,
+};
+
+/// The number of halfwords in the header.
+const HEADER_HALFWORDS: u16 = 0x60;
+
+/// The palette of the logo animation on the clients.
+///
+/// This is `0x81 + color * 0x10 + direction * 8 + speed * 2`.
+const PALETTE_DATA: u8 = 0x81;
+
+/// How many times to search for clients before giving up. Each attempt takes
+/// about 1/16th of a second.
+const SEARCH_ATTEMPTS: u8 = 32;
+
+/// How many times to wait for the clients to send their data before giving up.
+const HANDSHAKE_ATTEMPTS: u16 = 1024;
+
+/// How long to wait for a transfer to finish before giving up.
+const TRANSFER_TIMEOUT: u32 = 0x1_0000;
+
+/// A step of [send_multiboot], for progress reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MultiBootProgress {
+  /// Looking for clients.
+  Searching {
+    /// Counts up from 0 with each attempt.
+    attempt: u8,
+  },
+  /// Sending the header.
+  Header {
+    /// How many of the `0x60` header halfwords have been sent.
+    sent: u8,
+  },
+  /// Waiting for the clients to send their handshake data.
+  Handshake,
+  /// The BIOS is sending the rest of the program. This step takes a few
+  /// seconds.
+  Transfer {
+    /// The clients being sent to (bits 1 to 3).
+    clients: u8,
+  },
+}
+
+/// An error from [send_multiboot].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MultiBootError {
+  /// The image isn't 4-byte aligned, or it isn't a size that can be sent.
+  BadImage,
+  /// This GBA isn't the parent (player 0), or no clients answered.
+  NoClients,
+  /// A client stopped answering, or answered wrong.
+  ClientLost,
+  /// The BIOS reported that the transfer failed.
+  TransferFailed,
+}
+
+/// Sends a multiboot program to the other GBAs on the link cable.
+///
+/// The image is a whole multiboot program, starting with its header, such as
+/// the output of `gba-rom` for a program linked with
+/// `gba_multiboot_link_script.ld`. The image must be 4-byte aligned, between
+/// `0x1C0` and 256K bytes long, and a multiple of 16 bytes long.
+///
+/// This GBA must be the parent (the GBA on the purple end of the cable), and
+/// the clients should be at their boot screen. This uses multiplayer mode, so
+/// it can send to up to three clients at once. The `progress` function is
+/// called as each step starts, and for each header halfword.
+///
+/// After the header is sent, the BIOS [MultiBoot] function sends the rest of
+/// the program, and it doesn't return until it's done.
+///
+/// **Returns:** Which clients (bits 1 to 3) were sent the program.
+///
+/// The image usually comes from `include_bytes!`, which doesn't align the
+/// data, so put it in an aligned wrapper type:
+/// ```no_run
+/// # use gba2k::serial::*;
+/// #[repr(C, align(4))]
+/// struct Aligned<T: ?Sized>(T);
+/// # const DEMO_BYTES: [u8; 0x1C0] = [0; 0x1C0];
+/// // static DEMO: &Aligned<[u8]> = &Aligned(*include_bytes!("demo.gba"));
+/// static DEMO: &Aligned<[u8]> = &Aligned(DEMO_BYTES);
+///
+/// let result = send_multiboot(&DEMO.0, |step| {
+///   // show the step on the screen
+/// });
+/// ```
+pub fn send_multiboot(
+  image: &[u8], mut progress: impl FnMut(MultiBootProgress),
+) -> Result<u8, MultiBootError> {
+  if !(image.as_ptr() as usize).is_multiple_of(4)
+    || image.len() < 0x1C0
+    || image.len() > 0x4_0000
+    || !image.len().is_multiple_of(16)
+  {
+    return Err(MultiBootError::BadImage);
+  }
+
+  RCNT.write(ModeSelect::SIO);
+  SIOCNT_MULTI
+    .write(MultiplayerControl::new().with_baud_rate(BaudRate::Bps115200));
+
+  // Look for clients. Each one answers with `0x720x`, where `x` is its
+  // client bit.
+  let mut clients = 0_u8;
+  for attempt in 0..SEARCH_ATTEMPTS {
+    progress(MultiBootProgress::Searching { attempt });
+    if SIOCNT_MULTI.read().is_child() {
+      return Err(MultiBootError::NoClients);
+    }
+    for _ in 0..15 {
+      if let Ok(replies) = exchange(0x6200) {
+        for reply in replies {
+          if reply & 0xFFF1 == 0x7200 {
+            clients |= reply as u8;
+          }
+        }
+      }
+    }
+    if clients != 0 {
+      break;
+    }
+    wait_frames(4);
+  }
+  if clients == 0 {
+    return Err(MultiBootError::NoClients);
+  }
+  let client_bits = [2_u8, 4, 8];
+  let all_reply_low = |replies: [u16; 3], low: u16| {
+    client_bits.iter().zip(replies).all(|(&bit, reply)| {
+      clients & bit == 0 || reply & 0xFF == low | u16::from(bit)
+    })
+  };
+
+  exchange(0x6100 | u16::from(clients))?;
+
+  // Each client answers each header halfword with `NN0x`, where `NN` counts
+  // down.
+  for i in 0..HEADER_HALFWORDS {
+    progress(MultiBootProgress::Header { sent: i as u8 });
+    let i = usize::from(i) * 2;
+    let halfword = u16::from_le_bytes([image[i], image[i + 1]]);
+    if !all_reply_low(exchange(halfword)?, 0) {
+      return Err(MultiBootError::ClientLost);
+    }
+  }
+  progress(MultiBootProgress::Header { sent: HEADER_HALFWORDS as u8 });
+  exchange(0x6200)?;
+  exchange(0x6200 | u16::from(clients))?;
+
+  // Each client answers with `73cc` once it's ready, where `cc` is a random
+  // byte from that client.
+  progress(MultiBootProgress::Handshake);
+  let mut client_data = [0xFF_u8; 3];
+  let mut attempts = 0;
+  loop {
+    let replies = exchange(0x6300 | u16::from(PALETTE_DATA))?;
+    let ready = client_bits
+      .iter()
+      .zip(replies)
+      .all(|(&bit, reply)| clients & bit == 0 || reply & 0xFF00 == 0x7300);
+    if ready {
+      for ((&bit, reply), data) in
+        client_bits.iter().zip(replies).zip(client_data.iter_mut())
+      {
+        if clients & bit != 0 {
+          *data = reply as u8;
+        }
+      }
+      break;
+    }
+    attempts += 1;
+    if attempts == HANDSHAKE_ATTEMPTS {
+      return Err(MultiBootError::ClientLost);
+    }
+  }
+  let handshake_data =
+    client_data.iter().fold(0x11_u8, |sum, &data| sum.wrapping_add(data));
+  exchange(0x6400 | u16::from(handshake_data))?;
+  wait_frames(4);
+
+  progress(MultiBootProgress::Transfer { clients });
+  let mut param = MultiBootParam::new();
+  param.handshake_data = handshake_data;
+  param.client_data = client_data;
+  param.palette_data = PALETTE_DATA;
+  param.client_bit = clients;
+  param.boot_srcp = image[0xC0..].as_ptr();
+  param.boot_endp = image.as_ptr_range().end;
+  if unsafe { MultiBoot(&mut param, MultiBootMode::Multiplayer) } {
+    Ok(clients)
+  } else {
+    Err(MultiBootError::TransferFailed)
+  }
+}
+
+/// Sends a halfword as the parent, and gets the halfword from each client.
+fn exchange(send: u16) -> Result<[u16; 3], MultiBootError> {
+  let control = SIOCNT_MULTI.read();
+  if !control.all_ready() {
+    return Err(MultiBootError::ClientLost);
+  }
+  SIOMLT_SEND.write(send);
+  SIOCNT_MULTI.write(control.with_start(true));
+  let mut waited = 0;
+  while SIOCNT_MULTI.read().start() {
+    waited += 1;
+    if waited == TRANSFER_TIMEOUT {
+      return Err(MultiBootError::ClientLost);
+    }
+  }
+  Ok([
+    SIOMULTI.index(1).read(),
+    SIOMULTI.index(2).read(),
+    SIOMULTI.index(3).read(),
+  ])
+}
+
+/// Waits for `n` frames to pass, using the scanline counter.
+fn wait_frames(n: u32) {
+  for _ in 0..n {
+    let start = VCOUNT.read();
+    while VCOUNT.read() == start {}
+    while VCOUNT.read() != start {}
+  }
+}
@@ -0,0 +1,151 @@
+use crate::interrupts::{free, GbaCell, SpscQueue};
+
+use super::{
+  start_serial_irq, stop_serial_irq, BaudRate, ModeSelect, MultiplayerControl,
+  RCNT, SIOCNT, SIOCNT_MULTI, SIOMLT_SEND, SIOMULTI,
+};
+
+/// The value of a player that didn't send anything in a transfer.
+///
+/// Players that aren't connected also read as this value, so don't send it as
+/// real data.
+pub const MULTIPLAYER_NO_DATA: u16 = 0xFFFF;
+
+static MULTI_RX: SpscQueue<[u16; 4], 16> = SpscQueue::new();
+static MULTI_TX: SpscQueue<u16, 16> = SpscQueue::new();
+/// If `SIOMLT_SEND` holds a queued value that hasn't been sent yet.
+static MULTI_LOADED: GbaCell<bool> = GbaCell::new(false);
+static MULTI_ERRORS: GbaCell<u16> = GbaCell::new(0);
+
+/// An interrupt driven multiplayer mode serial driver.
+///
+/// Up to four GBAs are connected, and each transfer sends a 16-bit value from
+/// every player to every player. The parent (player 0, on the purple end of
+/// the cable) starts each transfer, so the parent starts one transfer for each
+/// value that it sends. A child's queued value is sent in the parent's next
+/// transfer.
+///
+/// Every transfer puts one `[u16; 4]` into the receive queue, with the value
+/// from each player. A player that had nothing queued sends
+/// [MULTIPLAYER_NO_DATA]. A parent that wants the children's data without
+/// having any of its own can send `MULTIPLAYER_NO_DATA` itself.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct MultiplayerSerial {
+  _private: (),
+}
+
+impl MultiplayerSerial {
+  /// Puts the serial port in multiplayer mode, and starts the driver.
+  ///
+  /// All of the GBAs must use the same baud rate.
+  ///
+  /// This sets the serial interrupt handler, and enables the serial interrupt
+  /// in [`IE`](crate::interrupts::IE).
+  pub fn start(baud_rate: BaudRate) -> Self {
+    stop_serial_irq();
+    while MULTI_RX.pop().is_some() {}
+    while MULTI_TX.pop().is_some() {}
+    MULTI_ERRORS.write(0);
+    MULTI_LOADED.write(false);
+    RCNT.write(ModeSelect::SIO);
+    SIOCNT_MULTI.write(
+      MultiplayerControl::new().with_baud_rate(baud_rate).with_irq(true),
+    );
+    SIOMLT_SEND.write(MULTIPLAYER_NO_DATA);
+    start_serial_irq(multi_irq_handler);
+    Self { _private: () }
+  }
+
+  /// Queues a value to send.
+  ///
+  /// ## Failure
+  /// * If the send queue is full you get the value back as an error.
+  pub fn send(&self, value: u16) -> Result<(), u16> {
+    MULTI_TX.push(value)?;
+    free(|_| load_next());
+    Ok(())
+  }
+
+  /// Takes the oldest transfer from the receive queue.
+  ///
+  /// Index `i` is the value from player `i`.
+  #[inline]
+  pub fn recv(&self) -> Option<[u16; 4]> {
+    MULTI_RX.pop()
+  }
+
+  /// If this GBA is the parent (player 0).
+  #[inline]
+  #[must_use]
+  pub fn is_parent(&self) -> bool {
+    !SIOCNT_MULTI.read().is_child()
+  }
+
+  /// If all of the connected GBAs are in multiplayer mode.
+  #[inline]
+  #[must_use]
+  pub fn all_ready(&self) -> bool {
+    SIOCNT_MULTI.read().all_ready()
+  }
+
+  /// This GBA's player number, which is only known after the first transfer.
+  #[inline]
+  #[must_use]
+  pub fn player_id(&self) -> u16 {
+    SIOCNT_MULTI.read().player_id()
+  }
+
+  /// The number of transfers that had errors, or were lost because the
+  /// receive queue was full.
+  #[inline]
+  #[must_use]
+  pub fn errors(&self) -> u16 {
+    MULTI_ERRORS.read()
+  }
+
+  /// Stops the driver.
+  ///
+  /// This disables the serial interrupt and clears the serial interrupt
+  /// handler.
+  pub fn stop(self) {
+    stop_serial_irq();
+    SIOCNT.write(0);
+  }
+}
+
+extern "C" fn multi_irq_handler() {
+  let control = SIOCNT_MULTI.read();
+  let received = [
+    SIOMULTI.index(0).read(),
+    SIOMULTI.index(1).read(),
+    SIOMULTI.index(2).read(),
+    SIOMULTI.index(3).read(),
+  ];
+  if control.error() || MULTI_RX.push(received).is_err() {
+    MULTI_ERRORS.write(MULTI_ERRORS.read().wrapping_add(1));
+  }
+  // The queued value was just sent.
+  MULTI_LOADED.write(false);
+  SIOMLT_SEND.write(MULTIPLAYER_NO_DATA);
+  load_next();
+}
+
+/// Loads the next queued value to send, and the parent starts a transfer.
+///
+/// This pops from the send queue, so it must only run with interrupts off, or
+/// in the interrupt handler.
+fn load_next() {
+  let control = SIOCNT_MULTI.read();
+  if MULTI_LOADED.read() || control.start() {
+    return;
+  }
+  let Some(value) = MULTI_TX.pop() else {
+    return;
+  };
+  SIOMLT_SEND.write(value);
+  MULTI_LOADED.write(true);
+  if !control.is_child() {
+    SIOCNT_MULTI.write(control.with_start(true));
+  }
+}
@@ -0,0 +1,155 @@
+use crate::interrupts::{free, GbaCell, SpscQueue};
+
+use super::{
+  start_serial_irq, stop_serial_irq, ModeSelect, NormalControl, RCNT, SIOCNT,
+  SIOCNT_NORMAL, SIODATA32, SIODATA8,
+};
+
+static NORMAL_RX: SpscQueue<u32, 32> = SpscQueue::new();
+static NORMAL_TX: SpscQueue<u32, 32> = SpscQueue::new();
+/// The control settings, without the start bit.
+static NORMAL_CONTROL: GbaCell<u16> = GbaCell::new(0);
+/// What a slave sends when the master transfers and nothing is queued.
+static NORMAL_IDLE: GbaCell<u32> = GbaCell::new(0);
+static NORMAL_ERRORS: GbaCell<u16> = GbaCell::new(0);
+
+/// Where the clock of a normal mode transfer comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NormalClock {
+  /// The other side provides the clock. This side is the "slave".
+  External,
+  /// This side provides a 256KHz clock. This side is the "master".
+  Internal256K,
+  /// This side provides a 2MHz clock. This side is the "master".
+  Internal2M,
+}
+
+/// The size of each normal mode transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum TransferSize {
+  Bits8,
+  Bits32,
+}
+
+/// An interrupt driven normal mode serial driver.
+///
+/// Every transfer sends a value and receives a value at the same time. The
+/// master starts a transfer for each value that it sends. The slave queues
+/// values to send, and sends the idle value (0 unless you
+/// [set it](NormalSerial::set_idle_value)) when the master starts a transfer
+/// and nothing is queued. Every value received, on either side, goes into the
+/// receive queue.
+///
+/// With 8-bit transfers, only the low 8 bits of each value are sent, and
+/// received values are 0 to 255.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct NormalSerial {
+  _private: (),
+}
+
+impl NormalSerial {
+  /// Puts the serial port in normal mode, and starts the driver.
+  ///
+  /// This sets the serial interrupt handler, and enables the serial interrupt
+  /// in [`IE`](crate::interrupts::IE).
+  pub fn start(clock: NormalClock, size: TransferSize) -> Self {
+    stop_serial_irq();
+    while NORMAL_RX.pop().is_some() {}
+    while NORMAL_TX.pop().is_some() {}
+    NORMAL_ERRORS.write(0);
+    let control = NormalControl::new()
+      .with_internal_clock(clock != NormalClock::External)
+      .with_clock_2mhz(clock == NormalClock::Internal2M)
+      .with_transfer_32bit(size == TransferSize::Bits32)
+      .with_irq(true);
+    NORMAL_CONTROL.write(control.0);
+    RCNT.write(ModeSelect::SIO);
+    SIOCNT_NORMAL.write(control);
+    start_serial_irq(normal_irq_handler);
+    free(|_| load_next());
+    Self { _private: () }
+  }
+
+  /// Queues a value to send.
+  ///
+  /// ## Failure
+  /// * If the send queue is full you get the value back as an error.
+  pub fn send(&self, value: u32) -> Result<(), u32> {
+    NORMAL_TX.push(value)?;
+    free(|_| load_next());
+    Ok(())
+  }
+
+  /// Takes the oldest value from the receive queue.
+  #[inline]
+  pub fn recv(&self) -> Option<u32> {
+    NORMAL_RX.pop()
+  }
+
+  /// Sets what a slave sends when nothing is queued.
+  #[inline]
+  pub fn set_idle_value(&self, value: u32) {
+    NORMAL_IDLE.write(value);
+  }
+
+  /// If a transfer is waiting or in progress.
+  #[inline]
+  #[must_use]
+  pub fn is_busy(&self) -> bool {
+    SIOCNT_NORMAL.read().start()
+  }
+
+  /// The number of received values that were lost because the receive queue
+  /// was full.
+  #[inline]
+  #[must_use]
+  pub fn errors(&self) -> u16 {
+    NORMAL_ERRORS.read()
+  }
+
+  /// Stops the driver.
+  ///
+  /// This disables the serial interrupt and clears the serial interrupt
+  /// handler.
+  pub fn stop(self) {
+    stop_serial_irq();
+    SIOCNT.write(0);
+  }
+}
+
+extern "C" fn normal_irq_handler() {
+  let control = NormalControl(NORMAL_CONTROL.read());
+  let value = if control.transfer_32bit() {
+    SIODATA32.read()
+  } else {
+    u32::from(SIODATA8.read())
+  };
+  if NORMAL_RX.push(value).is_err() {
+    NORMAL_ERRORS.write(NORMAL_ERRORS.read().wrapping_add(1));
+  }
+  load_next();
+}
+
+/// Starts the next transfer, if there isn't one already.
+///
+/// This pops from the send queue, so it must only run with interrupts off, or
+/// in the interrupt handler.
+fn load_next() {
+  let control = NormalControl(NORMAL_CONTROL.read());
+  if SIOCNT_NORMAL.read().start() {
+    return;
+  }
+  let value = match NORMAL_TX.pop() {
+    Some(value) => value,
+    None if !control.internal_clock() => NORMAL_IDLE.read(),
+    None => return,
+  };
+  if control.transfer_32bit() {
+    SIODATA32.write(value);
+  } else {
+    SIODATA8.write(value as u8);
+  }
+  SIOCNT_NORMAL.write(control.with_start(true));
+}
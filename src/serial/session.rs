@@ -0,0 +1,789 @@
+//! The link session protocol.
+//!
+//! Everything in this file is plain Rust with no hardware access, so that
+//! several sessions can be connected together and run on the host.
+
+/// The kind of a [Packet].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum PacketKind {
+  /// "I'm here." Sent while looking for players.
+  Hello = 0,
+  /// The parent starting the game. The `frame` field holds the roster. The
+  /// parent sends these in place of [Game](PacketKind::Game) packets until it
+  /// takes the first frame.
+  Start = 1,
+  /// A frame of the running game.
+  Game = 2,
+  /// "I'm leaving."
+  Bye = 3,
+}
+
+/// The data that each player sends in each exchange.
+///
+/// A packet is sent as four 16-bit words. Each word has 15 bits of data, and
+/// the top bit is set only in the first word, so that the start of a packet
+/// can always be found. The first word also never equals
+/// [MULTIPLAYER_NO_DATA](super::MULTIPLAYER_NO_DATA).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Packet {
+  /// What kind of packet this is.
+  pub kind: PacketKind,
+  /// The sender's current frame (12 bits), or the roster for
+  /// [PacketKind::Start].
+  pub frame: u16,
+  /// The sender's input (10 bits) for `frame`, and for the frame before it.
+  pub inputs: [u16; 2],
+  /// A byte of the reliable message channel, with its sequence number (3
+  /// bits).
+  pub message: Option<(u8, u8)>,
+  /// The sequence number (3 bits) of the last message received from each
+  /// player.
+  pub acks: [u8; 4],
+}
+
+impl Packet {
+  /// The bit set in the first word of a packet.
+  pub const START_BIT: u16 = 0x8000;
+
+  /// Encodes the packet as four words.
+  #[must_use]
+  pub const fn encode(&self) -> [u16; 4] {
+    let (has_message, seq, byte) = match self.message {
+      Some((seq, byte)) => (1, seq, byte),
+      None => (0, 0, 0),
+    };
+    // Bit 14 is left clear, so the first word is never 0xFFFF.
+    let bits: u64 = (self.frame as u64 & 0xFFF)
+      | (self.kind as u64) << 12
+      | (self.inputs[0] as u64 & 0x3FF) << 15
+      | (self.inputs[1] as u64 & 0x3FF) << 25
+      | has_message << 35
+      | (seq as u64 & 0b111) << 36
+      | (byte as u64) << 39
+      | (self.acks[0] as u64 & 0b111) << 47
+      | (self.acks[1] as u64 & 0b111) << 50
+      | (self.acks[2] as u64 & 0b111) << 53
+      | (self.acks[3] as u64 & 0b111) << 56;
+    [
+      Self::START_BIT | (bits & 0x7FFF) as u16,
+      (bits >> 15) as u16 & 0x7FFF,
+      (bits >> 30) as u16 & 0x7FFF,
+      (bits >> 45) as u16 & 0x7FFF,
+    ]
+  }
+
+  /// Decodes a packet from four words.
+  ///
+  /// **Returns:** `None` if the words aren't a packet.
+  #[must_use]
+  pub const fn decode(words: [u16; 4]) -> Option<Self> {
+    if words[0] & 0xC000 != Self::START_BIT
+      || words[1] & Self::START_BIT != 0
+      || words[2] & Self::START_BIT != 0
+      || words[3] & Self::START_BIT != 0
+    {
+      return None;
+    }
+    let bits = (words[0] as u64 & 0x7FFF)
+      | (words[1] as u64) << 15
+      | (words[2] as u64) << 30
+      | (words[3] as u64) << 45;
+    let kind = match (bits >> 12) & 0b11 {
+      0 => PacketKind::Hello,
+      1 => PacketKind::Start,
+      2 => PacketKind::Game,
+      _ => PacketKind::Bye,
+    };
+    let message = if (bits >> 35) & 1 != 0 {
+      Some(((bits >> 36) as u8 & 0b111, (bits >> 39) as u8))
+    } else {
+      None
+    };
+    Some(Self {
+      kind,
+      frame: bits as u16 & 0xFFF,
+      inputs: [(bits >> 15) as u16 & 0x3FF, (bits >> 25) as u16 & 0x3FF],
+      message,
+      acks: [
+        (bits >> 47) as u8 & 0b111,
+        (bits >> 50) as u8 & 0b111,
+        (bits >> 53) as u8 & 0b111,
+        (bits >> 56) as u8 & 0b111,
+      ],
+    })
+  }
+}
+
+/// The state of a [Session].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SessionState {
+  /// Looking for other players. The parent calls
+  /// [start](Session::start) to start the game with the players found.
+  Discovering,
+  /// The game is running in lockstep.
+  Running,
+  /// A player left, or stopped answering.
+  Disconnected {
+    /// The player that left.
+    player: u8,
+  },
+}
+
+/// The inputs of every player for one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameInputs {
+  /// The frame number, counting up from 0 when the game started.
+  pub frame: u16,
+  /// Each player's input, or `None` for players that aren't in the game.
+  pub inputs: [Option<u16>; 4],
+}
+
+/// A small ring buffer, for the message queues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Ring<T: Copy, const N: usize> {
+  items: [T; N],
+  start: usize,
+  len: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+  const fn new(fill: T) -> Self {
+    Self { items: [fill; N], start: 0, len: 0 }
+  }
+  fn push(&mut self, t: T) -> Result<(), T> {
+    if self.len == N {
+      return Err(t);
+    }
+    self.items[(self.start + self.len) % N] = t;
+    self.len += 1;
+    Ok(())
+  }
+  fn pop(&mut self) -> Option<T> {
+    if self.len == 0 {
+      return None;
+    }
+    let t = self.items[self.start];
+    self.start = (self.start + 1) % N;
+    self.len -= 1;
+    Some(t)
+  }
+}
+
+/// The sequence number that means "nothing received yet".
+const NO_ACK: u8 = 0b111;
+
+/// A multiplayer session between up to four consoles.
+///
+/// This is only the protocol. Each exchange, every player sends one [Packet]
+/// and gets the packets that the other players sent. [LinkSession] runs a
+/// session over the link cable, and for testing you can pass packets between
+/// several sessions yourself.
+///
+/// Each exchange:
+/// 1. Pass the packets from the other players to [receive](Session::receive).
+/// 2. Call [take_frame](Session::take_frame) until it returns `None`, and run a
+///    game frame with the inputs from each call.
+/// 3. Send the packet from [next_packet](Session::next_packet).
+///
+/// The game runs in lockstep: a frame is only ready when every player's input
+/// for it has arrived, so every console runs the same frames with the same
+/// inputs. Each packet holds the sender's input for its current frame and the
+/// frame before, so a lost packet doesn't lose an input.
+///
+/// There's also a reliable byte channel. Bytes sent with
+/// [send_message](Session::send_message) arrive at every other player in
+/// order, but only one byte is in flight at a time, so it's for small things
+/// like menu choices.
+///
+/// ```no_run
+/// # use gba2k::serial::*;
+/// // Two consoles, connected on the host.
+/// let mut parent = Session::new(0, 60);
+/// let mut child = Session::new(1, 60);
+/// let mut to_parent = [None; 4];
+/// let mut to_child = [None; 4];
+/// for exchange in 0..100 {
+///   if exchange == 5 {
+///     parent.start();
+///   }
+///   parent.receive(&to_parent);
+///   child.receive(&to_child);
+///   while let Some(frame) = parent.take_frame() {}
+///   while let Some(frame) = child.take_frame() {}
+///   to_child[0] = Some(parent.next_packet(0));
+///   to_parent[1] = Some(child.next_packet(0));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Session {
+  player_id: u8,
+  state: SessionState,
+  /// Players that have been heard from, or the roster once running.
+  players: u8,
+  timeout: u16,
+  /// Exchanges since each player's last packet.
+  silence: [u16; 4],
+  frame: u16,
+  /// If the first frame has been taken. Until then, the parent sends start
+  /// packets.
+  started: bool,
+  /// This player's input for the current frame, and the frame before.
+  local_inputs: [Option<u16>; 2],
+  /// Each player's input for the current frame.
+  inputs: [Option<u16>; 4],
+  /// Inputs for the next frame, from players that are a frame ahead.
+  next_inputs: [Option<u16>; 4],
+  /// Bytes waiting to be sent.
+  tx_queue: Ring<u8, 16>,
+  /// The byte being sent, until every player acks it.
+  tx_current: Option<u8>,
+  tx_seq: u8,
+  /// The last ack of our messages from each player.
+  acks_from: [u8; 4],
+  /// The next sequence number expected from each player.
+  rx_expected: [u8; 4],
+  /// Received bytes, with the player that sent them.
+  rx_queue: Ring<(u8, u8), 32>,
+}
+
+impl Session {
+  /// Makes a session for the player given (0 to 3, where 0 is the parent).
+  ///
+  /// A player that isn't heard from for `timeout` exchanges in a row is
+  /// considered disconnected.
+  ///
+  /// ## Panics
+  /// * The player ID must be less than 4.
+  #[must_use]
+  pub const fn new(player_id: u8, timeout: u16) -> Self {
+    assert!(player_id < 4, "the player ID must be 0 to 3");
+    Self {
+      player_id,
+      state: SessionState::Discovering,
+      players: 1 << player_id,
+      timeout,
+      silence: [0; 4],
+      frame: 0,
+      started: false,
+      local_inputs: [None, Some(0)],
+      inputs: [None; 4],
+      next_inputs: [None; 4],
+      tx_queue: Ring::new(0),
+      tx_current: None,
+      tx_seq: 0,
+      acks_from: [NO_ACK; 4],
+      rx_expected: [0; 4],
+      rx_queue: Ring::new((0, 0)),
+    }
+  }
+
+  /// This console's player ID.
+  #[inline]
+  #[must_use]
+  pub const fn player_id(&self) -> u8 {
+    self.player_id
+  }
+
+  /// The session's state.
+  #[inline]
+  #[must_use]
+  pub const fn state(&self) -> SessionState {
+    self.state
+  }
+
+  /// The players (as bits 0 to 3) that have been found, or that are in the
+  /// game once it's running.
+  #[inline]
+  #[must_use]
+  pub const fn players(&self) -> u8 {
+    self.players
+  }
+
+  /// The frame that the session is waiting for inputs for.
+  #[inline]
+  #[must_use]
+  pub const fn frame(&self) -> u16 {
+    self.frame
+  }
+
+  /// Starts the game with the players found so far.
+  ///
+  /// Only the parent can start the game. The other players start when they
+  /// get the parent's start packet.
+  ///
+  /// **Returns:** If the game was started.
+  pub fn start(&mut self) -> bool {
+    if self.player_id != 0 || self.state != SessionState::Discovering {
+      return false;
+    }
+    self.begin(self.players);
+    true
+  }
+
+  /// Leaves the session. The other players will see this player disconnect.
+  pub fn leave(&mut self) {
+    self.state = SessionState::Disconnected { player: self.player_id };
+  }
+
+  /// Queues a byte on the reliable message channel.
+  ///
+  /// ## Failure
+  /// * If the send queue is full you get the byte back as an error.
+  #[inline]
+  pub fn send_message(&mut self, byte: u8) -> Result<(), u8> {
+    self.tx_queue.push(byte)
+  }
+
+  /// Takes the oldest received message byte, with the player that sent it.
+  #[inline]
+  pub fn recv_message(&mut self) -> Option<(u8, u8)> {
+    self.rx_queue.pop()
+  }
+
+  /// Handles the packets from an exchange.
+  ///
+  /// Index `i` is the packet from player `i`, or `None` if nothing arrived
+  /// from that player. This player's own packet is ignored.
+  pub fn receive(&mut self, packets: &[Option<Packet>; 4]) {
+    for (player, packet) in packets.iter().enumerate() {
+      let player = player as u8;
+      if player == self.player_id {
+        continue;
+      }
+      match packet {
+        Some(packet) => {
+          self.silence[usize::from(player)] = 0;
+          self.handle(player, packet);
+        }
+        None => {
+          let silence = &mut self.silence[usize::from(player)];
+          *silence = silence.saturating_add(1);
+        }
+      }
+    }
+    self.check_timeouts();
+    self.check_message_acks();
+  }
+
+  /// Takes the inputs of the next frame, if every player's input for it has
+  /// arrived.
+  ///
+  /// This player's input for a frame is set by the first
+  /// [next_packet](Session::next_packet) call during that frame.
+  pub fn take_frame(&mut self) -> Option<FrameInputs> {
+    if self.state != SessionState::Running {
+      return None;
+    }
+    let mut inputs = [None; 4];
+    for (player, input) in inputs.iter_mut().enumerate() {
+      if self.players & (1 << player) != 0 {
+        *input = Some(self.inputs[player]?);
+      }
+    }
+    let frame = FrameInputs { frame: self.frame, inputs };
+    self.frame = self.frame.wrapping_add(1);
+    self.started = true;
+    self.local_inputs = [None, self.local_inputs[0]];
+    self.inputs = self.next_inputs;
+    self.next_inputs = [None; 4];
+    Some(frame)
+  }
+
+  /// Makes the packet to send in the next exchange.
+  ///
+  /// `input` is this player's input, such as the bits of
+  /// [`KEYINPUT`](crate::keys::KEYINPUT). Only the low 10 bits are sent.
+  pub fn next_packet(&mut self, input: u16) -> Packet {
+    let input = input & 0x3FF;
+    let (kind, frame) = match self.state {
+      SessionState::Discovering => (PacketKind::Hello, 0),
+      SessionState::Running => {
+        if self.local_inputs[0].is_none() {
+          self.local_inputs[0] = Some(input);
+          self.inputs[usize::from(self.player_id)] = Some(input);
+        }
+        if self.player_id == 0 && !self.started {
+          (PacketKind::Start, u16::from(self.players))
+        } else {
+          (PacketKind::Game, self.frame & 0xFFF)
+        }
+      }
+      SessionState::Disconnected { .. } => (PacketKind::Bye, 0),
+    };
+    if self.tx_current.is_none() {
+      self.tx_current = self.tx_queue.pop();
+    }
+    let mut acks = [NO_ACK; 4];
+    for (ack, expected) in acks.iter_mut().zip(self.rx_expected) {
+      *ack = expected.wrapping_sub(1) & 0b111;
+    }
+    Packet {
+      kind,
+      frame,
+      inputs: [
+        self.local_inputs[0].unwrap_or(0),
+        self.local_inputs[1].unwrap_or(0),
+      ],
+      message: self.tx_current.map(|byte| (self.tx_seq, byte)),
+      acks,
+    }
+  }
+
+  fn begin(&mut self, roster: u8) {
+    self.state = SessionState::Running;
+    self.players = roster;
+    self.frame = 0;
+    self.started = false;
+    self.local_inputs = [None, Some(0)];
+    self.inputs = [None; 4];
+    self.next_inputs = [None; 4];
+  }
+
+  fn handle(&mut self, player: u8, packet: &Packet) {
+    let bit = 1 << player;
+    match (self.state, packet.kind) {
+      (SessionState::Discovering, PacketKind::Hello) => self.players |= bit,
+      (SessionState::Discovering, PacketKind::Start) if player == 0 => {
+        let roster = packet.frame as u8 & 0b1111;
+        if roster & (1 << self.player_id) != 0 {
+          self.begin(roster);
+          self.inputs[0] = Some(packet.inputs[0]);
+        }
+      }
+      (SessionState::Discovering, PacketKind::Bye) => self.players &= !bit,
+      (SessionState::Running, _) if self.players & bit != 0 => {
+        let p = usize::from(player);
+        let current = self.frame & 0xFFF;
+        match packet.kind {
+          PacketKind::Start if !self.started => {
+            self.inputs[p] = Some(packet.inputs[0]);
+          }
+          PacketKind::Game if packet.frame == current => {
+            self.inputs[p] = Some(packet.inputs[0]);
+          }
+          PacketKind::Game if packet.frame == (current + 1) & 0xFFF => {
+            self.inputs[p] = Some(packet.inputs[1]);
+            self.next_inputs[p] = Some(packet.inputs[0]);
+          }
+          PacketKind::Bye => {
+            self.state = SessionState::Disconnected { player };
+            return;
+          }
+          _ => (),
+        }
+        self.acks_from[p] = packet.acks[usize::from(self.player_id)];
+        if let Some((seq, byte)) = packet.message {
+          if seq == self.rx_expected[p]
+            && self.rx_queue.push((player, byte)).is_ok()
+          {
+            self.rx_expected[p] = (seq + 1) & 0b111;
+          }
+        }
+      }
+      _ => (),
+    }
+  }
+
+  fn check_timeouts(&mut self) {
+    for player in 0..4_u8 {
+      let bit = 1 << player;
+      if player == self.player_id
+        || self.players & bit == 0
+        || self.silence[usize::from(player)] < self.timeout
+      {
+        continue;
+      }
+      match self.state {
+        SessionState::Discovering => self.players &= !bit,
+        SessionState::Running => {
+          self.state = SessionState::Disconnected { player };
+        }
+        SessionState::Disconnected { .. } => (),
+      }
+    }
+  }
+
+  /// Moves on to the next message once every other player has it.
+  fn check_message_acks(&mut self) {
+    if self.state != SessionState::Running || self.tx_current.is_none() {
+      return;
+    }
+    let all_acked = (0..4).all(|p| {
+      p == usize::from(self.player_id)
+        || self.players & (1 << p) == 0
+        || self.acks_from[p] == self.tx_seq
+    });
+    if all_acked {
+      self.tx_current = None;
+      self.tx_seq = (self.tx_seq + 1) & 0b111;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Consoles connected in memory. Every packet goes through
+  /// [Packet::encode] and [Packet::decode], like on the link cable.
+  struct Link {
+    sessions: Vec<Session>,
+    sent: [Option<Packet>; 4],
+    frames: Vec<Vec<FrameInputs>>,
+  }
+
+  impl Link {
+    fn new(consoles: u8, timeout: u16) -> Self {
+      Self {
+        sessions: (0..consoles).map(|p| Session::new(p, timeout)).collect(),
+        sent: [None; 4],
+        frames: vec![Vec::new(); usize::from(consoles)],
+      }
+    }
+
+    /// Runs one exchange. `lost(from, to)` says if a packet is lost.
+    fn exchange(&mut self, lost: &mut impl FnMut(usize, usize) -> bool) {
+      for (to, session) in self.sessions.iter_mut().enumerate() {
+        let mut packets = [None; 4];
+        for (from, packet) in packets.iter_mut().enumerate() {
+          if !lost(from, to) {
+            *packet = self.sent[from];
+          }
+        }
+        session.receive(&packets);
+        while let Some(frame) = session.take_frame() {
+          self.frames[to].push(frame);
+        }
+      }
+      for (player, session) in self.sessions.iter_mut().enumerate() {
+        let packet = session.next_packet(input(player, session.frame()));
+        self.sent[player] = Packet::decode(packet.encode());
+      }
+    }
+
+    fn run(&mut self, exchanges: u32) {
+      self.run_lossy(exchanges, &mut |_, _| false);
+    }
+
+    fn run_lossy(
+      &mut self, exchanges: u32, lost: &mut impl FnMut(usize, usize) -> bool,
+    ) {
+      for _ in 0..exchanges {
+        self.exchange(lost);
+      }
+    }
+
+    /// Finds each other, then starts the game.
+    fn start(&mut self) {
+      self.run(3);
+      assert!(self.sessions[0].start());
+      self.run(3);
+    }
+  }
+
+  /// The input that a player uses on a frame.
+  fn input(player: usize, frame: u16) -> u16 {
+    (player as u16 * 0x155).wrapping_add(frame.wrapping_mul(7)) & 0x3FF
+  }
+
+  /// Loses about `percent` of packets, the same way every run.
+  fn loss(percent: u32) -> impl FnMut(usize, usize) -> bool {
+    let mut state = 12345_u32;
+    move |_, _| {
+      state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+      (state >> 16) % 100 < percent
+    }
+  }
+
+  #[test]
+  fn packet_round_trip() {
+    let packet = Packet {
+      kind: PacketKind::Game,
+      frame: 0xABC,
+      inputs: [0x3FF, 0x155],
+      message: Some((5, 0xA5)),
+      acks: [1, 2, 7, 0],
+    };
+    let words = packet.encode();
+    assert_eq!(words[0] & 0xC000, Packet::START_BIT);
+    assert!(words[1..].iter().all(|w| w & Packet::START_BIT == 0));
+    assert_eq!(Packet::decode(words), Some(packet));
+    assert_eq!(Packet::decode([0xFFFF; 4]), None);
+    assert_eq!(Packet::decode([words[1], words[2], words[3], words[0]]), None);
+  }
+
+  #[test]
+  fn discovery_finds_every_player() {
+    let mut link = Link::new(3, 60);
+    link.run(3);
+    for session in &link.sessions {
+      assert_eq!(session.state(), SessionState::Discovering);
+      assert_eq!(session.players(), 0b111);
+    }
+    // Only the parent can start.
+    assert!(!link.sessions[1].start());
+    link.start();
+    for session in &link.sessions {
+      assert_eq!(session.state(), SessionState::Running);
+      assert_eq!(session.players(), 0b111);
+    }
+  }
+
+  #[test]
+  fn start_roster_leaves_out_late_players() {
+    let mut link = Link::new(4, 60);
+    // Player 3 isn't connected yet.
+    link.run_lossy(3, &mut |from, to| from == 3 || to == 3);
+    assert_eq!(link.sessions[0].players(), 0b0111);
+    assert!(link.sessions[0].start());
+    link.run(10);
+    for session in &link.sessions[..3] {
+      assert_eq!(session.state(), SessionState::Running);
+      assert_eq!(session.players(), 0b0111);
+    }
+    assert_eq!(link.sessions[3].state(), SessionState::Discovering);
+    assert!(link.frames[3].is_empty());
+    assert!(link.frames[0].iter().all(|f| f.inputs[3].is_none()));
+  }
+
+  #[test]
+  fn lockstep_frames_survive_lost_packets() {
+    let mut link = Link::new(4, 60);
+    link.start();
+    link.run_lossy(400, &mut loss(25));
+    let frames = &link.frames[0];
+    assert!(frames.len() > 50, "only {} frames", frames.len());
+    for (n, frame) in frames.iter().enumerate() {
+      assert_eq!(frame.frame, n as u16);
+      for (player, &got) in frame.inputs.iter().enumerate() {
+        assert_eq!(got, Some(input(player, frame.frame)));
+      }
+    }
+    // Every console ran the same frames, though some may be a frame behind.
+    for other in &link.frames[1..] {
+      assert!(frames.len().abs_diff(other.len()) <= 1);
+      let n = frames.len().min(other.len());
+      assert_eq!(frames[..n], other[..n]);
+    }
+    for session in &link.sessions {
+      assert_eq!(session.state(), SessionState::Running);
+    }
+  }
+
+  #[test]
+  fn lockstep_continues_past_frame_wrap() {
+    let mut link = Link::new(3, 60);
+    link.start();
+    let mut lost = loss(10);
+    while link.frames[0].len() < 0xFF00 {
+      link.exchange(&mut lost);
+    }
+    // Get every console to the last frame before the frame number wraps.
+    while link.frames.iter().any(|f| f.len() < 0xFFFF) {
+      link.exchange(&mut |_, _| false);
+    }
+    // The parent hears everyone's input for that frame and moves on, but
+    // nobody hears the parent's.
+    link.run_lossy(2, &mut |from, _| from == 0);
+    assert_eq!(link.frames[0].len(), 0x1_0000);
+    assert_eq!(link.frames[1].len(), 0xFFFF);
+    link.run_lossy(500, &mut lost);
+    assert!(link.frames[0].len() > 0x1_0000 + 100);
+    for (n, frame) in link.frames[0].iter().enumerate().skip(0xFFF0) {
+      assert_eq!(frame.frame, n as u16);
+      for (player, &got) in frame.inputs[..3].iter().enumerate() {
+        assert_eq!(got, Some(input(player, frame.frame)));
+      }
+      assert_eq!(frame.inputs[3], None);
+    }
+    // Only the very first packets are start packets.
+    assert!(link.sent.iter().flatten().all(|p| p.kind == PacketKind::Game));
+    for other in &link.frames[1..] {
+      assert!(link.frames[0].len().abs_diff(other.len()) <= 1);
+    }
+  }
+
+  #[test]
+  fn silent_player_times_out() {
+    let mut link = Link::new(2, 30);
+    link.start();
+    link.run_lossy(29, &mut |from, _| from == 1);
+    assert_eq!(link.sessions[0].state(), SessionState::Running);
+    let frames = link.frames[0].len();
+    link.run_lossy(1, &mut |from, _| from == 1);
+    assert_eq!(
+      link.sessions[0].state(),
+      SessionState::Disconnected { player: 1 }
+    );
+    // No frames ran without player 1's inputs.
+    assert_eq!(link.frames[0].len(), frames);
+  }
+
+  #[test]
+  fn silent_player_is_forgotten_while_discovering() {
+    let mut link = Link::new(2, 30);
+    link.run(3);
+    assert_eq!(link.sessions[0].players(), 0b11);
+    link.run_lossy(30, &mut |from, _| from == 1);
+    assert_eq!(link.sessions[0].state(), SessionState::Discovering);
+    assert_eq!(link.sessions[0].players(), 0b01);
+  }
+
+  #[test]
+  fn leaving_sends_bye() {
+    let mut link = Link::new(3, 60);
+    link.start();
+    link.sessions[1].leave();
+    assert_eq!(
+      link.sessions[1].state(),
+      SessionState::Disconnected { player: 1 }
+    );
+    link.run(2);
+    assert_eq!(link.sent[1].map(|p| p.kind), Some(PacketKind::Bye));
+    for session in &link.sessions {
+      assert_eq!(session.state(), SessionState::Disconnected { player: 1 });
+    }
+  }
+
+  #[test]
+  fn messages_arrive_once_in_order() {
+    let mut link = Link::new(3, 60);
+    link.start();
+    let mut lost = loss(30);
+    // 20 bytes wraps the 3-bit sequence number twice.
+    let mut next = 0_u8;
+    let mut got = [Vec::new(), Vec::new(), Vec::new()];
+    for _ in 0..1000 {
+      while next < 20 && link.sessions[0].send_message(next).is_ok() {
+        next += 1;
+      }
+      link.exchange(&mut lost);
+      for (player, got) in got.iter_mut().enumerate() {
+        while let Some(message) = link.sessions[player].recv_message() {
+          got.push(message);
+        }
+      }
+    }
+    let expected: Vec<_> = (0..20).map(|b| (0, b)).collect();
+    assert_eq!(got[1], expected);
+    assert_eq!(got[2], expected);
+    assert!(got[0].is_empty());
+  }
+
+  #[test]
+  fn unacked_message_is_resent_but_received_once() {
+    let mut link = Link::new(2, 60);
+    link.start();
+    link.sessions[0].send_message(42).unwrap();
+    // The child's acks are lost, so the parent keeps sending the byte.
+    for _ in 0..20 {
+      link.exchange(&mut |from, _| from == 1);
+      assert_eq!(link.sent[0].and_then(|p| p.message), Some((0, 42)));
+    }
+    link.run(3);
+    assert_eq!(link.sent[0].and_then(|p| p.message), None);
+    assert_eq!(link.sessions[1].recv_message(), Some((0, 42)));
+    assert_eq!(link.sessions[1].recv_message(), None);
+  }
+}
@@ -0,0 +1,155 @@
+use crate::interrupts::{free, GbaCell, SpscQueue};
+
+use super::{
+  start_serial_irq, stop_serial_irq, BaudRate, ModeSelect, UartControl, RCNT,
+  SIOCNT, SIOCNT_UART, SIODATA8,
+};
+
+static UART_RX: SpscQueue<u8, 64> = SpscQueue::new();
+static UART_TX: SpscQueue<u8, 64> = SpscQueue::new();
+static UART_ERRORS: GbaCell<u16> = GbaCell::new(0);
+
+/// The parity bit setting of a UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum Parity {
+  Even,
+  Odd,
+}
+
+/// Settings for [UartSerial].
+///
+/// Bytes are always 8 data bits and 1 stop bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UartConfig {
+  /// The baud rate.
+  pub baud_rate: BaudRate,
+  /// Only send while the other side allows it, using the CTS line.
+  pub cts: bool,
+  /// The parity bit, if any.
+  pub parity: Option<Parity>,
+  /// Use the hardware's 4 byte FIFOs.
+  pub fifo: bool,
+}
+
+impl UartConfig {
+  /// The settings for a baud rate, with no CTS, no parity, and the FIFOs on.
+  #[inline]
+  #[must_use]
+  pub const fn new(baud_rate: BaudRate) -> Self {
+    Self { baud_rate, cts: false, parity: None, fifo: true }
+  }
+}
+
+/// An interrupt driven UART mode serial driver.
+///
+/// Bytes that are sent go into a send queue, and the driver moves them to the
+/// hardware as there's room. Bytes that are received go into a receive queue.
+/// Calling [send](UartSerial::send) or [recv](UartSerial::recv) also moves
+/// bytes, in case an interrupt was missed.
+#[derive(Debug)]
+#[allow(missing_copy_implementations)]
+pub struct UartSerial {
+  _private: (),
+}
+
+impl UartSerial {
+  /// Puts the serial port in UART mode, and starts the driver.
+  ///
+  /// This sets the serial interrupt handler, and enables the serial interrupt
+  /// in [`IE`](crate::interrupts::IE).
+  pub fn start(config: UartConfig) -> Self {
+    stop_serial_irq();
+    while UART_RX.pop().is_some() {}
+    while UART_TX.pop().is_some() {}
+    UART_ERRORS.write(0);
+    RCNT.write(ModeSelect::SIO);
+    let control = UartControl::new()
+      .with_baud_rate(config.baud_rate)
+      .with_cts(config.cts)
+      .with_parity(config.parity.is_some())
+      .with_parity_odd(config.parity == Some(Parity::Odd))
+      .with_data_8bit(true)
+      .with_send_enable(true)
+      .with_recv_enable(true)
+      .with_irq(true);
+    // The FIFOs are reset by turning them off and then on.
+    SIOCNT_UART.write(control);
+    SIOCNT_UART.write(control.with_fifo(config.fifo));
+    start_serial_irq(uart_irq_handler);
+    Self { _private: () }
+  }
+
+  /// Queues a byte to send.
+  ///
+  /// ## Failure
+  /// * If the send queue is full you get the byte back as an error.
+  pub fn send(&self, byte: u8) -> Result<(), u8> {
+    UART_TX.push(byte)?;
+    free(|_| service());
+    Ok(())
+  }
+
+  /// Queues as many bytes as fit in the send queue.
+  ///
+  /// **Returns:** How many bytes were queued.
+  pub fn send_bytes(&self, bytes: &[u8]) -> usize {
+    let count =
+      bytes.iter().take_while(|&&byte| UART_TX.push(byte).is_ok()).count();
+    free(|_| service());
+    count
+  }
+
+  /// Takes the oldest byte from the receive queue.
+  #[inline]
+  pub fn recv(&self) -> Option<u8> {
+    free(|_| service());
+    UART_RX.pop()
+  }
+
+  /// The number of receive errors (such as a wrong parity bit or stop bit),
+  /// plus the number of bytes lost because the receive queue was full.
+  #[inline]
+  #[must_use]
+  pub fn errors(&self) -> u16 {
+    UART_ERRORS.read()
+  }
+
+  /// Stops the driver.
+  ///
+  /// This disables the serial interrupt and clears the serial interrupt
+  /// handler.
+  pub fn stop(self) {
+    stop_serial_irq();
+    SIOCNT.write(0);
+  }
+}
+
+extern "C" fn uart_irq_handler() {
+  service();
+}
+
+/// Moves received bytes to the receive queue, and queued bytes to the
+/// hardware.
+///
+/// This pushes to the receive queue and pops from the send queue, so it must
+/// only run with interrupts off, or in the interrupt handler.
+fn service() {
+  let mut control = SIOCNT_UART.read();
+  if control.error() {
+    UART_ERRORS.write(UART_ERRORS.read().wrapping_add(1));
+  }
+  while !control.recv_empty() {
+    if UART_RX.push(SIODATA8.read()).is_err() {
+      UART_ERRORS.write(UART_ERRORS.read().wrapping_add(1));
+    }
+    control = SIOCNT_UART.read();
+  }
+  while !control.send_full() {
+    let Some(byte) = UART_TX.pop() else {
+      break;
+    };
+    SIODATA8.write(byte);
+    control = SIOCNT_UART.read();
+  }
+}
@@ -0,0 +1,102 @@
+#![warn(missing_docs)]
+
+//! Module for controlling the GBA's sound.
+//!
+//! Currently this only covers the master sound enable, and which speakers each
+//! channel is mixed into. Support for the tone, wave, noise, and Direct Sound
+//! channels themselves is planned.
+
+use voladdress::*;
+
+/// "Sound Control X"
+///
+/// See [SoundStatus].
+pub const SOUNDCNT_X: VolAddress<SoundStatus, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0084) };
+
+/// The master sound enable, along with if each PSG channel is playing.
+///
+/// The per-channel flags are read-only, and they're ignored by the hardware
+/// when you write a `SoundStatus` value to `SOUNDCNT_X`.
+///
+/// While the `enabled` bit is off, all sound registers other than `SOUNDCNT_X`
+/// are reset to zero and can't be written. If you turn the sound off and then
+/// on again you'll need to set up the rest of the sound registers again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct SoundStatus(u16);
+
+#[allow(missing_docs)]
+impl SoundStatus {
+  pub_const_fn_new!();
+  u16_bool_field!(0, tone1_playing, with_tone1_playing);
+  u16_bool_field!(1, tone2_playing, with_tone2_playing);
+  u16_bool_field!(2, wave_playing, with_wave_playing);
+  u16_bool_field!(3, noise_playing, with_noise_playing);
+  u16_bool_field!(7, enabled, with_enabled);
+}
+
+/// "Sound Control L"
+///
+/// See [PsgMix].
+pub const SOUNDCNT_L: VolAddress<PsgMix, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0080) };
+
+/// The volume of the PSG channels (tone 1, tone 2, wave, and noise) on each
+/// side, and which channels play on each side.
+///
+/// A channel that isn't enabled on either side keeps running, it just isn't
+/// heard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PsgMix(u16);
+
+#[allow(missing_docs)]
+impl PsgMix {
+  pub_const_fn_new!();
+  u16_val_field!(0 - 2, right_volume, with_right_volume);
+  u16_val_field!(4 - 6, left_volume, with_left_volume);
+  u16_bool_field!(8, tone1_right, with_tone1_right);
+  u16_bool_field!(9, tone2_right, with_tone2_right);
+  u16_bool_field!(10, wave_right, with_wave_right);
+  u16_bool_field!(11, noise_right, with_noise_right);
+  u16_bool_field!(12, tone1_left, with_tone1_left);
+  u16_bool_field!(13, tone2_left, with_tone2_left);
+  u16_bool_field!(14, wave_left, with_wave_left);
+  u16_bool_field!(15, noise_left, with_noise_left);
+}
+
+/// "Sound Control H"
+///
+/// See [SoundMix].
+pub const SOUNDCNT_H: VolAddress<SoundMix, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0082) };
+
+/// The overall PSG volume, and the volume, sides, and timer of the two Direct
+/// Sound channels.
+///
+/// * `psg_volume`: 0 is 25%, 1 is 50%, and 2 is 100%.
+/// * `dma_a_full` and `dma_b_full`: 100% volume when set, 50% when clear.
+/// * `dma_a_timer1` and `dma_b_timer1`: The channel takes its samples on timer
+///   1 overflowing when set, or timer 0 when clear.
+/// * `dma_a_reset` and `dma_b_reset`: Writing with these set empties that
+///   channel's FIFO. They always read as clear.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct SoundMix(u16);
+
+#[allow(missing_docs)]
+impl SoundMix {
+  pub_const_fn_new!();
+  u16_val_field!(0 - 1, psg_volume, with_psg_volume);
+  u16_bool_field!(2, dma_a_full, with_dma_a_full);
+  u16_bool_field!(3, dma_b_full, with_dma_b_full);
+  u16_bool_field!(8, dma_a_right, with_dma_a_right);
+  u16_bool_field!(9, dma_a_left, with_dma_a_left);
+  u16_bool_field!(10, dma_a_timer1, with_dma_a_timer1);
+  u16_bool_field!(11, dma_a_reset, with_dma_a_reset);
+  u16_bool_field!(12, dma_b_right, with_dma_b_right);
+  u16_bool_field!(13, dma_b_left, with_dma_b_left);
+  u16_bool_field!(14, dma_b_timer1, with_dma_b_timer1);
+  u16_bool_field!(15, dma_b_reset, with_dma_b_reset);
+}
@@ -0,0 +1,8 @@
+#![warn(missing_docs)]
+
+//! Module for system control settings.
+//!
+//! Currently this is the cartridge bus timing, [`WAITCNT`].
+
+mod wait_control;
+pub use wait_control::*;
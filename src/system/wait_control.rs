@@ -0,0 +1,121 @@
+use voladdress::*;
+
+/// Cartridge wait state control.
+///
+/// See [WaitControl].
+pub const WAITCNT: VolAddress<WaitControl, Safe, Safe> =
+  unsafe { VolAddress::new(0x0400_0204) };
+
+/// The number of wait cycles for the first access to a part of the cartridge
+/// bus (or for any SRAM access).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum WaitCycles {
+  #[default]
+  Cycles4 = 0,
+  Cycles3 = 1,
+  Cycles2 = 2,
+  Cycles8 = 3,
+}
+
+/// The clock output on the cartridge's PHI pin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+#[allow(missing_docs)]
+pub enum PhiOutput {
+  #[default]
+  Off = 0,
+  Mhz4 = 1,
+  Mhz8 = 2,
+  Mhz16 = 3,
+}
+
+/// Timings for the cartridge bus.
+///
+/// ROM is mapped three times, as wait states 0, 1, and 2 (at `0x0800_0000`,
+/// `0x0A00_0000`, and `0x0C00_0000`), and each one has its own timing. A
+/// program normally runs from wait state 0. Wait state 2 is also where EEPROM
+/// save chips are.
+///
+/// Each wait state has a timing for the "first" (random) access, and for
+/// "second" (sequential) accesses after it. The second access is either the
+/// slow setting (2, 4, or 8 cycles, for wait states 0, 1, and 2) or 1 cycle.
+///
+/// * `sram`: The timing of every SRAM and Flash access.
+/// * `ws*_first`: The timing of a first access.
+/// * `ws*_second_fast`: Use 1 cycle for second accesses.
+/// * `phi`: The clock output on the cartridge's PHI pin. Nothing needs this, so
+///   leave it off.
+/// * `prefetch`: Let the prefetch buffer read ROM ahead while the CPU is busy.
+///   This speeds up code running from ROM, especially in loops.
+/// * `cgb_cart`: (read-only) A Game Boy Color cartridge is inserted.
+///
+/// ## Boot Value
+///
+/// The rt0 sets `WAITCNT` to [WaitControl::FAST] before `main`. To use a
+/// different value, either turn on one of the crate's `waitcnt-bios` or
+/// `waitcnt-cart-safe` cargo features, or define the
+/// `__waitcnt_boot_value` symbol anywhere in your program:
+/// ```no_run
+/// core::arch::global_asm! {
+///   ".global __waitcnt_boot_value",
+///   ".set __waitcnt_boot_value, 0x4303",
+/// }
+/// ```
+/// [boot_wait_control](crate::rt0::boot_wait_control) gives the value that
+/// was used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct WaitControl(u16);
+
+impl WaitControl {
+  /// The value that the BIOS leaves: 4/2 cycle ROM, 4 cycle SRAM, and no
+  /// prefetch. Every cartridge works with this, but it's slow.
+  pub const BIOS_DEFAULT: Self = Self::new();
+
+  /// 3/1 cycle ROM with prefetch, and 8 cycle SRAM and wait state 2. This is
+  /// what most commercial games use, and nearly all cartridges support it.
+  pub const FAST: Self = Self::new()
+    .with_sram(WaitCycles::Cycles8)
+    .with_ws0_first(WaitCycles::Cycles3)
+    .with_ws0_second_fast(true)
+    .with_ws2_first(WaitCycles::Cycles8)
+    .with_prefetch(true);
+
+  /// 4/2 cycle ROM with prefetch, and 8 cycle SRAM and wait state 2. This is
+  /// for cartridges (such as some flash carts) that can't keep up with
+  /// [FAST](Self::FAST).
+  pub const CART_SAFE: Self = Self::new()
+    .with_sram(WaitCycles::Cycles8)
+    .with_ws2_first(WaitCycles::Cycles8)
+    .with_prefetch(true);
+
+  pub_const_fn_new!();
+  unsafe_u16_enum_field!(0 - 1: WaitCycles, sram, with_sram);
+  unsafe_u16_enum_field!(2 - 3: WaitCycles, ws0_first, with_ws0_first);
+  u16_bool_field!(4, ws0_second_fast, with_ws0_second_fast);
+  unsafe_u16_enum_field!(5 - 6: WaitCycles, ws1_first, with_ws1_first);
+  u16_bool_field!(7, ws1_second_fast, with_ws1_second_fast);
+  unsafe_u16_enum_field!(8 - 9: WaitCycles, ws2_first, with_ws2_first);
+  u16_bool_field!(10, ws2_second_fast, with_ws2_second_fast);
+  unsafe_u16_enum_field!(11 - 12: PhiOutput, phi, with_phi);
+  u16_bool_field!(14, prefetch, with_prefetch);
+  u16_bool_field!(15, cgb_cart, with_cgb_cart);
+}
+
+impl From<WaitControl> for u16 {
+  #[inline]
+  #[must_use]
+  fn from(w: WaitControl) -> Self {
+    w.0
+  }
+}
+
+impl From<u16> for WaitControl {
+  #[inline]
+  #[must_use]
+  fn from(u: u16) -> Self {
+    Self(u)
+  }
+}
@@ -0,0 +1,136 @@
+//! Reports the memory used by a GBA program.
+//!
+//! ```txt
+//! gba-mem-report [--top N] <ELF_FILE>
+//! ```
+//!
+//! * `--top`: How many of the largest symbols to list per region. Default 10.
+//!
+//! The program must be linked with `gba2k`'s linker script, which exports the
+//! symbols that this reads, including the size of each stack. The exit code is
+//! 1 if the program's IWRAM data plus the stacks don't fit in IWRAM.
+
+use gba2k_tools::{elf::*, parse_number, region_of, Region, EWRAM, IWRAM, ROM};
+use std::process::ExitCode;
+
+/// The BIOS uses the top 0x20 bytes of IWRAM, and the stacks go below that.
+const BIOS_RESERVED: u32 = 0x20;
+
+const USAGE: &str = "usage: gba-mem-report [--top N] <ELF_FILE>";
+
+fn main() -> ExitCode {
+  match run() {
+    Ok(code) => code,
+    Err(msg) => {
+      eprintln!("error: {msg}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn run() -> Result<ExitCode, String> {
+  let mut top = 10;
+  let mut path = None;
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--top" => {
+        top = args.next().as_deref().and_then(parse_number).ok_or(USAGE)?
+      }
+      "-h" | "--help" => {
+        println!("{USAGE}");
+        return Ok(ExitCode::SUCCESS);
+      }
+      _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+      _ => return Err(USAGE.to_string()),
+    }
+  }
+  let path = path.ok_or(USAGE)?;
+  let bytes = std::fs::read(&path).map_err(|e| format!("{path}: {e}"))?;
+  let elf = Elf::parse(&bytes).map_err(|e| format!("{path}: {e}"))?;
+  let symbol = |name: &str| match elf.symbol(name) {
+    Some(s) => Ok(s.value),
+    None => Err(format!(
+      "{path}: no `{name}` symbol, was it linked with gba2k's linker script?"
+    )),
+  };
+  let rom_used = symbol("__rom_used")?;
+  let iwram_used = symbol("__iwram_used")?;
+  let ewram_used = symbol("__ewram_used")?;
+
+  println!("{:<6} {:>10} {:>10} {:>10}", "region", "used", "size", "free");
+  for (region, used) in
+    [(ROM, rom_used), (IWRAM, iwram_used), (EWRAM, ewram_used)]
+  {
+    println!(
+      "{:<6} {:>10} {:>10} {:>10}",
+      region.name,
+      used,
+      region.size,
+      region.size.saturating_sub(used)
+    );
+  }
+
+  // IWRAM usage above counts only data, the stacks are separate.
+  println!();
+  println!("{:<6} {:>10} {:>10}", "stack", "size", "bottom");
+  let mut stacks_total = 0;
+  for (name, size, bottom) in [
+    ("SYS", "__sys_stack_size", "__sys_stack_bottom"),
+    ("IRQ", "__irq_stack_size", "__irq_stack_bottom"),
+    ("SVC", "__svc_stack_size", "__svc_stack_bottom"),
+  ] {
+    let size = symbol(size)?;
+    stacks_total += size;
+    println!("{name:<6} {size:>10} 0x{:08X}", symbol(bottom)?);
+  }
+  let sys_stack_bottom = symbol("__sys_stack_bottom")?;
+  let free = sys_stack_bottom.saturating_sub(IWRAM.start + iwram_used);
+  println!("IWRAM free between the data and the stacks: {free} bytes");
+
+  for region in [ROM, IWRAM, EWRAM] {
+    print_largest_symbols(&elf, region, top);
+  }
+
+  let total = iwram_used + stacks_total + BIOS_RESERVED;
+  if total > IWRAM.size {
+    eprintln!();
+    eprintln!(
+      "error: IWRAM overflow: {iwram_used} bytes of data + {stacks_total} \
+       bytes of stacks + {BIOS_RESERVED} bytes for the BIOS = {total} bytes, \
+       which is more than the {} bytes of IWRAM. Move some statics to EWRAM \
+       with `#[link_section = \".ewram\"]` or \
+       `#[link_section = \".ewram_bss\"]`, or use smaller stacks.",
+      IWRAM.size
+    );
+    return Ok(ExitCode::FAILURE);
+  }
+  Ok(ExitCode::SUCCESS)
+}
+
+fn print_largest_symbols(elf: &Elf<'_>, region: Region, top: u32) {
+  let mut symbols: Vec<&Symbol> = elf
+    .symbols
+    .iter()
+    .filter(|s| {
+      (s.kind == STT_FUNC || s.kind == STT_OBJECT)
+        && s.size > 0
+        && region_of(s.value) == Some(region)
+    })
+    .collect();
+  if symbols.is_empty() || top == 0 {
+    return;
+  }
+  symbols.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+  println!();
+  println!("largest symbols in {}:", region.name);
+  for s in symbols.into_iter().take(top as usize) {
+    // Thumb functions have the low bit of their address set.
+    let addr = if s.kind == STT_FUNC { s.value & !1 } else { s.value };
+    println!(
+      "{:>10} 0x{addr:08X} {:#}",
+      s.size,
+      rustc_demangle::demangle(&s.name)
+    );
+  }
+}
@@ -0,0 +1,114 @@
+//! Turns a GBA program into a `.gba` ROM file.
+//!
+//! ```txt
+//! gba-rom [--save-type TYPE] [--no-pad] <ELF_FILE> [OUTPUT_FILE]
+//! ```
+//!
+//! * `--save-type`: Adds a save type marker so that emulators know what save
+//!   hardware the game uses. This is one of `sram`, `eeprom`, `flash`,
+//!   `flash512`, or `flash1m`, or it can be a full marker string such as
+//!   `FLASH1M_V103`.
+//! * `--no-pad`: Don't pad the ROM to a power of two size.
+//!
+//! A multiboot program (linked with `gba_multiboot_link_script.ld`) is
+//! detected automatically. Its image is padded to a multiple of 16 bytes
+//! instead, and it can't have a save type.
+//!
+//! The output file defaults to the input file with a `.gba` extension. The
+//! header's Nintendo logo, fixed value, and checksum are checked, and any that
+//! are wrong are fixed (with a warning).
+
+use gba2k_tools::{elf::Elf, rom::*, EWRAM};
+use std::{path::PathBuf, process::ExitCode};
+
+const USAGE: &str =
+  "usage: gba-rom [--save-type TYPE] [--no-pad] <ELF_FILE> [OUTPUT_FILE]";
+
+fn main() -> ExitCode {
+  match run() {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(msg) => {
+      eprintln!("error: {msg}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn run() -> Result<(), String> {
+  let mut save_marker = None;
+  let mut pad = true;
+  let mut paths = Vec::new();
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--save-type" => {
+        let save_type = args.next().ok_or(USAGE)?;
+        save_marker = Some(save_marker_for(&save_type)?);
+      }
+      "--no-pad" => pad = false,
+      "-h" | "--help" => {
+        println!("{USAGE}");
+        return Ok(());
+      }
+      _ if paths.len() < 2 && !arg.starts_with('-') => {
+        paths.push(PathBuf::from(arg))
+      }
+      _ => return Err(USAGE.to_string()),
+    }
+  }
+  let input = paths.first().ok_or(USAGE)?;
+  let output = match paths.get(1) {
+    Some(path) => path.clone(),
+    None => input.with_extension("gba"),
+  };
+
+  let bytes =
+    std::fs::read(input).map_err(|e| format!("{}: {e}", input.display()))?;
+  let elf =
+    Elf::parse(&bytes).map_err(|e| format!("{}: {e}", input.display()))?;
+  let (region, mut rom) = flatten(&elf)?;
+  for fix in fix_header(&mut rom) {
+    eprintln!("warning: {fix}, fixed it");
+  }
+  if region == EWRAM {
+    if save_marker.is_some() {
+      return Err("a multiboot program can't have a save type".to_string());
+    }
+    pad_for_multiboot(&mut rom);
+    if rom.len() > EWRAM.size as usize {
+      return Err("the multiboot program is bigger than 256K".to_string());
+    }
+  } else {
+    if let Some(marker) = save_marker {
+      add_save_marker(&mut rom, &marker);
+    }
+    if pad {
+      pad_to_power_of_two(&mut rom);
+    }
+  }
+  std::fs::write(&output, &rom)
+    .map_err(|e| format!("{}: {e}", output.display()))?;
+  println!("wrote {} ({} bytes)", output.display(), rom.len());
+  Ok(())
+}
+
+/// Gets the marker string for a `--save-type` argument.
+fn save_marker_for(save_type: &str) -> Result<String, String> {
+  if let Some((_, marker)) =
+    SAVE_MARKERS.iter().find(|(name, _)| name.eq_ignore_ascii_case(save_type))
+  {
+    return Ok(marker.to_string());
+  }
+  let is_marker = SAVE_MARKERS.iter().any(|(_, marker)| {
+    let prefix = &marker[..marker.find("_V").unwrap()];
+    save_type.starts_with(prefix) && save_type[prefix.len()..].starts_with("_V")
+  });
+  if is_marker {
+    Ok(save_type.to_string())
+  } else {
+    Err(format!(
+      "unknown save type `{save_type}`, use one of: sram, eeprom, flash, \
+       flash512, flash1m"
+    ))
+  }
+}
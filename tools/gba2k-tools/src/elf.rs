@@ -0,0 +1,357 @@
+//! A minimal reader for the 32-bit little-endian ELF files that the linker
+//! makes for the GBA.
+//!
+//! Only the parts that the tools need are parsed: the section headers, the
+//! program headers (segments), and the symbol table.
+
+use std::fmt;
+
+/// Something wrong with the ELF file's data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+  /// The file doesn't start with the ELF magic bytes.
+  NotElf,
+  /// The file is ELF, but not 32-bit little-endian ARM.
+  NotGbaElf,
+  /// Some header or table points outside of the file.
+  Truncated,
+}
+
+impl fmt::Display for ElfError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::NotElf => "not an ELF file",
+      Self::NotGbaElf => "not a 32-bit little-endian ARM ELF file",
+      Self::Truncated => "the ELF file is truncated or corrupted",
+    })
+  }
+}
+
+impl std::error::Error for ElfError {}
+
+/// `sh_type` of a section with no data in the file (such as `.bss`).
+pub const SHT_NOBITS: u32 = 8;
+/// `sh_flags` bit of a section that's in memory when the program runs.
+pub const SHF_ALLOC: u32 = 0x2;
+/// `p_type` of a segment that's loaded into memory.
+pub const PT_LOAD: u32 = 1;
+/// Symbol type of a data object, such as a static.
+pub const STT_OBJECT: u8 = 1;
+/// Symbol type of a function.
+pub const STT_FUNC: u8 = 2;
+
+/// A section header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+  pub name: String,
+  pub kind: u32,
+  pub flags: u32,
+  pub addr: u32,
+  pub offset: u32,
+  pub size: u32,
+}
+
+/// A program header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+  pub kind: u32,
+  pub offset: u32,
+  /// The address that the segment runs at.
+  pub vaddr: u32,
+  /// The address that the segment is stored at. For data that's copied into
+  /// RAM at startup, this is the address in ROM.
+  pub paddr: u32,
+  pub file_size: u32,
+  pub mem_size: u32,
+}
+
+/// An entry in the symbol table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+  pub name: String,
+  pub value: u32,
+  pub size: u32,
+  /// The `STT_*` type of the symbol.
+  pub kind: u8,
+  /// The index of the symbol's section.
+  pub section: u16,
+}
+
+/// A parsed ELF file.
+#[derive(Debug, Clone)]
+pub struct Elf<'a> {
+  bytes: &'a [u8],
+  pub sections: Vec<Section>,
+  pub segments: Vec<Segment>,
+  pub symbols: Vec<Symbol>,
+}
+
+impl<'a> Elf<'a> {
+  /// Parses the bytes of an ELF file.
+  pub fn parse(bytes: &'a [u8]) -> Result<Self, ElfError> {
+    if bytes.get(0..4) != Some(b"\x7FELF") {
+      return Err(ElfError::NotElf);
+    }
+    // 32-bit, little-endian, ARM
+    if bytes.get(4) != Some(&1)
+      || bytes.get(5) != Some(&1)
+      || u16_at(bytes, 18)? != 40
+    {
+      return Err(ElfError::NotGbaElf);
+    }
+    let ph_offset = u32_at(bytes, 28)? as usize;
+    let sh_offset = u32_at(bytes, 32)? as usize;
+    let ph_entry_size = usize::from(u16_at(bytes, 42)?);
+    let ph_count = usize::from(u16_at(bytes, 44)?);
+    let sh_entry_size = usize::from(u16_at(bytes, 46)?);
+    let sh_count = usize::from(u16_at(bytes, 48)?);
+    let sh_names = usize::from(u16_at(bytes, 50)?);
+
+    let mut segments = Vec::with_capacity(ph_count);
+    for i in 0..ph_count {
+      let h = ph_offset + i * ph_entry_size;
+      segments.push(Segment {
+        kind: u32_at(bytes, h)?,
+        offset: u32_at(bytes, h + 4)?,
+        vaddr: u32_at(bytes, h + 8)?,
+        paddr: u32_at(bytes, h + 12)?,
+        file_size: u32_at(bytes, h + 16)?,
+        mem_size: u32_at(bytes, h + 20)?,
+      });
+    }
+
+    // The raw headers are read first, because the section names are in one
+    // of the sections.
+    struct RawSection {
+      name: u32,
+      kind: u32,
+      flags: u32,
+      addr: u32,
+      offset: u32,
+      size: u32,
+      link: u32,
+    }
+    let mut raw = Vec::with_capacity(sh_count);
+    for i in 0..sh_count {
+      let h = sh_offset + i * sh_entry_size;
+      raw.push(RawSection {
+        name: u32_at(bytes, h)?,
+        kind: u32_at(bytes, h + 4)?,
+        flags: u32_at(bytes, h + 8)?,
+        addr: u32_at(bytes, h + 12)?,
+        offset: u32_at(bytes, h + 16)?,
+        size: u32_at(bytes, h + 20)?,
+        link: u32_at(bytes, h + 24)?,
+      });
+    }
+    let names = match raw.get(sh_names) {
+      Some(s) => slice(bytes, s.offset, s.size)?,
+      None => &[],
+    };
+    let mut sections = Vec::with_capacity(sh_count);
+    for s in &raw {
+      sections.push(Section {
+        name: str_at(names, s.name)?,
+        kind: s.kind,
+        flags: s.flags,
+        addr: s.addr,
+        offset: s.offset,
+        size: s.size,
+      });
+    }
+
+    // SHT_SYMTAB
+    let mut symbols = Vec::new();
+    if let Some(table) = raw.iter().find(|s| s.kind == 2) {
+      let strings = match raw.get(table.link as usize) {
+        Some(s) => slice(bytes, s.offset, s.size)?,
+        None => &[],
+      };
+      let table = slice(bytes, table.offset, table.size)?;
+      for entry in table.chunks_exact(16) {
+        symbols.push(Symbol {
+          name: str_at(strings, u32_at(entry, 0)?)?,
+          value: u32_at(entry, 4)?,
+          size: u32_at(entry, 8)?,
+          kind: entry[12] & 0xF,
+          section: u16_at(entry, 14)?,
+        });
+      }
+    }
+
+    Ok(Self { bytes, sections, segments, symbols })
+  }
+
+  /// Finds a symbol by name.
+  #[must_use]
+  pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+    self.symbols.iter().find(|s| s.name == name)
+  }
+
+  /// The data of a segment that's stored in the file.
+  ///
+  /// This is `file_size` bytes long, which can be less than `mem_size`.
+  pub fn segment_data(&self, segment: &Segment) -> Result<&'a [u8], ElfError> {
+    slice(self.bytes, segment.offset, segment.file_size)
+  }
+}
+
+fn slice(bytes: &[u8], offset: u32, size: u32) -> Result<&[u8], ElfError> {
+  let start = offset as usize;
+  bytes.get(start..start + size as usize).ok_or(ElfError::Truncated)
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> Result<u16, ElfError> {
+  match bytes.get(i..i + 2) {
+    Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+    None => Err(ElfError::Truncated),
+  }
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> Result<u32, ElfError> {
+  match bytes.get(i..i + 4) {
+    Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+    None => Err(ElfError::Truncated),
+  }
+}
+
+/// Reads a null-terminated string from a string table.
+fn str_at(strings: &[u8], i: u32) -> Result<String, ElfError> {
+  let rest = strings.get(i as usize..).ok_or(ElfError::Truncated)?;
+  let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+  Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SECTION_NAMES: &[u8] = b"\0.shstrtab\0.strtab\0.symtab\0.text\0";
+  const SYMBOL_NAMES: &[u8] = b"\0main\0BUFFER\0";
+  const TEXT: [u8; 4] = [0x70, 0x47, 0xC0, 0x46];
+
+  fn push_u16(out: &mut Vec<u8>, n: u16) {
+    out.extend_from_slice(&n.to_le_bytes());
+  }
+
+  fn push_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+  }
+
+  fn push_symbol(
+    out: &mut Vec<u8>, name: u32, value: u32, size: u32, kind: u8, section: u16,
+  ) {
+    push_u32(out, name);
+    push_u32(out, value);
+    push_u32(out, size);
+    // Global binding, in the high 4 bits.
+    out.extend_from_slice(&[0x10 | kind, 0]);
+    push_u16(out, section);
+  }
+
+  /// A linked program with one segment in ROM, and a `main` function and a
+  /// `BUFFER` static in its symbol table.
+  fn build_elf() -> Vec<u8> {
+    const HEADER: u32 = 52;
+    const PH: u32 = HEADER;
+    const TEXT_AT: u32 = PH + 32;
+    const NAMES_AT: u32 = TEXT_AT + TEXT.len() as u32;
+    const STRINGS_AT: u32 = NAMES_AT + SECTION_NAMES.len() as u32;
+    const SYMBOLS_AT: u32 = STRINGS_AT + SYMBOL_NAMES.len() as u32;
+    const SH: u32 = SYMBOLS_AT + 3 * 16;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\x7FELF\x01\x01\x01");
+    out.resize(16, 0);
+    push_u16(&mut out, 2); // executable
+    push_u16(&mut out, 40); // ARM
+    push_u32(&mut out, 1);
+    push_u32(&mut out, 0x0800_0000); // entry
+    push_u32(&mut out, PH);
+    push_u32(&mut out, SH);
+    push_u32(&mut out, 0x0500_0000); // flags
+    push_u16(&mut out, HEADER as u16);
+    push_u16(&mut out, 32);
+    push_u16(&mut out, 1);
+    push_u16(&mut out, 40);
+    push_u16(&mut out, 5);
+    push_u16(&mut out, 1); // .shstrtab
+    assert_eq!(out.len(), PH as usize);
+
+    for n in [PT_LOAD, TEXT_AT, 0x0800_0000, 0x0800_0000, 4, 4, 5, 4] {
+      push_u32(&mut out, n);
+    }
+    out.extend_from_slice(&TEXT);
+    out.extend_from_slice(SECTION_NAMES);
+    out.extend_from_slice(SYMBOL_NAMES);
+    push_symbol(&mut out, 0, 0, 0, 0, 0);
+    push_symbol(&mut out, 1, 0x0800_0001, 4, STT_FUNC, 4);
+    push_symbol(&mut out, 6, 0x0300_0000, 0x400, STT_OBJECT, 0xFFF1);
+    assert_eq!(out.len(), SH as usize);
+
+    // name, type, flags, addr, offset, size, link, info, align, entry size
+    let sections: [[u32; 10]; 5] = [
+      [0; 10],
+      [1, 3, 0, 0, NAMES_AT, SECTION_NAMES.len() as u32, 0, 0, 1, 0],
+      [11, 3, 0, 0, STRINGS_AT, SYMBOL_NAMES.len() as u32, 0, 0, 1, 0],
+      [19, 2, 0, 0, SYMBOLS_AT, 3 * 16, 2, 1, 4, 16],
+      [27, 1, SHF_ALLOC | 0x4, 0x0800_0000, TEXT_AT, 4, 0, 0, 4, 0],
+    ];
+    for section in sections {
+      section.into_iter().for_each(|n| push_u32(&mut out, n));
+    }
+    out
+  }
+
+  #[test]
+  fn parses_sections_and_segments() {
+    let bytes = build_elf();
+    let elf = Elf::parse(&bytes).unwrap();
+    let names: Vec<&str> = elf.sections.iter().map(|s| &s.name[..]).collect();
+    assert_eq!(names, ["", ".shstrtab", ".strtab", ".symtab", ".text"]);
+    assert_eq!(elf.sections[4].addr, 0x0800_0000);
+    assert_eq!(elf.sections[4].flags & SHF_ALLOC, SHF_ALLOC);
+    assert_eq!(elf.segments.len(), 1);
+    assert_eq!(elf.segments[0].kind, PT_LOAD);
+    assert_eq!(elf.segment_data(&elf.segments[0]), Ok(&TEXT[..]));
+  }
+
+  #[test]
+  fn parses_symbols() {
+    let bytes = build_elf();
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(elf.symbols.len(), 3);
+    assert_eq!(
+      elf.symbol("main"),
+      Some(&Symbol {
+        name: "main".to_string(),
+        value: 0x0800_0001,
+        size: 4,
+        kind: STT_FUNC,
+        section: 4,
+      })
+    );
+    let buffer = elf.symbol("BUFFER").unwrap();
+    assert_eq!((buffer.value, buffer.size), (0x0300_0000, 0x400));
+    assert_eq!((buffer.kind, buffer.section), (STT_OBJECT, 0xFFF1));
+    assert_eq!(elf.symbol("missing"), None);
+  }
+
+  #[test]
+  fn rejects_bad_files() {
+    let mut bytes = build_elf();
+    assert_eq!(Elf::parse(b"MZ\x90\0").unwrap_err(), ElfError::NotElf);
+    assert_eq!(Elf::parse(&bytes[..30]).unwrap_err(), ElfError::Truncated);
+    // Without the last section header.
+    let len = bytes.len();
+    assert_eq!(
+      Elf::parse(&bytes[..len - 40]).unwrap_err(),
+      ElfError::Truncated
+    );
+    bytes[18] = 3; // x86
+    assert_eq!(Elf::parse(&bytes).unwrap_err(), ElfError::NotGbaElf);
+    bytes[18] = 40;
+    bytes[4] = 2; // 64-bit
+    assert_eq!(Elf::parse(&bytes).unwrap_err(), ElfError::NotGbaElf);
+  }
+}
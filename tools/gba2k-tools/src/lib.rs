@@ -0,0 +1,56 @@
+//! Host-side tools for programs made with `gba2k`.
+//!
+//! Each tool is a binary of this crate. Install them all with
+//!
+//! ```sh
+//! cargo install --path tools/gba2k-tools
+//! ```
+//!
+//! * `gba-mem-report`: Shows how much ROM, IWRAM, and EWRAM a program uses, and
+//!   checks that the stacks fit.
+//! * `gba-rom`: Turns a program into a `.gba` ROM file.
+
+pub mod elf;
+pub mod rom;
+
+/// A region of the GBA's memory map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+  pub name: &'static str,
+  pub start: u32,
+  pub size: u32,
+}
+
+impl Region {
+  /// If an address is within this region.
+  #[must_use]
+  pub const fn contains(&self, addr: u32) -> bool {
+    addr >= self.start && addr - self.start < self.size
+  }
+}
+
+/// The memory regions that a program can be placed in, matching the `MEMORY`
+/// of the linker script.
+pub const REGIONS: [Region; 3] = [ROM, IWRAM, EWRAM];
+
+pub const ROM: Region =
+  Region { name: "ROM", start: 0x0800_0000, size: 32 * 1024 * 1024 };
+pub const IWRAM: Region =
+  Region { name: "IWRAM", start: 0x0300_0000, size: 32 * 1024 };
+pub const EWRAM: Region =
+  Region { name: "EWRAM", start: 0x0200_0000, size: 256 * 1024 };
+
+/// The region that an address is within, if any.
+#[must_use]
+pub fn region_of(addr: u32) -> Option<Region> {
+  REGIONS.into_iter().find(|r| r.contains(addr))
+}
+
+/// Parses a number in decimal, or in hex with a `0x` prefix.
+#[must_use]
+pub fn parse_number(s: &str) -> Option<u32> {
+  match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+    Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16).ok(),
+    None => s.replace('_', "").parse().ok(),
+  }
+}
@@ -0,0 +1,209 @@
+//! Building a `.gba` ROM image out of a linked program.
+
+use crate::{elf::*, region_of, Region, EWRAM, ROM};
+
+/// The Nintendo logo, which must be at `0x04` in the ROM for it to boot.
+pub const NINTENDO_LOGO: [u8; 156] = [
+  0x24, 0xFF, 0xAE, 0x51, 0x69, 0x9A, 0xA2, 0x21, 0x3D, 0x84, 0x82, 0x0A, 0x84,
+  0xE4, 0x09, 0xAD, 0x11, 0x24, 0x8B, 0x98, 0xC0, 0x81, 0x7F, 0x21, 0xA3, 0x52,
+  0xBE, 0x19, 0x93, 0x09, 0xCE, 0x20, 0x10, 0x46, 0x4A, 0x4A, 0xF8, 0x27, 0x31,
+  0xEC, 0x58, 0xC7, 0xE8, 0x33, 0x82, 0xE3, 0xCE, 0xBF, 0x85, 0xF4, 0xDF, 0x94,
+  0xCE, 0x4B, 0x09, 0xC1, 0x94, 0x56, 0x8A, 0xC0, 0x13, 0x72, 0xA7, 0xFC, 0x9F,
+  0x84, 0x4D, 0x73, 0xA3, 0xCA, 0x9A, 0x61, 0x58, 0x97, 0xA3, 0x27, 0xFC, 0x03,
+  0x98, 0x76, 0x23, 0x1D, 0xC7, 0x61, 0x03, 0x04, 0xAE, 0x56, 0xBF, 0x38, 0x84,
+  0x00, 0x40, 0xA7, 0x0E, 0xFD, 0xFF, 0x52, 0xFE, 0x03, 0x6F, 0x95, 0x30, 0xF1,
+  0x97, 0xFB, 0xC0, 0x85, 0x60, 0xD6, 0x80, 0x25, 0xA9, 0x63, 0xBE, 0x03, 0x01,
+  0x4E, 0x38, 0xE2, 0xF9, 0xA2, 0x34, 0xFF, 0xBB, 0x3E, 0x03, 0x44, 0x78, 0x00,
+  0x90, 0xCB, 0x88, 0x11, 0x3A, 0x94, 0x65, 0xC0, 0x7C, 0x63, 0x87, 0xF0, 0x3C,
+  0xAF, 0xD6, 0x25, 0xE4, 0x8B, 0x38, 0x0A, 0xAC, 0x72, 0x21, 0xD4, 0xF8, 0x07,
+];
+
+/// The size of the ROM header.
+pub const HEADER_SIZE: usize = 0xC0;
+
+/// The save type markers that emulators look for, by short name.
+pub const SAVE_MARKERS: [(&str, &str); 5] = [
+  ("sram", "SRAM_V113"),
+  ("eeprom", "EEPROM_V124"),
+  ("flash", "FLASH_V126"),
+  ("flash512", "FLASH512_V131"),
+  ("flash1m", "FLASH1M_V103"),
+];
+
+/// Copies each loaded segment of the program to its place in a ROM image.
+///
+/// Segments are placed by their physical (load) address, so data that's
+/// copied into RAM at startup is placed where the rt0 will copy it from. Any
+/// gaps are filled with `0xFF`.
+///
+/// A normal program is loaded in ROM. A multiboot program (linked with
+/// `gba_multiboot_link_script.ld`) is loaded in EWRAM instead.
+///
+/// **Returns:** The region that the image is loaded in, and the image.
+pub fn flatten(elf: &Elf<'_>) -> Result<(Region, Vec<u8>), String> {
+  let mut segments: Vec<&Segment> = elf
+    .segments
+    .iter()
+    .filter(|s| s.kind == PT_LOAD && s.file_size > 0)
+    .collect();
+  segments.sort_by_key(|s| s.paddr);
+  let region = match segments.first().and_then(|s| region_of(s.paddr)) {
+    Some(region) if region == ROM || region == EWRAM => region,
+    _ => return Err("the program isn't loaded in ROM or EWRAM".to_string()),
+  };
+  let mut rom = Vec::new();
+  for segment in segments {
+    if !region.contains(segment.paddr)
+      || !region.contains(segment.paddr + segment.file_size - 1)
+    {
+      return Err(format!(
+        "a segment is loaded at 0x{:08X}, which is outside of {}",
+        segment.paddr, region.name
+      ));
+    }
+    let data = elf.segment_data(segment).map_err(|e| e.to_string())?;
+    let start = (segment.paddr - region.start) as usize;
+    let end = start + data.len();
+    if rom.len() < end {
+      rom.resize(end, 0xFF);
+    }
+    rom[start..end].copy_from_slice(data);
+  }
+  if rom.len() < HEADER_SIZE {
+    rom.resize(HEADER_SIZE, 0xFF);
+  }
+  Ok((region, rom))
+}
+
+/// The complement check of a ROM's header.
+///
+/// ## Panics
+/// * The ROM must be at least [HEADER_SIZE] bytes.
+#[must_use]
+pub fn header_checksum(rom: &[u8]) -> u8 {
+  let sum = rom[0xA0..=0xBC].iter().fold(0_u8, |sum, &b| sum.wrapping_add(b));
+  0_u8.wrapping_sub(sum.wrapping_add(0x19))
+}
+
+/// Checks the parts of a ROM's header that the BIOS checks, and fixes them.
+///
+/// **Returns:** A description of each thing that had to be fixed.
+///
+/// ## Panics
+/// * The ROM must be at least [HEADER_SIZE] bytes.
+pub fn fix_header(rom: &mut [u8]) -> Vec<&'static str> {
+  let mut fixes = Vec::new();
+  if rom[0x04..0xA0] != NINTENDO_LOGO {
+    rom[0x04..0xA0].copy_from_slice(&NINTENDO_LOGO);
+    fixes.push("the Nintendo logo was missing or wrong");
+  }
+  if rom[0xB2] != 0x96 {
+    rom[0xB2] = 0x96;
+    fixes.push("the fixed value at 0xB2 wasn't 0x96");
+  }
+  let checksum = header_checksum(rom);
+  if rom[0xBD] != checksum {
+    rom[0xBD] = checksum;
+    fixes.push("the header checksum was wrong");
+  }
+  fixes
+}
+
+/// Appends a save type marker (such as `FLASH1M_V103`) to the ROM.
+///
+/// Emulators search the ROM for these strings to pick what save hardware to
+/// emulate. The marker is placed at a 4-byte aligned position, and followed by
+/// zeroes up to the next 4-byte boundary.
+pub fn add_save_marker(rom: &mut Vec<u8>, marker: &str) {
+  rom.resize(rom.len().next_multiple_of(4), 0xFF);
+  rom.extend_from_slice(marker.as_bytes());
+  rom.push(0);
+  rom.resize(rom.len().next_multiple_of(4), 0);
+}
+
+/// Pads the ROM with `0xFF` up to the next power of two in size.
+pub fn pad_to_power_of_two(rom: &mut Vec<u8>) {
+  rom.resize(rom.len().next_power_of_two(), 0xFF);
+}
+
+/// Pads a multiboot image with `0xFF` to a size that can be sent.
+///
+/// That's a multiple of 16 bytes, and at least `0x1C0` bytes.
+pub fn pad_for_multiboot(rom: &mut Vec<u8>) {
+  rom.resize(rom.len().next_multiple_of(16).max(0x1C0), 0xFF);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A ROM with the header fields from `0xA0` to `0xBC` of a retail game.
+  fn retail_header(title: &[u8; 12], code: &[u8; 4]) -> Vec<u8> {
+    let mut rom = vec![0; HEADER_SIZE];
+    rom[0xA0..0xAC].copy_from_slice(title);
+    rom[0xAC..0xB0].copy_from_slice(code);
+    rom[0xB0..0xB2].copy_from_slice(b"01");
+    rom[0xB2] = 0x96;
+    rom
+  }
+
+  #[test]
+  fn checksum_matches_retail_games() {
+    let rom = retail_header(b"POKEMON EMER", b"BPEE");
+    assert_eq!(header_checksum(&rom), 0x72);
+    let rom = retail_header(b"POKEMON FIRE", b"BPRE");
+    assert_eq!(header_checksum(&rom), 0x68);
+  }
+
+  #[test]
+  fn fix_header_fixes_each_part() {
+    let mut rom = retail_header(b"POKEMON EMER", b"BPEE");
+    rom[0xB2] = 0;
+    assert_eq!(fix_header(&mut rom).len(), 3);
+    assert_eq!(rom[0x04..0xA0], NINTENDO_LOGO);
+    assert_eq!(rom[0xB2], 0x96);
+    assert_eq!(rom[0xBD], 0x72);
+    // A fixed header needs no more fixes.
+    assert!(fix_header(&mut rom).is_empty());
+    rom[0xBD] = 0;
+    assert_eq!(fix_header(&mut rom), ["the header checksum was wrong"]);
+  }
+
+  #[test]
+  fn pads_to_power_of_two() {
+    let mut rom = vec![0; 0x1_0001];
+    pad_to_power_of_two(&mut rom);
+    assert_eq!(rom.len(), 0x2_0000);
+    assert!(rom[..0x1_0001].iter().all(|&b| b == 0));
+    assert!(rom[0x1_0001..].iter().all(|&b| b == 0xFF));
+    // Already a power of two.
+    pad_to_power_of_two(&mut rom);
+    assert_eq!(rom.len(), 0x2_0000);
+  }
+
+  #[test]
+  fn save_marker_is_aligned() {
+    let mut rom = vec![0; HEADER_SIZE + 1];
+    add_save_marker(&mut rom, "FLASH1M_V103");
+    // Padding up to 4 bytes, the marker, then a 0 and padding up to 4 bytes.
+    assert_eq!(rom[HEADER_SIZE + 1..HEADER_SIZE + 4], [0xFF; 3]);
+    assert_eq!(&rom[HEADER_SIZE + 4..HEADER_SIZE + 16], b"FLASH1M_V103");
+    assert_eq!(rom[HEADER_SIZE + 16..], [0; 4]);
+    assert_eq!(rom.len(), HEADER_SIZE + 20);
+
+    let mut rom = vec![0; HEADER_SIZE];
+    add_save_marker(&mut rom, "SRAM_V113");
+    assert_eq!(&rom[HEADER_SIZE..HEADER_SIZE + 9], b"SRAM_V113");
+    assert_eq!(rom[HEADER_SIZE + 9..], [0; 3]);
+  }
+
+  #[test]
+  fn pads_for_multiboot() {
+    let mut rom = vec![0; HEADER_SIZE];
+    pad_for_multiboot(&mut rom);
+    assert_eq!(rom.len(), 0x1C0);
+    let mut rom = vec![0; 0x1C1];
+    pad_for_multiboot(&mut rom);
+    assert_eq!(rom.len(), 0x1D0);
+  }
+}